@@ -0,0 +1,481 @@
+//! A per-database glossary kept in a YAML file: what tables and columns mean,
+//! what the codes stored in a column stand for, example queries and known
+//! pitfalls. It covers what `COMMENT ON` can't, and is the only source of
+//! descriptions for SQLite, which has no comments at all.
+//!
+//! ```yaml
+//! description: Billing data for the shop
+//! tables:
+//!   accounts:
+//!     description: One row per customer account
+//!     gotchas:
+//!       - Closed accounts are never deleted; filter on status
+//!     columns:
+//!       acct_typ:
+//!         description: Account type
+//!         values:
+//!           1: personal
+//!           2: business
+//!       opened_at: UTC, not local time
+//! examples:
+//!   - description: Open business accounts
+//!     sql: SELECT * FROM accounts WHERE acct_typ = 2 AND status <> 3
+//! gotchas:
+//!   - Amounts are stored in cents
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::McpSqlError;
+use crate::policy::PerDatabase;
+use crate::suggest::unqualified;
+
+/// Annotations for every database.
+pub type AnnotationSet = PerDatabase<Annotations>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Annotations {
+    /// What the database as a whole holds.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Keyed by table name, optionally schema-qualified.
+    #[serde(default)]
+    pub tables: BTreeMap<String, TableAnnotation>,
+    #[serde(default)]
+    pub examples: Vec<ExampleQuery>,
+    /// Pitfalls that apply across tables.
+    #[serde(default)]
+    pub gotchas: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableAnnotation {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Keyed by column name.
+    #[serde(default)]
+    pub columns: BTreeMap<String, ColumnAnnotation>,
+    #[serde(default)]
+    pub gotchas: Vec<String>,
+}
+
+/// A column's description, either as a plain string or with the meaning of
+/// the values it holds.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnAnnotation {
+    Description(String),
+    Detailed(DetailedColumnAnnotation),
+}
+
+/// The long form of a [`ColumnAnnotation`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DetailedColumnAnnotation {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Meaning of each stored value, e.g. `3: closed`.
+    #[serde(default, deserialize_with = "scalar_keys")]
+    pub values: BTreeMap<String, String>,
+}
+
+impl ColumnAnnotation {
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Self::Description(text) => Some(text),
+            Self::Detailed(detailed) => detailed.description.as_deref(),
+        }
+    }
+
+    pub fn values(&self) -> Option<&BTreeMap<String, String>> {
+        match self {
+            Self::Detailed(detailed) if !detailed.values.is_empty() => Some(&detailed.values),
+            _ => None,
+        }
+    }
+
+    /// The description followed by the value meanings, on one line:
+    /// `Account type (1 = personal, 2 = business)`.
+    pub fn summary(&self) -> Option<String> {
+        let values = self.values().map(|values| {
+            values
+                .iter()
+                .map(|(value, meaning)| format!("{value} = {meaning}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        match (self.description(), values) {
+            (Some(description), Some(values)) => Some(format!("{description} ({values})")),
+            (Some(description), None) => Some(description.to_string()),
+            (None, Some(values)) => Some(values),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Read a map whose keys may be numbers or booleans as well as strings, as
+/// codes stored in a column usually are.
+fn scalar_keys<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = serde_yaml::Mapping::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(key, meaning)| Ok((scalar(key)?, scalar(meaning)?)))
+        .collect()
+}
+
+fn scalar<E: serde::de::Error>(value: serde_yaml::Value) -> Result<String, E> {
+    match value {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        serde_yaml::Value::Null => Ok("null".to_string()),
+        other => Err(E::custom(format!("expected a single value, found {other:?}"))),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExampleQuery {
+    /// The question the query answers.
+    pub description: String,
+    pub sql: String,
+}
+
+/// One match from `Annotations::search`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnnotationHit {
+    /// `database`, `table`, `column`, `value`, `example` or `gotcha`.
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+    /// Higher is better: search terms found in names count double.
+    pub score: usize,
+}
+
+/// Annotations for a database that has none.
+pub static EMPTY: Annotations = Annotations {
+    description: None,
+    tables: BTreeMap::new(),
+    examples: Vec::new(),
+    gotchas: Vec::new(),
+};
+
+impl Annotations {
+    pub fn load(path: &Path) -> Result<Self, McpSqlError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            McpSqlError::Other(format!("Cannot read annotations file {}: {e}", path.display()))
+        })?;
+        Self::parse(&text).map_err(|e| {
+            McpSqlError::Other(format!("Invalid annotations file {}: {e}", path.display()))
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, serde_yaml::Error> {
+        // An empty file is valid and annotates nothing
+        if text.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(text)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.tables.is_empty()
+            && self.examples.is_empty()
+            && self.gotchas.is_empty()
+    }
+
+    /// The annotation for `table`. `users` and `public.users` match each
+    /// other unless both are qualified with different schemas.
+    pub fn table(&self, table: &str) -> Option<&TableAnnotation> {
+        self.tables.get(table).or_else(|| {
+            self.tables
+                .iter()
+                .find(|(key, _)| same_table(key, table))
+                .map(|(_, annotation)| annotation)
+        })
+    }
+
+    pub fn table_comment(&self, table: &str) -> Option<&str> {
+        self.table(table)?.description.as_deref()
+    }
+
+    pub fn column(&self, table: &str, column: &str) -> Option<&ColumnAnnotation> {
+        self.table(table)?.columns.get(column)
+    }
+
+    /// Fill in the `comment` of `list_tables` rows the database left empty.
+    pub fn apply_to_tables(&self, tables: &mut [Value]) {
+        for table in tables {
+            let Some(name) = table.get("table_name").and_then(Value::as_str) else {
+                continue;
+            };
+            if let Some(comment) = self.table_comment(name) {
+                fill_comment(table, comment);
+            }
+        }
+    }
+
+    /// Fill in the `comment` of `describe_table` columns the database left
+    /// empty, and add the meaning of annotated values as `values`.
+    pub fn apply_to_columns(&self, table: &str, columns: &mut [Value]) {
+        for column in columns {
+            let Some(name) = column.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let Some(annotation) = self.column(table, name) else {
+                continue;
+            };
+            if let Some(comment) = annotation.description() {
+                fill_comment(column, comment);
+            }
+            if let (Some(values), Value::Object(map)) = (annotation.values(), &mut *column) {
+                map.insert("values".to_string(), serde_json::json!(values));
+            }
+        }
+    }
+
+    /// Notes for `describe_table` to show above a table's columns: its
+    /// description (unless the database has its own comment) and gotchas.
+    pub fn table_notes(&self, table: &str, db_comment: Option<&str>) -> Vec<String> {
+        let annotation = self.table(table);
+        let description = db_comment.or(annotation.and_then(|a| a.description.as_deref()));
+        let mut notes: Vec<String> = description.map(|d| format!("{table}: {d}")).into_iter().collect();
+        for gotcha in annotation.map(|a| a.gotchas.as_slice()).unwrap_or_default() {
+            notes.push(format!("Gotcha: {gotcha}"));
+        }
+        notes
+    }
+
+    /// A summary for the server instructions: the database description,
+    /// gotchas and example queries.
+    pub fn instructions(&self, database: &str) -> Option<String> {
+        if self.description.is_none() && self.gotchas.is_empty() && self.examples.is_empty() {
+            return None;
+        }
+        let mut text = format!("Database '{database}'");
+        match &self.description {
+            Some(description) => text.push_str(&format!(": {description}")),
+            None => text.push(':'),
+        }
+        for gotcha in &self.gotchas {
+            text.push_str(&format!("\n- Gotcha: {gotcha}"));
+        }
+        for example in &self.examples {
+            text.push_str(&format!("\n- Example ({}): {}", example.description, example.sql.trim()));
+        }
+        Some(text)
+    }
+
+    /// Annotations mentioning every word of `query`, ignoring case, best
+    /// first. Use it to find the table behind a concept such as "refund".
+    pub fn search(&self, query: &str) -> Vec<AnnotationHit> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut hits = Vec::new();
+        let mut consider = |kind, table: Option<&str>, column: Option<&str>, name: &str, text: &str, sql: Option<&str>| {
+            let name = name.to_lowercase();
+            let haystack = format!("{name} {} {}", text.to_lowercase(), sql.unwrap_or("").to_lowercase());
+            if !terms.iter().all(|t| haystack.contains(t)) {
+                return;
+            }
+            let score = terms.len() + terms.iter().filter(|t| name.contains(t.as_str())).count();
+            hits.push(AnnotationHit {
+                kind,
+                table: table.map(String::from),
+                column: column.map(String::from),
+                text: text.to_string(),
+                sql: sql.map(String::from),
+                score,
+            });
+        };
+
+        if let Some(description) = &self.description {
+            consider("database", None, None, "", description, None);
+        }
+        for (table, annotation) in &self.tables {
+            let description = annotation.description.as_deref().unwrap_or("");
+            consider("table", Some(table), None, table, description, None);
+            for (column, column_annotation) in &annotation.columns {
+                let description = column_annotation.description().unwrap_or("");
+                consider("column", Some(table), Some(column), column, description, None);
+                for (value, meaning) in column_annotation.values().into_iter().flatten() {
+                    let text = format!("{value} = {meaning}");
+                    consider("value", Some(table), Some(column), column, &text, None);
+                }
+            }
+            for gotcha in &annotation.gotchas {
+                consider("gotcha", Some(table), None, "", gotcha, None);
+            }
+        }
+        for example in &self.examples {
+            consider("example", None, None, "", &example.description, Some(&example.sql));
+        }
+        for gotcha in &self.gotchas {
+            consider("gotcha", None, None, "", gotcha, None);
+        }
+
+        // Stable, so equally good hits keep file order
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits
+    }
+}
+
+/// Where a SQLite database's annotations live when not given explicitly:
+/// `app.db.annotations.yaml` next to `app.db`.
+pub fn sidecar_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("sqlite:")?;
+    let path = rest.split('?').next().unwrap_or(rest);
+    let path = path.strip_prefix("//").unwrap_or(path);
+    if path.is_empty() || path == ":memory:" {
+        return None;
+    }
+    Some(PathBuf::from(format!("{path}.annotations.yaml")))
+}
+
+fn same_table(a: &str, b: &str) -> bool {
+    let (a_qualified, b_qualified) = (a.contains('.'), b.contains('.'));
+    a_qualified != b_qualified && unqualified(a) == unqualified(b)
+}
+
+fn fill_comment(object: &mut Value, comment: &str) {
+    if let Value::Object(map) = object {
+        let current = map.get("comment").and_then(Value::as_str).unwrap_or("");
+        if current.is_empty() {
+            map.insert("comment".to_string(), Value::String(comment.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "
+description: Billing data for the shop
+tables:
+  users:
+    description: People who can log in
+    columns:
+      active: 0 once the account is closed
+  sales.orders:
+    description: Orders placed through the shop
+    gotchas:
+      - Cancelled orders keep their lines
+    columns:
+      status:
+        description: Order status
+        values:
+          1: pending
+          3: refunded
+examples:
+  - description: Refunds this month
+    sql: SELECT * FROM sales.orders WHERE status = 3
+gotchas:
+  - Amounts are stored in cents
+";
+
+    #[test]
+    fn test_lookup() {
+        let annotations = Annotations::parse(YAML).unwrap();
+        assert_eq!(annotations.table_comment("users"), Some("People who can log in"));
+        assert_eq!(annotations.table_comment("main.users"), Some("People who can log in"));
+        assert_eq!(annotations.table_comment("orders"), Some("Orders placed through the shop"));
+        assert_eq!(annotations.table_comment("public.orders"), None);
+        assert_eq!(
+            annotations.column("users", "active").and_then(|c| c.description()),
+            Some("0 once the account is closed")
+        );
+        assert!(annotations.column("users", "name").is_none());
+        assert_eq!(
+            annotations.column("orders", "status").and_then(|c| c.summary()).as_deref(),
+            Some("Order status (1 = pending, 3 = refunded)")
+        );
+
+        assert!(Annotations::parse("").unwrap().is_empty());
+        assert!(Annotations::parse("tables:\n  users:\n    descripton: typo\n").is_err());
+        assert!(Annotations::parse(
+            "tables:\n  users:\n    columns:\n      active:\n        valeus: {0: closed}\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let annotations = Annotations::parse(YAML).unwrap();
+        let mut tables = vec![
+            serde_json::json!({"table_name": "users", "comment": null}),
+            serde_json::json!({"table_name": "sales.orders", "comment": "From COMMENT ON"}),
+        ];
+        annotations.apply_to_tables(&mut tables);
+        assert_eq!(tables[0]["comment"], "People who can log in");
+        assert_eq!(tables[1]["comment"], "From COMMENT ON");
+
+        let mut columns = vec![serde_json::json!({"name": "status", "comment": null})];
+        annotations.apply_to_columns("sales.orders", &mut columns);
+        assert_eq!(columns[0]["comment"], "Order status");
+        assert_eq!(columns[0]["values"]["3"], "refunded");
+
+        assert_eq!(
+            annotations.table_notes("sales.orders", None),
+            vec![
+                "sales.orders: Orders placed through the shop",
+                "Gotcha: Cancelled orders keep their lines"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instructions() {
+        let annotations = Annotations::parse(YAML).unwrap();
+        assert_eq!(
+            annotations.instructions("shop").unwrap(),
+            "Database 'shop': Billing data for the shop\n\
+             - Gotcha: Amounts are stored in cents\n\
+             - Example (Refunds this month): SELECT * FROM sales.orders WHERE status = 3"
+        );
+        assert!(EMPTY.instructions("shop").is_none());
+    }
+
+    #[test]
+    fn test_search() {
+        let annotations = Annotations::parse(YAML).unwrap();
+        let hits = annotations.search("Refund");
+        let kinds: Vec<&str> = hits.iter().map(|h| h.kind).collect();
+        assert_eq!(kinds, vec!["value", "example"]);
+        assert_eq!(hits[0].column.as_deref(), Some("status"));
+
+        // Names weigh more than descriptions
+        let hits = annotations.search("orders");
+        assert_eq!(hits[0].kind, "table");
+        assert_eq!(hits[0].table.as_deref(), Some("sales.orders"));
+
+        assert!(annotations.search("account closed").iter().all(|h| h.column.as_deref() == Some("active")));
+        assert!(annotations.search("  ").is_empty());
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path("sqlite:data/app.db?mode=ro"),
+            Some(PathBuf::from("data/app.db.annotations.yaml"))
+        );
+        assert_eq!(sidecar_path("sqlite::memory:"), None);
+        assert_eq!(sidecar_path("postgres://localhost/app"), None);
+    }
+}
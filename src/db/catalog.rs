@@ -0,0 +1,473 @@
+//! Every table and column of a database, read with a single catalog query
+//! instead of one `describe_table` per table.
+
+use std::time::Duration;
+
+use serde_json::Value;
+use sqlx::{AnyPool, Row};
+
+use crate::db::convert::row_to_json;
+use crate::db::dialect::quote_identifier;
+use crate::db::exec;
+use crate::db::ident::QualifiedName;
+use crate::db::{DatabaseEntry, DbBackend};
+use crate::error::McpSqlError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+    /// Named as in `list_tables`: schema-qualified on PostgreSQL.
+    pub name: String,
+    /// `table`, `view` or `materialized_view`.
+    pub kind: String,
+    /// Approximate, from statistics; `None` for views or when unknown.
+    pub row_count: Option<i64>,
+    pub comment: Option<String>,
+    pub columns: Vec<ColumnInfo>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+    pub primary_key: bool,
+    /// Unique on its own, by a single-column unique constraint or index.
+    pub unique: bool,
+    /// The referenced `table.column` (`schema.table.column` on PostgreSQL).
+    /// A column in several foreign keys shows the first; see
+    /// [`TableInfo::foreign_keys`] for all of them.
+    pub foreign_key: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// A foreign key: `columns` reference `referenced_columns` of
+/// `referenced_table`, in the same order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignKey {
+    /// `None` on SQLite, whose keys have no names.
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    /// Named as in [`TableInfo::name`].
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+impl TableInfo {
+    pub fn is_view(&self) -> bool {
+        self.kind != "table"
+    }
+}
+
+#[cfg(test)]
+impl TableInfo {
+    /// A table without a row count or comment, for tests.
+    pub fn fixture(name: &str, columns: Vec<ColumnInfo>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind: "table".to_string(),
+            row_count: None,
+            comment: None,
+            columns,
+            foreign_keys: Vec::new(),
+        }
+    }
+
+    /// Add a foreign key, and set it as the `foreign_key` of its columns
+    /// that have none yet.
+    pub fn with_foreign_key(
+        mut self,
+        name: &str,
+        columns: &[&str],
+        referenced_table: &str,
+        referenced_columns: &[&str],
+    ) -> Self {
+        for (name, referenced) in columns.iter().zip(referenced_columns) {
+            let column = self.columns.iter_mut().find(|c| c.name == *name).expect("column");
+            column.foreign_key.get_or_insert_with(|| format!("{referenced_table}.{referenced}"));
+        }
+        self.foreign_keys.push(ForeignKey {
+            name: Some(name.to_string()),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            referenced_table: referenced_table.to_string(),
+            referenced_columns: referenced_columns.iter().map(|c| c.to_string()).collect(),
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+impl ColumnInfo {
+    /// A nullable column without keys, default or comment, for tests.
+    pub fn fixture(name: &str, data_type: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            default: None,
+            primary_key: false,
+            unique: false,
+            foreign_key: None,
+            comment: None,
+        }
+    }
+}
+
+/// Load every table and view with its columns, optionally only those in
+/// `schema`. Without one, this covers all user schemas on PostgreSQL, the
+/// connected MySQL database and SQLite's `main`. SQLite keeps no row
+/// statistics, so its row counts are unknown until [`count_rows`].
+pub async fn load(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+) -> Result<Vec<TableInfo>, McpSqlError> {
+    let mut tables = load_columns(pool, backend, schema).await?;
+    let sqlite_schema = schema.filter(|s| *s != "main");
+    let sqlite_keys_sql;
+    let sql = match backend {
+        DbBackend::Postgres => {
+            "SELECT n.nspname || '.' || c.relname AS table_name, f.conname::text AS name, \
+                    k.position AS position, a.attname::text AS column_name, \
+                    rn.nspname || '.' || rc.relname AS referenced_table, \
+                    ra.attname::text AS referenced_column \
+             FROM pg_constraint f \
+             JOIN pg_class c ON c.oid = f.conrelid \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             CROSS JOIN LATERAL unnest(f.conkey, f.confkey) \
+                  WITH ORDINALITY AS k(col, ref, position) \
+             JOIN pg_attribute a ON a.attrelid = f.conrelid AND a.attnum = k.col \
+             JOIN pg_class rc ON rc.oid = f.confrelid \
+             JOIN pg_namespace rn ON rn.oid = rc.relnamespace \
+             JOIN pg_attribute ra ON ra.attrelid = f.confrelid AND ra.attnum = k.ref \
+             WHERE f.contype = 'f' \
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+               AND n.nspname NOT LIKE 'pg\\_toast%' \
+               AND ($1 IS NULL OR n.nspname = $1) \
+             ORDER BY table_name, name, k.position"
+        }
+        DbBackend::Sqlite => {
+            sqlite_keys_sql = sqlite_foreign_keys_sql(sqlite_schema);
+            sqlite_keys_sql.as_str()
+        }
+        DbBackend::Mysql => {
+            "SELECT CASE WHEN k.table_schema = DATABASE() THEN k.table_name \
+                    ELSE CONCAT(k.table_schema, '.', k.table_name) END AS table_name, \
+                    k.constraint_name AS name, k.ordinal_position AS position, \
+                    k.column_name AS column_name, \
+                    CASE WHEN k.referenced_table_schema = DATABASE() THEN k.referenced_table_name \
+                         ELSE CONCAT(k.referenced_table_schema, '.', k.referenced_table_name) \
+                    END AS referenced_table, \
+                    k.referenced_column_name AS referenced_column \
+             FROM information_schema.key_column_usage k \
+             WHERE k.table_schema = COALESCE(?, DATABASE()) \
+               AND k.referenced_table_name IS NOT NULL \
+             ORDER BY k.table_name, k.constraint_name, k.ordinal_position"
+        }
+    };
+    let query = match backend {
+        DbBackend::Sqlite => sqlx::query(sql),
+        DbBackend::Postgres | DbBackend::Mysql => sqlx::query(sql).bind(schema),
+    };
+    let rows = query.fetch_all(pool).await?;
+    group_foreign_keys(rows.iter().map(row_to_json), &mut tables);
+    Ok(tables)
+}
+
+/// Load every table and view with its columns as [`load`] does, but without
+/// the foreign keys that take a second catalog query. Each column's
+/// [`ColumnInfo::foreign_key`] is still filled in.
+pub async fn load_columns(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+) -> Result<Vec<TableInfo>, McpSqlError> {
+    let sqlite_schema = schema.filter(|s| *s != "main");
+    let sqlite_sql;
+    let sql = match backend {
+        DbBackend::Postgres => {
+            "SELECT n.nspname || '.' || c.relname AS table_name, \
+                    CASE c.relkind WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized_view' \
+                         ELSE 'table' END AS kind, \
+                    CASE WHEN c.relkind IN ('r', 'p') THEN COALESCE(s.n_live_tup, 0) END AS row_count, \
+                    obj_description(c.oid, 'pg_class') AS table_comment, \
+                    a.attname::text AS column_name, \
+                    format_type(a.atttypid, a.atttypmod) AS type, \
+                    NOT a.attnotnull AS nullable, \
+                    pg_get_expr(d.adbin, d.adrelid) AS default_value, \
+                    EXISTS (SELECT 1 FROM pg_constraint p WHERE p.conrelid = c.oid \
+                            AND p.contype = 'p' AND a.attnum = ANY (p.conkey)) AS primary_key, \
+                    EXISTS (SELECT 1 FROM pg_index i WHERE i.indrelid = c.oid AND i.indisunique \
+                            AND i.indnkeyatts = 1 AND i.indkey[0] = a.attnum \
+                            AND i.indpred IS NULL) AS is_unique, \
+                    (SELECT rn.nspname || '.' || rc.relname || '.' || ra.attname \
+                     FROM pg_constraint f \
+                     CROSS JOIN LATERAL unnest(f.conkey, f.confkey) AS k(col, ref) \
+                     JOIN pg_class rc ON rc.oid = f.confrelid \
+                     JOIN pg_namespace rn ON rn.oid = rc.relnamespace \
+                     JOIN pg_attribute ra ON ra.attrelid = f.confrelid AND ra.attnum = k.ref \
+                     WHERE f.conrelid = c.oid AND f.contype = 'f' AND k.col = a.attnum \
+                     ORDER BY f.conname LIMIT 1) AS foreign_key, \
+                    col_description(c.oid, a.attnum) AS comment \
+             FROM pg_class c \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped \
+             LEFT JOIN pg_attrdef d ON d.adrelid = c.oid AND d.adnum = a.attnum \
+             LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid \
+             WHERE c.relkind IN ('r', 'p', 'v', 'm') \
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+               AND n.nspname NOT LIKE 'pg\\_toast%' \
+               AND ($1 IS NULL OR n.nspname = $1) \
+             ORDER BY table_name, a.attnum"
+        }
+        DbBackend::Sqlite => {
+            sqlite_sql = sqlite_catalog_sql(sqlite_schema);
+            sqlite_sql.as_str()
+        }
+        DbBackend::Mysql => {
+            "SELECT CASE WHEN c.table_schema = DATABASE() THEN c.table_name \
+                    ELSE CONCAT(c.table_schema, '.', c.table_name) END AS table_name, \
+                    CASE WHEN t.table_type = 'VIEW' THEN 'view' ELSE 'table' END AS kind, \
+                    t.table_rows AS row_count, \
+                    CASE WHEN t.table_type = 'VIEW' THEN NULL \
+                         ELSE NULLIF(t.table_comment, '') END AS table_comment, \
+                    c.column_name AS column_name, c.column_type AS type, \
+                    c.is_nullable = 'YES' AS nullable, c.column_default AS default_value, \
+                    c.column_key = 'PRI' AS primary_key, c.column_key = 'UNI' AS is_unique, \
+                    (SELECT CONCAT(CASE WHEN k.referenced_table_schema = DATABASE() \
+                                        THEN k.referenced_table_name \
+                                        ELSE CONCAT(k.referenced_table_schema, '.', k.referenced_table_name) \
+                                   END, '.', k.referenced_column_name) \
+                     FROM information_schema.key_column_usage k \
+                     WHERE k.table_schema = c.table_schema AND k.table_name = c.table_name \
+                       AND k.column_name = c.column_name AND k.referenced_table_name IS NOT NULL \
+                     ORDER BY k.constraint_name LIMIT 1) AS foreign_key, \
+                    NULLIF(c.column_comment, '') AS comment \
+             FROM information_schema.columns c \
+             JOIN information_schema.tables t \
+               ON t.table_schema = c.table_schema AND t.table_name = c.table_name \
+             WHERE c.table_schema = COALESCE(?, DATABASE()) \
+             ORDER BY c.table_name, c.ordinal_position"
+        }
+    };
+
+    let query = match backend {
+        DbBackend::Sqlite => sqlx::query(sql),
+        DbBackend::Postgres | DbBackend::Mysql => sqlx::query(sql).bind(schema),
+    };
+    let rows = query.fetch_all(pool).await?;
+    Ok(group_rows(rows.iter().map(row_to_json)))
+}
+
+/// The catalog query for SQLite, whose pragma functions take the schema as
+/// an argument rather than a bind parameter. Tables outside `main` are
+/// prefixed with their schema, and so are the tables their keys reference.
+fn sqlite_catalog_sql(schema: Option<&str>) -> String {
+    let (master, prefix, pragma_schema) = match schema {
+        Some(schema) => (
+            format!("{}.sqlite_master", quote_identifier(DbBackend::Sqlite, schema)),
+            quote_literal(&format!("{schema}.")),
+            quote_literal(schema),
+        ),
+        None => ("sqlite_master".to_string(), "''".to_string(), "'main'".to_string()),
+    };
+    // `to` is NULL when a key references the parent's primary key implicitly
+    format!(
+        "SELECT {prefix} || m.name AS table_name, m.type AS kind, NULL AS row_count, \
+                NULL AS table_comment, p.name AS column_name, p.type AS type, \
+                p.\"notnull\" = 0 AND p.pk = 0 AS nullable, p.dflt_value AS default_value, \
+                p.pk > 0 AS primary_key, \
+                EXISTS (SELECT 1 FROM pragma_index_list(m.name, {pragma_schema}) il \
+                        WHERE il.\"unique\" AND il.partial = 0 \
+                          AND (SELECT COUNT(*) FROM pragma_index_info(il.name, {pragma_schema})) = 1 \
+                          AND (SELECT ii.name FROM pragma_index_info(il.name, {pragma_schema}) ii) \
+                              = p.name) AS is_unique, \
+                (SELECT {prefix} || f.\"table\" || '.' || COALESCE(f.\"to\", \
+                            (SELECT pk.name FROM pragma_table_info(f.\"table\", {pragma_schema}) pk \
+                             WHERE pk.pk = f.seq + 1)) \
+                 FROM pragma_foreign_key_list(m.name, {pragma_schema}) f WHERE f.\"from\" = p.name \
+                 ORDER BY f.id LIMIT 1) AS foreign_key, \
+                NULL AS comment \
+         FROM {master} m JOIN pragma_table_info(m.name, {pragma_schema}) p \
+         WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%' \
+         ORDER BY m.name, p.cid"
+    )
+}
+
+/// The foreign key query for SQLite, one row per column of each key, with
+/// names prefixed as in [`sqlite_catalog_sql`].
+fn sqlite_foreign_keys_sql(schema: Option<&str>) -> String {
+    let (master, prefix, pragma_schema) = match schema {
+        Some(schema) => (
+            format!("{}.sqlite_master", quote_identifier(DbBackend::Sqlite, schema)),
+            quote_literal(&format!("{schema}.")),
+            quote_literal(schema),
+        ),
+        None => ("sqlite_master".to_string(), "''".to_string(), "'main'".to_string()),
+    };
+    format!(
+        "SELECT {prefix} || m.name AS table_name, NULL AS name, f.seq + 1 AS position, \
+                f.\"from\" AS column_name, {prefix} || f.\"table\" AS referenced_table, \
+                COALESCE(f.\"to\", \
+                         (SELECT pk.name FROM pragma_table_info(f.\"table\", {pragma_schema}) pk \
+                          WHERE pk.pk = f.seq + 1)) AS referenced_column \
+         FROM {master} m JOIN pragma_foreign_key_list(m.name, {pragma_schema}) f \
+         WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' \
+         ORDER BY m.name, f.id, f.seq"
+    )
+}
+
+/// Build tables from catalog rows ordered by table.
+fn group_rows(rows: impl IntoIterator<Item = Value>) -> Vec<TableInfo> {
+    let text = |row: &Value, key: &str| row.get(key).and_then(Value::as_str).map(String::from);
+    let mut tables: Vec<TableInfo> = Vec::new();
+    for row in rows {
+        let Some(table) = text(&row, "table_name") else {
+            continue;
+        };
+        if tables.last().map(|t| t.name.as_str()) != Some(table.as_str()) {
+            tables.push(TableInfo {
+                name: table,
+                kind: text(&row, "kind").unwrap_or_else(|| "table".to_string()),
+                row_count: row.get("row_count").and_then(Value::as_i64),
+                comment: text(&row, "table_comment"),
+                columns: Vec::new(),
+                foreign_keys: Vec::new(),
+            });
+        }
+        let Some(name) = text(&row, "column_name") else {
+            continue;
+        };
+        let column = ColumnInfo {
+            name,
+            data_type: text(&row, "type").unwrap_or_default(),
+            nullable: flag(&row["nullable"]),
+            default: text(&row, "default_value"),
+            primary_key: flag(&row["primary_key"]),
+            unique: flag(&row["is_unique"]),
+            foreign_key: text(&row, "foreign_key"),
+            comment: text(&row, "comment"),
+        };
+        tables.last_mut().expect("pushed above").columns.push(column);
+    }
+    tables
+}
+
+/// Add foreign keys to `tables` from rows with one column of a key each,
+/// ordered by table, key and `position` in the key.
+fn group_foreign_keys(rows: impl IntoIterator<Item = Value>, tables: &mut [TableInfo]) {
+    let text = |row: &Value, key: &str| row.get(key).and_then(Value::as_str).map(String::from);
+    for row in rows {
+        let (Some(table), Some(column), Some(referenced_table), Some(referenced_column)) = (
+            text(&row, "table_name"),
+            text(&row, "column_name"),
+            text(&row, "referenced_table"),
+            text(&row, "referenced_column"),
+        ) else {
+            continue;
+        };
+        let Some(table) = tables.iter_mut().find(|t| t.name == table) else {
+            continue;
+        };
+        // Each key starts again at position 1
+        let first = row.get("position").and_then(Value::as_i64).map_or(true, |p| p <= 1);
+        match table.foreign_keys.last_mut() {
+            Some(key) if !first => {
+                key.columns.push(column);
+                key.referenced_columns.push(referenced_column);
+            }
+            _ => table.foreign_keys.push(ForeignKey {
+                name: text(&row, "name"),
+                columns: vec![column],
+                referenced_table,
+                referenced_columns: vec![referenced_column],
+            }),
+        }
+    }
+}
+
+/// A boolean, however the backend returns it: PostgreSQL as a boolean,
+/// SQLite and MySQL as 0 or 1.
+fn flag(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_i64().is_some_and(|n| n != 0),
+        Value::String(s) => s == "1" || s.eq_ignore_ascii_case("YES"),
+        _ => false,
+    }
+}
+
+/// Fill in the row counts of SQLite `tables`, loaded from `schema`, by
+/// counting every table in one statement. That reads the whole database, so
+/// the counts are left unknown if it takes more than a second. Other
+/// backends already have counts from their statistics.
+pub async fn count_rows(entry: &DatabaseEntry, schema: Option<&str>, tables: &mut [TableInfo]) {
+    if entry.backend != DbBackend::Sqlite {
+        return;
+    }
+    let schema = schema.filter(|s| *s != "main");
+    let prefix = schema.map(|s| format!("{s}.")).unwrap_or_default();
+    let counts: Vec<String> = tables
+        .iter()
+        .filter(|t| !t.is_view())
+        .map(|t| {
+            let name = t.name.strip_prefix(prefix.as_str()).unwrap_or(&t.name);
+            let table = QualifiedName::new(schema, name);
+            format!(
+                "SELECT {} AS name, COUNT(*) AS n FROM {}",
+                quote_literal(&t.name),
+                table.quoted(DbBackend::Sqlite)
+            )
+        })
+        .collect();
+    if counts.is_empty() {
+        return;
+    }
+    let query = counts.join(" UNION ALL ");
+    let Ok(rows) = exec::fetch_all(entry, &query, Duration::from_secs(1), true).await else {
+        return;
+    };
+    for row in rows {
+        let (Ok(name), Ok(count)) = (row.try_get::<String, _>(0), row.try_get::<i64, _>(1)) else {
+            continue;
+        };
+        if let Some(table) = tables.iter_mut().find(|t| t.name == name) {
+            table.row_count = Some(count);
+        }
+    }
+}
+
+fn quote_literal(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_rows() {
+        let rows = vec![
+            serde_json::json!({"table_name": "orders", "kind": "table", "row_count": 12,
+                               "table_comment": null, "column_name": "id", "type": "integer",
+                               "nullable": 0, "default_value": null, "primary_key": 1,
+                               "is_unique": 0, "foreign_key": null, "comment": null}),
+            serde_json::json!({"table_name": "orders", "kind": "table", "row_count": 12,
+                               "table_comment": null, "column_name": "user_id", "type": "integer",
+                               "nullable": true, "default_value": null, "primary_key": false,
+                               "is_unique": false, "foreign_key": "users.id", "comment": "Buyer"}),
+            serde_json::json!({"table_name": "v", "kind": "view", "row_count": null,
+                               "table_comment": null, "column_name": "x", "type": "text",
+                               "nullable": "YES", "default_value": null, "primary_key": "NO",
+                               "is_unique": null, "foreign_key": null, "comment": null}),
+        ];
+        let tables = group_rows(rows);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].row_count, Some(12));
+        assert_eq!(tables[0].columns.len(), 2);
+        assert!(tables[0].columns[0].primary_key && !tables[0].columns[0].nullable);
+        assert_eq!(tables[0].columns[1].foreign_key.as_deref(), Some("users.id"));
+        assert!(tables[1].is_view() && tables[1].columns[0].nullable);
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::{AnyPool, Row};
+
+use crate::db::dialect::{quote_identifier, sqlite_pragma};
+use crate::db::ident::QualifiedName;
+use crate::db::DbBackend;
+use crate::error::McpSqlError;
+use crate::sql;
+
+/// A primary key, unique, foreign key, check or exclusion constraint.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableConstraint {
+    /// `None` for SQLite constraints declared without `CONSTRAINT name`.
+    pub name: Option<String>,
+    /// `primary_key`, `unique`, `foreign_key`, `check` or `exclusion`.
+    pub kind: &'static str,
+    pub columns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referenced_table: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub referenced_columns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_delete: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<String>,
+    /// The checked expression, or the full EXCLUDE clause.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+}
+
+impl TableConstraint {
+    fn new(name: Option<String>, kind: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            columns: Vec::new(),
+            referenced_table: None,
+            referenced_columns: Vec::new(),
+            on_delete: None,
+            on_update: None,
+            expression: None,
+        }
+    }
+}
+
+/// Returns every constraint on a table: primary key first, then unique,
+/// check, exclusion and foreign key constraints, each in name order.
+pub async fn list_constraints(
+    pool: &AnyPool,
+    backend: DbBackend,
+    table: &QualifiedName,
+) -> Result<Vec<TableConstraint>, McpSqlError> {
+    match backend {
+        DbBackend::Postgres => list_constraints_postgres(pool, table).await,
+        DbBackend::Sqlite => list_constraints_sqlite(pool, table).await,
+        DbBackend::Mysql => list_constraints_mysql(pool, table).await,
+    }
+}
+
+/// Map each foreign key column to the `table.column` it references. A column
+/// of a composite key maps to the referenced column in the same position.
+pub fn foreign_key_map(constraints: &[TableConstraint]) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    for constraint in constraints.iter().filter(|c| c.kind == "foreign_key") {
+        let Some(referenced) = &constraint.referenced_table else {
+            continue;
+        };
+        for (column, target) in constraint.columns.iter().zip(&constraint.referenced_columns) {
+            map.entry(column.clone()).or_insert_with(|| format!("{referenced}.{target}"));
+        }
+    }
+    map
+}
+
+async fn list_constraints_postgres(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<TableConstraint>, McpSqlError> {
+    let regclass = table.quoted(DbBackend::Postgres);
+    let exists: (bool,) = sqlx::query_as("SELECT to_regclass($1) IS NOT NULL")
+        .bind(&regclass)
+        .fetch_one(pool)
+        .await?;
+    if !exists.0 {
+        return Err(McpSqlError::TableNotFound(table.to_string()));
+    }
+
+    // One row per constrained column, with the referenced column at the same
+    // position for foreign keys
+    let rows = sqlx::query(
+        "SELECT con.conname::text AS name, con.contype::text AS kind, \
+                a.attname::text AS column_name, \
+                rn.nspname::text || '.' || rc.relname::text AS referenced_table, \
+                ra.attname::text AS referenced_column, \
+                con.confdeltype::text AS on_delete, con.confupdtype::text AS on_update, \
+                CASE con.contype WHEN 'c' THEN pg_get_expr(con.conbin, con.conrelid) \
+                                 WHEN 'x' THEN pg_get_constraintdef(con.oid, true) END AS expression \
+         FROM pg_constraint con \
+         LEFT JOIN LATERAL unnest(con.conkey) WITH ORDINALITY AS k(attnum, position) ON true \
+         LEFT JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum \
+         LEFT JOIN pg_attribute ra ON ra.attrelid = con.confrelid \
+                                  AND ra.attnum = con.confkey[k.position] \
+         LEFT JOIN pg_class rc ON rc.oid = con.confrelid \
+         LEFT JOIN pg_namespace rn ON rn.oid = rc.relnamespace \
+         WHERE con.conrelid = to_regclass($1) AND con.contype IN ('p', 'u', 'f', 'c', 'x') \
+         ORDER BY CASE con.contype WHEN 'p' THEN 0 WHEN 'u' THEN 1 WHEN 'c' THEN 2 \
+                                   WHEN 'x' THEN 3 ELSE 4 END, \
+                  con.conname, k.position",
+    )
+    .bind(&regclass)
+    .fetch_all(pool)
+    .await?;
+
+    let mut constraints: Vec<TableConstraint> = Vec::new();
+    for row in &rows {
+        let name: String = row.try_get("name")?;
+        let kind = constraint_kind(&row.try_get::<String, _>("kind")?);
+        if !is_last(&constraints, kind, &name) {
+            let mut constraint = TableConstraint::new(Some(name), kind);
+            if constraint.kind == "foreign_key" {
+                constraint.referenced_table = row.try_get("referenced_table")?;
+                constraint.on_delete = postgres_action(&row.try_get::<String, _>("on_delete")?);
+                constraint.on_update = postgres_action(&row.try_get::<String, _>("on_update")?);
+            }
+            constraint.expression = row.try_get("expression")?;
+            constraints.push(constraint);
+        }
+        let constraint = constraints.last_mut().expect("pushed above");
+        if let Some(column) = row.try_get::<Option<String>, _>("column_name")? {
+            constraint.columns.push(column);
+        }
+        if let Some(column) = row.try_get::<Option<String>, _>("referenced_column")? {
+            constraint.referenced_columns.push(column);
+        }
+    }
+    Ok(constraints)
+}
+
+/// Whether rows for the constraint `kind`/`name` are already being collected.
+fn is_last(constraints: &[TableConstraint], kind: &str, name: &str) -> bool {
+    constraints
+        .last()
+        .is_some_and(|c| c.kind == kind && c.name.as_deref() == Some(name))
+}
+
+fn constraint_kind(contype: &str) -> &'static str {
+    match contype {
+        "p" => "primary_key",
+        "u" => "unique",
+        "f" => "foreign_key",
+        "x" => "exclusion",
+        _ => "check",
+    }
+}
+
+/// Spell out a `pg_constraint.confdeltype` / `confupdtype` code.
+fn postgres_action(code: &str) -> Option<String> {
+    let action = match code {
+        "a" => "NO ACTION",
+        "r" => "RESTRICT",
+        "c" => "CASCADE",
+        "n" => "SET NULL",
+        "d" => "SET DEFAULT",
+        _ => return None,
+    };
+    Some(action.to_string())
+}
+
+async fn list_constraints_sqlite(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<TableConstraint>, McpSqlError> {
+    let backend = DbBackend::Sqlite;
+    let master = match &table.schema {
+        Some(schema) => format!("{}.sqlite_master", quote_identifier(backend, schema)),
+        None => "sqlite_master".to_string(),
+    };
+    let create_sql: Option<(Option<String>,)> =
+        sqlx::query_as(&format!("SELECT sql FROM {master} WHERE type = 'table' AND name = ?"))
+            .bind(&table.name)
+            .fetch_optional(pool)
+            .await?;
+    let Some((create_sql,)) = create_sql else {
+        return Err(McpSqlError::TableNotFound(table.to_string()));
+    };
+
+    let mut constraints = Vec::new();
+
+    let mut primary_key: Vec<(i64, String)> = Vec::new();
+    for row in sqlx::query(&sqlite_pragma(table, "table_info")).fetch_all(pool).await? {
+        let position: i64 = row.try_get("pk")?;
+        if position > 0 {
+            primary_key.push((position, row.try_get("name")?));
+        }
+    }
+    if !primary_key.is_empty() {
+        primary_key.sort();
+        let mut constraint = TableConstraint::new(None, "primary_key");
+        constraint.columns = primary_key.into_iter().map(|(_, name)| name).collect();
+        constraints.push(constraint);
+    }
+
+    // UNIQUE constraints are backed by indexes with origin 'u'
+    for row in sqlx::query(&sqlite_pragma(table, "index_list")).fetch_all(pool).await? {
+        let origin: String = row.try_get("origin")?;
+        if origin != "u" {
+            continue;
+        }
+        let index_name: String = row.try_get("name")?;
+        let index = QualifiedName::new(table.schema.as_deref(), &index_name);
+        let mut constraint = TableConstraint::new(None, "unique");
+        for column in sqlx::query(&sqlite_pragma(&index, "index_info")).fetch_all(pool).await? {
+            constraint.columns.push(column.try_get("name")?);
+        }
+        // Constraints declared inline get an internal index name
+        if !index_name.starts_with("sqlite_autoindex_") {
+            constraint.name = Some(index_name);
+        }
+        constraints.push(constraint);
+    }
+
+    for check in sql::check_constraints(create_sql.as_deref().unwrap_or_default()) {
+        let mut constraint = TableConstraint::new(check.name, "check");
+        constraint.columns = check.column.into_iter().collect();
+        constraint.expression = Some(check.expression);
+        constraints.push(constraint);
+    }
+
+    // One row per column, grouped by the key's id
+    let mut foreign_keys: Vec<(i64, TableConstraint)> = Vec::new();
+    for row in sqlx::query(&sqlite_pragma(table, "foreign_key_list")).fetch_all(pool).await? {
+        let id: i64 = row.try_get("id")?;
+        if foreign_keys.last().map(|(last, _)| *last) != Some(id) {
+            let mut constraint = TableConstraint::new(None, "foreign_key");
+            constraint.referenced_table = Some(row.try_get("table")?);
+            constraint.on_delete = Some(row.try_get("on_delete")?);
+            constraint.on_update = Some(row.try_get("on_update")?);
+            foreign_keys.push((id, constraint));
+        }
+        let constraint = &mut foreign_keys.last_mut().expect("pushed above").1;
+        constraint.columns.push(row.try_get("from")?);
+        // `to` is NULL when the key references the parent's primary key implicitly
+        if let Some(to) = row.try_get::<Option<String>, _>("to")? {
+            constraint.referenced_columns.push(to);
+        }
+    }
+    // The pragma lists keys last-declared first
+    foreign_keys.sort_by_key(|(id, _)| *id);
+    for (_, mut constraint) in foreign_keys {
+        if constraint.referenced_columns.is_empty() {
+            let parent = QualifiedName::new(
+                table.schema.as_deref(),
+                constraint.referenced_table.as_deref().unwrap_or_default(),
+            );
+            let mut key: Vec<(i64, String)> = Vec::new();
+            for row in sqlx::query(&sqlite_pragma(&parent, "table_info")).fetch_all(pool).await? {
+                let position: i64 = row.try_get("pk")?;
+                if position > 0 {
+                    key.push((position, row.try_get("name")?));
+                }
+            }
+            key.sort();
+            constraint.referenced_columns = key.into_iter().map(|(_, name)| name).collect();
+        }
+        constraints.push(constraint);
+    }
+    Ok(constraints)
+}
+
+async fn list_constraints_mysql(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<TableConstraint>, McpSqlError> {
+    let exists: Option<(String,)> = sqlx::query_as(
+        "SELECT table_name AS name FROM information_schema.tables \
+         WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ?",
+    )
+    .bind(table.schema.as_deref())
+    .bind(&table.name)
+    .fetch_optional(pool)
+    .await?;
+    if exists.is_none() {
+        return Err(McpSqlError::TableNotFound(table.to_string()));
+    }
+
+    let rows = sqlx::query(
+        "SELECT tc.constraint_name AS name, tc.constraint_type AS kind, \
+                k.column_name AS column_name, \
+                CASE WHEN k.referenced_table_schema = tc.table_schema THEN k.referenced_table_name \
+                     ELSE CONCAT(k.referenced_table_schema, '.', k.referenced_table_name) \
+                END AS referenced_table, \
+                k.referenced_column_name AS referenced_column, \
+                rc.delete_rule AS on_delete, rc.update_rule AS on_update \
+         FROM information_schema.table_constraints tc \
+         LEFT JOIN information_schema.key_column_usage k \
+           ON k.constraint_schema = tc.constraint_schema \
+          AND k.constraint_name = tc.constraint_name AND k.table_name = tc.table_name \
+         LEFT JOIN information_schema.referential_constraints rc \
+           ON rc.constraint_schema = tc.constraint_schema \
+          AND rc.constraint_name = tc.constraint_name AND rc.table_name = tc.table_name \
+         WHERE tc.table_schema = COALESCE(?, DATABASE()) AND tc.table_name = ? \
+         ORDER BY CASE tc.constraint_type WHEN 'PRIMARY KEY' THEN 0 WHEN 'UNIQUE' THEN 1 \
+                                          WHEN 'CHECK' THEN 2 ELSE 4 END, \
+                  tc.constraint_name, k.ordinal_position",
+    )
+    .bind(table.schema.as_deref())
+    .bind(&table.name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut constraints: Vec<TableConstraint> = Vec::new();
+    for row in &rows {
+        let name: String = row.try_get("name")?;
+        let kind = match row.try_get::<String, _>("kind")?.as_str() {
+            "PRIMARY KEY" => "primary_key",
+            "UNIQUE" => "unique",
+            "FOREIGN KEY" => "foreign_key",
+            _ => "check",
+        };
+        if !is_last(&constraints, kind, &name) {
+            let mut constraint = TableConstraint::new(Some(name), kind);
+            if kind == "foreign_key" {
+                constraint.referenced_table = row.try_get("referenced_table")?;
+                constraint.on_delete = row.try_get("on_delete")?;
+                constraint.on_update = row.try_get("on_update")?;
+            }
+            constraints.push(constraint);
+        }
+        let constraint = constraints.last_mut().expect("pushed above");
+        if let Some(column) = row.try_get::<Option<String>, _>("column_name")? {
+            constraint.columns.push(column);
+        }
+        if let Some(column) = row.try_get::<Option<String>, _>("referenced_column")? {
+            constraint.referenced_columns.push(column);
+        }
+    }
+
+    // CHECK constraints are enforced from MySQL 8.0.16; older servers have
+    // no CHECK_CONSTRAINTS table
+    if constraints.iter().any(|c| c.kind == "check") {
+        let checks: Vec<(String, String)> = sqlx::query_as(
+            "SELECT cc.constraint_name AS name, cc.check_clause AS expression \
+             FROM information_schema.check_constraints cc \
+             JOIN information_schema.table_constraints tc \
+               ON tc.constraint_schema = cc.constraint_schema \
+              AND tc.constraint_name = cc.constraint_name \
+             WHERE tc.table_schema = COALESCE(?, DATABASE()) AND tc.table_name = ? \
+               AND tc.constraint_type = 'CHECK'",
+        )
+        .bind(table.schema.as_deref())
+        .bind(&table.name)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+        for (name, expression) in checks {
+            if let Some(constraint) = constraints
+                .iter_mut()
+                .find(|c| c.kind == "check" && c.name.as_deref() == Some(name.as_str()))
+            {
+                constraint.expression = Some(expression);
+            }
+        }
+    }
+    Ok(constraints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_foreign_key_map() {
+        let mut composite = TableConstraint::new(Some("line_order_fkey".to_string()), "foreign_key");
+        composite.columns = vec!["order_id".to_string(), "order_year".to_string()];
+        composite.referenced_table = Some("orders".to_string());
+        composite.referenced_columns = vec!["id".to_string(), "year".to_string()];
+        let mut check = TableConstraint::new(None, "check");
+        check.columns = vec!["qty".to_string()];
+
+        let map = foreign_key_map(&[composite, check]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["order_id"], "orders.id");
+        assert_eq!(map["order_year"], "orders.year");
+    }
+}
@@ -1,38 +1,86 @@
+use std::time::Duration;
+
+use serde::Serialize;
 use serde_json::Value;
 use sqlx::{AnyPool, Row};
 
+use crate::db::constraints::{foreign_key_map, list_constraints};
 use crate::db::convert::row_to_json;
-use crate::db::DbBackend;
+use crate::db::exec;
+use crate::db::ident::QualifiedName;
+use crate::db::pg_ddl;
+use crate::db::{DatabaseEntry, DbBackend};
 use crate::error::McpSqlError;
 
-/// List tables with approximate row counts.
-pub async fn list_tables(pool: &AnyPool, backend: DbBackend) -> Result<Vec<Value>, McpSqlError> {
-    let sql = match backend {
+/// List tables, views and materialized views with approximate row counts,
+/// optionally only those in `schema`. `kind` is `table`, `view` or
+/// `materialized_view`. Names are always schema-qualified on PostgreSQL
+/// (`public.users`); on MySQL and SQLite only outside the connected database
+/// or `main`.
+pub async fn list_tables(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+) -> Result<Vec<Value>, McpSqlError> {
+    let rows = match backend {
         DbBackend::Postgres => {
-            "SELECT schemaname || '.' || tablename AS table_name, \
-                    COALESCE(n_live_tup, 0) AS row_count \
-             FROM pg_tables \
-             LEFT JOIN pg_stat_user_tables ON tablename = relname AND schemaname = pg_stat_user_tables.schemaname \
-             WHERE pg_tables.schemaname NOT IN ('pg_catalog', 'information_schema') \
-             ORDER BY table_name"
+            sqlx::query(
+                "SELECT n.nspname || '.' || c.relname AS table_name, \
+                        CASE c.relkind WHEN 'v' THEN 'view' WHEN 'm' THEN 'materialized_view' \
+                             ELSE 'table' END AS kind, \
+                        CASE WHEN c.relkind IN ('r', 'p') THEN COALESCE(s.n_live_tup, 0) END \
+                            AS row_count, \
+                        obj_description(c.oid, 'pg_class') AS comment \
+                 FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid \
+                 WHERE c.relkind IN ('r', 'p', 'v', 'm') \
+                   AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                   AND n.nspname NOT LIKE 'pg\\_toast%' \
+                   AND ($1 IS NULL OR n.nspname = $1) \
+                 ORDER BY table_name",
+            )
+            .bind(schema)
+            .fetch_all(pool)
+            .await?
         }
         DbBackend::Sqlite => {
+            let (master, prefix) = match schema {
+                Some(schema) if schema != "main" => (
+                    format!("{}.sqlite_master", quote_identifier(backend, schema)),
+                    format!("{schema}."),
+                ),
+                _ => ("sqlite_master".to_string(), String::new()),
+            };
             // Get table names first
-            let name_rows = sqlx::query(
-                "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
-            )
+            let name_rows = sqlx::query(&format!(
+                "SELECT name, type FROM {master} WHERE type IN ('table', 'view') \
+                 AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            ))
             .fetch_all(pool)
             .await?;
 
             let mut results = Vec::new();
             for row in &name_rows {
                 let name: String = row.try_get("name")?;
+                let kind: String = row.try_get("type")?;
+                if kind == "view" {
+                    // Counting a view would run its query
+                    results.push(serde_json::json!({
+                        "table_name": format!("{prefix}{name}"),
+                        "kind": "view",
+                        "row_count": null,
+                        "comment": null,
+                    }));
+                    continue;
+                }
+                let table = QualifiedName::new(schema, &name);
                 // Count rows with a timeout — fall back to 0 for very large tables
                 let count: i64 = match tokio::time::timeout(
                     std::time::Duration::from_secs(1),
                     sqlx::query_as::<_, (i64,)>(&format!(
-                        "SELECT COUNT(*) FROM \"{}\"",
-                        name.replace('"', "\"\"")
+                        "SELECT COUNT(*) FROM {}",
+                        table.quoted(backend)
                     ))
                     .fetch_one(pool),
                 )
@@ -42,131 +90,273 @@ pub async fn list_tables(pool: &AnyPool, backend: DbBackend) -> Result<Vec<Value
                     _ => 0,
                 };
                 results.push(serde_json::json!({
-                    "table_name": name,
+                    "table_name": format!("{prefix}{name}"),
+                    "kind": "table",
                     "row_count": count,
+                    "comment": null,
                 }));
             }
             return Ok(results);
         }
         DbBackend::Mysql => {
-            "SELECT table_name, table_rows AS row_count \
-             FROM information_schema.tables \
-             WHERE table_schema = DATABASE() \
-             ORDER BY table_name"
+            sqlx::query(
+                "SELECT CASE WHEN table_schema = DATABASE() THEN table_name \
+                        ELSE CONCAT(table_schema, '.', table_name) END AS table_name, \
+                        CASE WHEN table_type = 'VIEW' THEN 'view' ELSE 'table' END AS kind, \
+                        table_rows AS row_count, \
+                        CASE WHEN table_type = 'VIEW' THEN NULL \
+                             ELSE NULLIF(table_comment, '') END AS comment \
+                 FROM information_schema.tables \
+                 WHERE table_schema = COALESCE(?, DATABASE()) \
+                 ORDER BY table_name",
+            )
+            .bind(schema)
+            .fetch_all(pool)
+            .await?
         }
     };
 
-    let rows = sqlx::query(sql).fetch_all(pool).await?;
     Ok(rows.iter().map(row_to_json).collect())
 }
 
+/// List schemas (PostgreSQL schemas, MySQL databases, attached SQLite
+/// databases) with their table counts. `is_default` marks the one unqualified
+/// table names resolve to.
+pub async fn list_schemas(pool: &AnyPool, backend: DbBackend) -> Result<Vec<Value>, McpSqlError> {
+    let sql = match backend {
+        DbBackend::Postgres => {
+            "SELECT n.nspname::text AS schema_name, COUNT(c.oid) AS table_count, \
+                    n.nspname = current_schema() AS is_default \
+             FROM pg_namespace n \
+             LEFT JOIN pg_class c ON c.relnamespace = n.oid AND c.relkind IN ('r', 'p') \
+             WHERE n.nspname NOT LIKE 'pg\\_%' AND n.nspname <> 'information_schema' \
+             GROUP BY n.nspname ORDER BY n.nspname"
+        }
+        DbBackend::Mysql => {
+            "SELECT s.schema_name AS schema_name, COUNT(t.table_name) AS table_count, \
+                    CAST(s.schema_name = DATABASE() AS SIGNED) AS is_default \
+             FROM information_schema.schemata s \
+             LEFT JOIN information_schema.tables t ON t.table_schema = s.schema_name \
+             WHERE s.schema_name NOT IN ('mysql', 'information_schema', 'performance_schema', 'sys') \
+             GROUP BY s.schema_name ORDER BY s.schema_name"
+        }
+        DbBackend::Sqlite => {
+            let databases = sqlx::query("PRAGMA database_list").fetch_all(pool).await?;
+            let mut results = Vec::new();
+            for row in &databases {
+                let name: String = row.try_get("name")?;
+                let (count,): (i64,) = sqlx::query_as(&format!(
+                    "SELECT COUNT(*) FROM {}.sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
+                    quote_identifier(backend, &name)
+                ))
+                .fetch_one(pool)
+                .await?;
+                results.push(serde_json::json!({
+                    "schema_name": name,
+                    "table_count": count,
+                    "is_default": name == "main",
+                }));
+            }
+            return Ok(results);
+        }
+    };
+    let rows = sqlx::query(sql).fetch_all(pool).await?;
+    let mut results: Vec<Value> = rows.iter().map(row_to_json).collect();
+    // MySQL has no boolean type; normalise 0/1
+    for row in &mut results {
+        if let Some(flag) = row.get_mut("is_default") {
+            if let Some(n) = flag.as_i64() {
+                *flag = Value::Bool(n != 0);
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Names of all tables and views, schema-qualified on PostgreSQL. Cheaper
+/// than `list_tables` since it skips row counts.
+pub async fn table_names(pool: &AnyPool, backend: DbBackend) -> Result<Vec<String>, McpSqlError> {
+    let sql = match backend {
+        DbBackend::Postgres => {
+            "SELECT n.nspname || '.' || c.relname FROM pg_class c \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE c.relkind IN ('r', 'p', 'v', 'm') \
+               AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+               AND n.nspname NOT LIKE 'pg\\_toast%' ORDER BY 1"
+        }
+        DbBackend::Sqlite => {
+            "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+             AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        }
+        DbBackend::Mysql => {
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = DATABASE() ORDER BY 1"
+        }
+    };
+    let rows: Vec<(String,)> = sqlx::query_as(sql).fetch_all(pool).await?;
+    Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
 /// Describe a table's columns.
 pub async fn describe_table(
     pool: &AnyPool,
     backend: DbBackend,
-    table: &str,
+    table: &QualifiedName,
 ) -> Result<Vec<Value>, McpSqlError> {
-    match backend {
-        DbBackend::Postgres => describe_table_postgres(pool, table).await,
-        DbBackend::Sqlite => describe_table_sqlite(pool, table).await,
-        DbBackend::Mysql => describe_table_mysql(pool, table).await,
+    let mut columns = match backend {
+        DbBackend::Postgres => describe_table_postgres(pool, table).await?,
+        DbBackend::Sqlite => describe_table_sqlite(pool, table).await?,
+        DbBackend::Mysql => describe_table_mysql(pool, table).await?,
+    };
+
+    // Views have no constraints to look up
+    let constraints = list_constraints(pool, backend, table).await.unwrap_or_default();
+    let fk_map = foreign_key_map(&constraints);
+    for col in &mut columns {
+        if let Value::Object(map) = col {
+            let col_name = map.get("name").and_then(|v| v.as_str()).unwrap_or("");
+            let fk = fk_map.get(col_name).map(|s| Value::String(s.clone())).unwrap_or(Value::Null);
+            map.insert("foreign_key".to_string(), fk);
+        }
     }
+    Ok(columns)
 }
 
-async fn describe_table_postgres(pool: &AnyPool, table: &str) -> Result<Vec<Value>, McpSqlError> {
-    // Handle schema.table format
-    let (schema, tbl) = if let Some((s, t)) = table.split_once('.') {
-        (s, t)
-    } else {
-        ("public", table)
-    };
+async fn describe_table_postgres(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<Value>, McpSqlError> {
+    let (schema, tbl) = (table.schema.as_deref(), table.name.as_str());
 
-    let sql = "SELECT c.column_name AS name, c.data_type AS type, \
-               c.is_nullable AS nullable, c.column_default AS default_value, \
-               CASE WHEN tc.constraint_type = 'PRIMARY KEY' THEN 'YES' ELSE 'NO' END AS primary_key \
+    // Enums, domains, composite types and arrays are shown by their own name
+    let sql = format!("SELECT c.column_name::text AS name, \
+               CASE WHEN c.data_type IN ('USER-DEFINED', 'ARRAY') OR c.domain_name IS NOT NULL \
+                    THEN format_type(a.atttypid, a.atttypmod) ELSE c.data_type::text END AS type, \
+               c.is_nullable::text AS nullable, c.column_default::text AS default_value, \
+               CASE WHEN EXISTS ( \
+                 SELECT 1 FROM information_schema.table_constraints tc \
+                 JOIN information_schema.key_column_usage kcu \
+                   ON kcu.constraint_schema = tc.constraint_schema \
+                   AND kcu.constraint_name = tc.constraint_name \
+                   AND kcu.table_name = tc.table_name \
+                 WHERE tc.constraint_type = 'PRIMARY KEY' \
+                   AND tc.table_schema = c.table_schema AND tc.table_name = c.table_name \
+                   AND kcu.column_name = c.column_name \
+               ) THEN 'YES' ELSE 'NO' END AS primary_key, \
+               col_description(a.attrelid, a.attnum) AS comment, \
+               {PG_ENUM_VALUES} \
                FROM information_schema.columns c \
-               LEFT JOIN information_schema.key_column_usage kcu \
-                 ON c.table_schema = kcu.table_schema \
-                 AND c.table_name = kcu.table_name \
-                 AND c.column_name = kcu.column_name \
-               LEFT JOIN information_schema.table_constraints tc \
-                 ON kcu.constraint_name = tc.constraint_name \
-                 AND kcu.table_schema = tc.table_schema \
-                 AND tc.constraint_type = 'PRIMARY KEY' \
-               WHERE c.table_schema = $1 AND c.table_name = $2 \
-               ORDER BY c.ordinal_position";
+               JOIN pg_attribute a \
+                 ON a.attrelid = CAST(quote_ident(c.table_schema) || '.' || quote_ident(c.table_name) AS regclass) \
+                AND a.attnum = c.ordinal_position \
+               WHERE c.table_schema = COALESCE($1, current_schema()) AND c.table_name = $2 \
+               ORDER BY c.ordinal_position");
 
-    let rows = sqlx::query(sql)
+    let rows = sqlx::query(&sql)
         .bind(schema)
         .bind(tbl)
         .fetch_all(pool)
         .await?;
 
     if rows.is_empty() {
-        return Err(McpSqlError::Other(format!("Table '{table}' not found")));
+        // information_schema leaves out materialized views
+        let columns = describe_relation_postgres(pool, table).await?;
+        if columns.is_empty() {
+            return Err(McpSqlError::TableNotFound(table.to_string()));
+        }
+        return Ok(columns);
     }
 
-    // Fetch FK info
-    let fk_sql = "SELECT kcu.column_name, ccu.table_schema || '.' || ccu.table_name || '.' || ccu.column_name AS references_col \
-                   FROM information_schema.key_column_usage kcu \
-                   JOIN information_schema.referential_constraints rc \
-                     ON kcu.constraint_name = rc.constraint_name AND kcu.constraint_schema = rc.constraint_schema \
-                   JOIN information_schema.constraint_column_usage ccu \
-                     ON rc.unique_constraint_name = ccu.constraint_name AND rc.unique_constraint_schema = ccu.constraint_schema \
-                   WHERE kcu.table_schema = $1 AND kcu.table_name = $2";
+    let mut columns: Vec<Value> = rows.iter().map(row_to_json).collect();
+    parse_enum_values(&mut columns);
+    Ok(columns)
+}
 
-    let fk_rows = sqlx::query(fk_sql)
-        .bind(schema)
-        .bind(tbl)
-        .fetch_all(pool)
-        .await
-        .unwrap_or_default();
+/// The labels of a column's enum type, directly or through a domain or an
+/// array, as a JSON array in text (the Any driver has no array support).
+const PG_ENUM_VALUES: &str = "(SELECT json_agg(e.enumlabel ORDER BY e.enumsortorder)::text \
+     FROM pg_type t JOIN pg_enum e ON e.enumtypid IN (t.oid, t.typbasetype, t.typelem) \
+     WHERE t.oid = a.atttypid) AS enum_values";
 
-    let fk_map: std::collections::HashMap<String, String> = fk_rows
-        .iter()
-        .filter_map(|r| {
-            let col: String = r.try_get("column_name").ok()?;
-            let refs: String = r.try_get("references_col").ok()?;
-            Some((col, refs))
-        })
-        .collect();
-
-    let mut result: Vec<Value> = rows.iter().map(row_to_json).collect();
-    for col in &mut result {
-        if let Value::Object(map) = col {
-            let col_name = map.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            let fk = fk_map.get(col_name).map(|s| Value::String(s.clone())).unwrap_or(Value::Null);
-            map.insert("foreign_key".to_string(), fk);
+/// Turn the `enum_values` text from [`PG_ENUM_VALUES`] into an array, and
+/// drop it from columns that are not enums.
+fn parse_enum_values(columns: &mut [Value]) {
+    for column in columns {
+        let Value::Object(map) = column else { continue };
+        let labels = map
+            .remove("enum_values")
+            .and_then(|v| v.as_str().and_then(|text| serde_json::from_str::<Value>(text).ok()));
+        if let Some(labels) = labels {
+            map.insert("enum_values".to_string(), labels);
         }
     }
+}
 
-    Ok(result)
+/// The comment on a table (`COMMENT ON TABLE` or MySQL's `COMMENT=`).
+/// SQLite has no comments.
+pub async fn table_comment(
+    pool: &AnyPool,
+    backend: DbBackend,
+    table: &QualifiedName,
+) -> Result<Option<String>, McpSqlError> {
+    let row: Option<(Option<String>,)> = match backend {
+        DbBackend::Sqlite => return Ok(None),
+        DbBackend::Postgres => {
+            sqlx::query_as("SELECT obj_description(to_regclass($1), 'pg_class')")
+                .bind(table.quoted(backend))
+                .fetch_optional(pool)
+                .await?
+        }
+        DbBackend::Mysql => {
+            sqlx::query_as(
+                "SELECT NULLIF(table_comment, '') FROM information_schema.tables \
+                 WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ? \
+                   AND table_type <> 'VIEW'",
+            )
+            .bind(table.schema.as_deref())
+            .bind(&table.name)
+            .fetch_optional(pool)
+            .await?
+        }
+    };
+    Ok(row.and_then(|(comment,)| comment))
 }
 
-async fn describe_table_sqlite(pool: &AnyPool, table: &str) -> Result<Vec<Value>, McpSqlError> {
-    // SQLite PRAGMA doesn't support parameterized queries, so we validate the table name
-    let safe_table = sanitize_identifier(table)?;
-    let sql = format!("PRAGMA table_info(\"{}\")", safe_table);
+/// Describe any relation's columns straight from `pg_attribute`.
+async fn describe_relation_postgres(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<Value>, McpSqlError> {
+    let rows = sqlx::query(&format!(
+        "SELECT a.attname::text AS name, format_type(a.atttypid, a.atttypmod) AS type, \
+                CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END AS nullable, \
+                pg_get_expr(d.adbin, d.adrelid) AS default_value, \
+                'NO' AS primary_key, col_description(a.attrelid, a.attnum) AS comment, \
+                {PG_ENUM_VALUES} \
+         FROM pg_attribute a \
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+         WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped \
+         ORDER BY a.attnum"
+    ))
+    .bind(table.quoted(DbBackend::Postgres))
+    .fetch_all(pool)
+    .await?;
+    let mut columns: Vec<Value> = rows.iter().map(row_to_json).collect();
+    parse_enum_values(&mut columns);
+    Ok(columns)
+}
+
+async fn describe_table_sqlite(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<Value>, McpSqlError> {
+    // SQLite PRAGMA doesn't support parameterized queries, so quote each part
+    let sql = sqlite_pragma(table, "table_info");
     let rows = sqlx::query(&sql).fetch_all(pool).await?;
 
     if rows.is_empty() {
-        return Err(McpSqlError::Other(format!("Table '{table}' not found")));
+        return Err(McpSqlError::TableNotFound(table.to_string()));
     }
 
-    // Fetch FK info via PRAGMA foreign_key_list
-    let fk_sql = format!("PRAGMA foreign_key_list(\"{}\")", safe_table);
-    let fk_rows = sqlx::query(&fk_sql).fetch_all(pool).await.unwrap_or_default();
-
-    let fk_map: std::collections::HashMap<String, String> = fk_rows
-        .iter()
-        .filter_map(|r| {
-            let from: String = r.try_get("from").ok()?;
-            let ref_table: String = r.try_get("table").ok()?;
-            let ref_col: String = r.try_get("to").ok()?;
-            Some((from, format!("{ref_table}.{ref_col}")))
-        })
-        .collect();
-
     let mut result = Vec::new();
     for row in &rows {
         let name: String = row.try_get("name").unwrap_or_default();
@@ -174,7 +364,6 @@ async fn describe_table_sqlite(pool: &AnyPool, table: &str) -> Result<Vec<Value>
         let notnull: i32 = row.try_get("notnull").unwrap_or(0);
         let dflt_value: Option<String> = row.try_get("dflt_value").ok();
         let pk: i32 = row.try_get("pk").unwrap_or(0);
-        let fk = fk_map.get(&name).cloned();
 
         result.push(serde_json::json!({
             "name": name,
@@ -182,79 +371,75 @@ async fn describe_table_sqlite(pool: &AnyPool, table: &str) -> Result<Vec<Value>
             "nullable": if notnull == 0 { "YES" } else { "NO" },
             "default_value": dflt_value,
             "primary_key": if pk > 0 { "YES" } else { "NO" },
-            "foreign_key": fk,
+            "comment": null,
         }));
     }
 
     Ok(result)
 }
 
-async fn describe_table_mysql(pool: &AnyPool, table: &str) -> Result<Vec<Value>, McpSqlError> {
+async fn describe_table_mysql(
+    pool: &AnyPool,
+    table: &QualifiedName,
+) -> Result<Vec<Value>, McpSqlError> {
     let sql = "SELECT column_name AS name, column_type AS type, \
                is_nullable AS nullable, column_default AS default_value, \
-               CASE WHEN column_key = 'PRI' THEN 'YES' ELSE 'NO' END AS primary_key \
+               CASE WHEN column_key = 'PRI' THEN 'YES' ELSE 'NO' END AS primary_key, \
+               NULLIF(column_comment, '') AS comment \
                FROM information_schema.columns \
-               WHERE table_schema = DATABASE() AND table_name = ? \
+               WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ? \
                ORDER BY ordinal_position";
 
-    let rows = sqlx::query(sql).bind(table).fetch_all(pool).await?;
+    let rows = sqlx::query(sql)
+        .bind(table.schema.as_deref())
+        .bind(&table.name)
+        .fetch_all(pool)
+        .await?;
 
     if rows.is_empty() {
-        return Err(McpSqlError::Other(format!("Table '{table}' not found")));
+        return Err(McpSqlError::TableNotFound(table.to_string()));
     }
 
-    // Fetch FK info
-    let fk_sql = "SELECT column_name, CONCAT(referenced_table_name, '.', referenced_column_name) AS references_col \
-                   FROM information_schema.key_column_usage \
-                   WHERE table_schema = DATABASE() AND table_name = ? AND referenced_table_name IS NOT NULL";
-
-    let fk_rows = sqlx::query(fk_sql).bind(table).fetch_all(pool).await.unwrap_or_default();
-
-    let fk_map: std::collections::HashMap<String, String> = fk_rows
-        .iter()
-        .filter_map(|r| {
-            let col: String = r.try_get("column_name").ok()?;
-            let refs: String = r.try_get("references_col").ok()?;
-            Some((col, refs))
-        })
-        .collect();
+    Ok(rows.iter().map(row_to_json).collect())
+}
 
-    let mut result: Vec<Value> = rows.iter().map(row_to_json).collect();
-    for col in &mut result {
-        if let Value::Object(map) = col {
-            let col_name = map.get("name").and_then(|v| v.as_str()).unwrap_or("");
-            let fk = fk_map.get(col_name).map(|s| Value::String(s.clone())).unwrap_or(Value::Null);
-            map.insert("foreign_key".to_string(), fk);
-        }
+/// `PRAGMA [schema.]name("table")` for a possibly schema-qualified table.
+pub fn sqlite_pragma(table: &QualifiedName, pragma: &str) -> String {
+    let backend = DbBackend::Sqlite;
+    match &table.schema {
+        Some(schema) => format!(
+            "PRAGMA {}.{pragma}({})",
+            quote_identifier(backend, schema),
+            quote_identifier(backend, &table.name)
+        ),
+        None => format!("PRAGMA {pragma}({})", quote_identifier(backend, &table.name)),
     }
-
-    Ok(result)
 }
 
 /// Sample N rows from a table.
 pub async fn sample_data(
     pool: &AnyPool,
     backend: DbBackend,
-    table: &str,
+    table: &QualifiedName,
     limit: u32,
 ) -> Result<Vec<Value>, McpSqlError> {
-    let safe_table = sanitize_identifier(table)?;
-    let sql = match backend {
-        DbBackend::Postgres => format!(
-            "SELECT * FROM \"{}\" TABLESAMPLE BERNOULLI (100) LIMIT {}",
-            safe_table, limit
-        ),
-        DbBackend::Sqlite => format!("SELECT * FROM \"{}\" LIMIT {}", safe_table, limit),
-        DbBackend::Mysql => format!(
-            "SELECT * FROM `{}` ORDER BY RAND() LIMIT {}",
-            safe_table, limit
-        ),
-    };
-
+    let sql = sample_data_sql(backend, table, limit);
     let rows = sqlx::query(&sql).fetch_all(pool).await?;
     Ok(rows.iter().map(row_to_json).collect())
 }
 
+/// Build the statement used by [`sample_data`].
+pub fn sample_data_sql(backend: DbBackend, table: &QualifiedName, limit: u32) -> String {
+    let table = table.quoted(backend);
+    match backend {
+        DbBackend::Postgres => {
+            format!("SELECT * FROM {table} TABLESAMPLE BERNOULLI (100) LIMIT {limit}")
+        }
+        DbBackend::Sqlite => format!("SELECT * FROM {table} LIMIT {limit}"),
+        DbBackend::Mysql => format!("SELECT * FROM {table} ORDER BY RAND() LIMIT {limit}"),
+    }
+}
+
 /// Get the correct EXPLAIN prefix for each backend.
 pub fn explain_prefix(backend: DbBackend) -> &'static str {
     match backend {
@@ -264,74 +449,317 @@ pub fn explain_prefix(backend: DbBackend) -> &'static str {
     }
 }
 
-/// Returns the CREATE TABLE DDL for a given table.
+/// Estimate how many rows a write statement would affect, without running it.
+///
+/// PostgreSQL and MySQL report the planner's estimate from EXPLAIN, in a
+/// read-only transaction; MySQL only for a plain UPDATE or DELETE. SQLite's
+/// query plan carries no row estimates, so the WHERE clause is counted
+/// directly instead, in a read-only transaction so that nothing smuggled into
+/// the clause can write. Returns `None` when no estimate is available,
+/// including for more than one statement.
+pub async fn estimate_affected_rows(
+    entry: &DatabaseEntry,
+    sql: &str,
+    timeout: Duration,
+) -> Option<i64> {
+    if crate::sql::classify(sql).len() != 1 {
+        return None;
+    }
+    let backend = entry.backend;
+    match backend {
+        DbBackend::Postgres => {
+            let explain = format!("EXPLAIN {sql}");
+            let rows = exec::fetch_all(entry, &explain, timeout, true).await.ok()?;
+            let lines: Vec<String> = rows.iter().filter_map(|r| r.try_get(0).ok()).collect();
+            plan_rows_from_text(&lines)
+        }
+        DbBackend::Mysql => {
+            // `ANALYZE DELETE ...` would become EXPLAIN ANALYZE, which runs it
+            let keyword = crate::sql::statement_keyword(sql)?;
+            if keyword != "UPDATE" && keyword != "DELETE" {
+                return None;
+            }
+            let explain = format!("EXPLAIN {sql}");
+            let rows = exec::fetch_all(entry, &explain, timeout, true).await.ok()?;
+            rows.iter()
+                .map(row_to_json)
+                .find_map(|r| match r.get("rows")? {
+                    Value::Number(n) => n.as_i64(),
+                    Value::String(s) => s.parse().ok(),
+                    _ => None,
+                })
+        }
+        DbBackend::Sqlite => {
+            let target = crate::sql::parse_write(sql)?;
+            if target.kind == crate::sql::WriteKind::Insert {
+                return None;
+            }
+            let filter = target.row_filter();
+            let table = QualifiedName::parse(&target.table).ok()?;
+            let alias = target.alias.map(|a| format!(" {a}")).unwrap_or_default();
+            let count_sql =
+                format!("SELECT COUNT(*) FROM {}{alias}{filter}", table.quoted(backend));
+            let rows = exec::fetch_all(entry, &count_sql, timeout, true).await.ok()?;
+            rows.first()?.try_get::<i64, _>(0).ok()
+        }
+    }
+}
+
+/// Pull the row estimate out of a PostgreSQL text plan. The top node of a
+/// write (`Update on ...`) always reports `rows=0`, so use the first node that
+/// actually produces rows.
+fn plan_rows_from_text(lines: &[String]) -> Option<i64> {
+    lines
+        .iter()
+        .map(|l| l.trim_start().trim_start_matches("->").trim_start())
+        .filter(|l| !["Insert on", "Update on", "Delete on", "Merge on"].iter().any(|p| l.starts_with(p)))
+        .find_map(|l| {
+            let rest = &l[l.find("rows=")? + "rows=".len()..];
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+}
+
+/// What the planner expects a statement to cost, as reported by EXPLAIN.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PlanSummary {
+    /// Total cost of the top plan node, in the backend's own units. SQLite
+    /// does not report costs.
+    pub total_cost: Option<f64>,
+    /// Rows the planner expects the statement to produce.
+    pub estimated_rows: Option<f64>,
+    /// Tables read in full, without using an index.
+    pub full_scans: Vec<FullScan>,
+}
+
+/// A table the plan reads from start to end.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FullScan {
+    pub table: String,
+    /// Approximate size of the table, when the backend can tell cheaply.
+    pub table_rows: Option<i64>,
+}
+
+/// EXPLAIN prefix whose output `explain_summary` can parse: YAML on
+/// PostgreSQL, verbose to name each table's schema, the tree format on MySQL
+/// (8.0.18+), and the query plan on SQLite.
+pub fn explain_plan_prefix(backend: DbBackend) -> &'static str {
+    match backend {
+        DbBackend::Postgres => "EXPLAIN (FORMAT YAML, VERBOSE) ",
+        DbBackend::Sqlite => "EXPLAIN QUERY PLAN ",
+        DbBackend::Mysql => "EXPLAIN FORMAT=TREE ",
+    }
+}
+
+/// EXPLAIN a single statement without running it and summarize the plan.
+pub async fn explain_summary(
+    pool: &AnyPool,
+    backend: DbBackend,
+    sql: &str,
+) -> Result<PlanSummary, McpSqlError> {
+    let rows = sqlx::query(&format!("{}{sql}", explain_plan_prefix(backend)))
+        .fetch_all(pool)
+        .await?;
+    let mut summary = match backend {
+        DbBackend::Postgres | DbBackend::Mysql => {
+            let text: Vec<String> = rows.iter().filter_map(|r| r.try_get(0).ok()).collect();
+            let text = text.join("\n");
+            if backend == DbBackend::Postgres {
+                summary_from_yaml_plan(&text)
+            } else {
+                summary_from_tree_plan(&text)
+            }
+        }
+        DbBackend::Sqlite => {
+            let details: Vec<String> = rows.iter().filter_map(|r| r.try_get("detail").ok()).collect();
+            summary_from_query_plan(&details)
+        }
+    };
+    for scan in &mut summary.full_scans {
+        scan.table_rows = table_row_estimate(pool, backend, &scan.table).await;
+    }
+    Ok(summary)
+}
+
+/// Parse `EXPLAIN (FORMAT YAML)` output. The first `Total Cost` and
+/// `Plan Rows` belong to the top node. A scanned table is qualified with the
+/// `Schema` that follows its `Relation Name` in verbose output.
+fn summary_from_yaml_plan(text: &str) -> PlanSummary {
+    let mut summary = PlanSummary::default();
+    let mut node_type = String::new();
+    let mut relation: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches("- ");
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        match key {
+            "Node Type" => {
+                if let Some(table) = relation.take() {
+                    push_full_scan(&mut summary, &table);
+                }
+                node_type = value.to_string();
+            }
+            "Relation Name" if node_type == "Seq Scan" => relation = Some(value.to_string()),
+            "Schema" => {
+                if let Some(table) = relation.take() {
+                    push_full_scan(&mut summary, &format!("{value}.{table}"));
+                }
+            }
+            "Total Cost" if summary.total_cost.is_none() => summary.total_cost = value.parse().ok(),
+            "Plan Rows" if summary.estimated_rows.is_none() => {
+                summary.estimated_rows = value.parse().ok()
+            }
+            _ => {}
+        }
+    }
+    if let Some(table) = relation {
+        push_full_scan(&mut summary, &table);
+    }
+    summary
+}
+
+/// Parse MySQL `EXPLAIN FORMAT=TREE` output, whose lines look like
+/// `-> Table scan on users  (cost=1.25 rows=10)`.
+fn summary_from_tree_plan(text: &str) -> PlanSummary {
+    let mut summary = PlanSummary::default();
+    for line in text.lines() {
+        let line = line.trim().trim_start_matches("->").trim();
+        if let Some(rest) = line.strip_prefix("Table scan on ") {
+            if let Some(table) = rest.split_whitespace().next() {
+                push_full_scan(&mut summary, table);
+            }
+        }
+        if summary.total_cost.is_none() {
+            summary.total_cost = plan_number(line, "cost=");
+            summary.estimated_rows = plan_number(line, "rows=");
+        }
+    }
+    summary
+}
+
+/// Parse SQLite's `EXPLAIN QUERY PLAN` details. `SCAN users` (or
+/// `SCAN TABLE users` on older versions) is a full scan; `SEARCH` uses an index.
+fn summary_from_query_plan(details: &[String]) -> PlanSummary {
+    let mut summary = PlanSummary::default();
+    for detail in details {
+        let Some(rest) = detail.strip_prefix("SCAN ") else {
+            continue;
+        };
+        let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+        match rest.split_whitespace().next() {
+            Some("CONSTANT" | "SUBQUERY") | None => {}
+            Some(table) => push_full_scan(&mut summary, table),
+        }
+    }
+    summary
+}
+
+fn push_full_scan(summary: &mut PlanSummary, table: &str) {
+    if !summary.full_scans.iter().any(|s| s.table == table) {
+        summary.full_scans.push(FullScan {
+            table: table.to_string(),
+            table_rows: None,
+        });
+    }
+}
+
+/// Read the number after `key` in a plan line, e.g. `cost=0.00..35.50` gives
+/// the total cost 35.50.
+fn plan_number(line: &str, key: &str) -> Option<f64> {
+    let rest = &line[line.find(key)? + key.len()..];
+    let value = rest.split([' ', ')']).next()?;
+    value.rsplit("..").next()?.parse().ok()
+}
+
+/// Approximate row count for a table, from planner statistics. SQLite only
+/// has them after `ANALYZE`; counting instead would be the very full scan
+/// a cost limit guards against. A PostgreSQL table named without its schema
+/// is looked up on the `search_path`.
+async fn table_row_estimate(pool: &AnyPool, backend: DbBackend, table: &str) -> Option<i64> {
+    let row: (i64,) = match backend {
+        DbBackend::Postgres => match table.split_once('.') {
+            Some((schema, name)) => sqlx::query_as(
+                "SELECT CAST(c.reltuples AS BIGINT) FROM pg_class c \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE n.nspname = $1 AND c.relname = $2",
+            )
+            .bind(schema)
+            .bind(name),
+            None => sqlx::query_as(
+                "SELECT CAST(reltuples AS BIGINT) FROM pg_class WHERE oid = to_regclass($1)",
+            )
+            .bind(table),
+        }
+        .fetch_one(pool)
+        .await
+        .ok()?,
+        DbBackend::Mysql => sqlx::query_as(
+            "SELECT CAST(table_rows AS SIGNED) FROM information_schema.tables \
+             WHERE table_schema = DATABASE() AND table_name = ?",
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .ok()?,
+        // The first number of a table's statistics is its row count
+        DbBackend::Sqlite => sqlx::query_as(
+            "SELECT CAST(stat AS INTEGER) FROM sqlite_stat1 WHERE tbl = ? LIMIT 1",
+        )
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .ok()?,
+    };
+    // PostgreSQL reports -1 for tables that were never analyzed
+    (row.0 >= 0).then_some(row.0)
+}
+
+/// Returns the CREATE TABLE DDL for a given table, or the CREATE VIEW
+/// statement for a view or materialized view.
 pub async fn show_create_table(
     pool: &AnyPool,
     backend: DbBackend,
-    table: &str,
+    table: &QualifiedName,
 ) -> Result<String, McpSqlError> {
-    let table = sanitize_identifier(table)?;
-
     match backend {
         DbBackend::Sqlite => {
-            let sql =
-                format!("SELECT sql FROM sqlite_master WHERE type='table' AND name='{table}'");
+            let master = match &table.schema {
+                Some(schema) => format!("{}.sqlite_master", quote_identifier(backend, schema)),
+                None => "sqlite_master".to_string(),
+            };
+            let sql = format!("SELECT sql FROM {master} WHERE type IN ('table', 'view') AND name = ?");
             let row = sqlx::query(&sql)
+                .bind(&table.name)
                 .fetch_optional(pool)
                 .await?
-                .ok_or_else(|| McpSqlError::Other(format!("table '{table}' not found")))?;
+                .ok_or_else(|| McpSqlError::TableNotFound(table.to_string()))?;
             let ddl: String = row.try_get("sql")?;
             Ok(ddl)
         }
         DbBackend::Mysql => {
-            let sql = format!("SHOW CREATE TABLE `{table}`");
+            let is_view = sqlx::query_as::<_, (String,)>(
+                "SELECT table_type FROM information_schema.tables \
+                 WHERE table_schema = COALESCE(?, DATABASE()) AND table_name = ?",
+            )
+            .bind(table.schema.as_deref())
+            .bind(&table.name)
+            .fetch_optional(pool)
+            .await?
+            .is_some_and(|(kind,)| kind == "VIEW");
+            let statement = if is_view { "SHOW CREATE VIEW" } else { "SHOW CREATE TABLE" };
+            let sql = format!("{statement} {}", table.quoted(backend));
             let row = sqlx::query(&sql)
                 .fetch_one(pool)
                 .await
-                .map_err(|_| McpSqlError::Other(format!("table '{table}' not found")))?;
-            // MySQL returns two columns: "Table" and "Create Table"
+                .map_err(|_| McpSqlError::TableNotFound(table.to_string()))?;
+            // The definition is the second column ("Create Table" or "Create View")
             let ddl: String = row.try_get(1)?;
             Ok(ddl)
         }
-        DbBackend::Postgres => {
-            // PostgreSQL has no built-in SHOW CREATE TABLE.
-            // Reconstruct from information_schema.
-            let rows = sqlx::query(
-                "SELECT column_name, data_type, is_nullable, column_default \
-                 FROM information_schema.columns \
-                 WHERE table_name = $1 \
-                 ORDER BY ordinal_position",
-            )
-            .bind(&table)
-            .fetch_all(pool)
-            .await?;
-
-            if rows.is_empty() {
-                return Err(McpSqlError::Other(format!("table '{table}' not found")));
-            }
-
-            let mut ddl = format!("CREATE TABLE {table} (\n");
-            for (i, row) in rows.iter().enumerate() {
-                let name: String = row.try_get("column_name")?;
-                let dtype: String = row.try_get("data_type")?;
-                let nullable: String = row.try_get("is_nullable")?;
-                let default: Option<String> = row.try_get("column_default").ok();
-
-                ddl.push_str(&format!("    {name} {}", dtype.to_uppercase()));
-                if nullable == "NO" {
-                    ddl.push_str(" NOT NULL");
-                }
-                if let Some(def) = default {
-                    ddl.push_str(&format!(" DEFAULT {def}"));
-                }
-                if i < rows.len() - 1 {
-                    ddl.push(',');
-                }
-                ddl.push('\n');
-            }
-            ddl.push_str(");");
-            Ok(ddl)
-        }
+        // PostgreSQL has no built-in SHOW CREATE TABLE
+        DbBackend::Postgres => pg_ddl::show_create_table(pool, table).await,
     }
 }
 
@@ -339,18 +767,13 @@ pub async fn show_create_table(
 pub async fn list_indexes(
     pool: &AnyPool,
     backend: DbBackend,
-    table: &str,
+    table: &QualifiedName,
 ) -> Result<Vec<Value>, McpSqlError> {
-    let table = sanitize_identifier(table)?;
-
     match backend {
         DbBackend::Sqlite => {
-            let index_rows = sqlx::query(&format!(
-                "PRAGMA index_list(\"{}\")",
-                table.replace('"', "\"\"")
-            ))
-            .fetch_all(pool)
-            .await?;
+            let index_rows = sqlx::query(&sqlite_pragma(table, "index_list"))
+                .fetch_all(pool)
+                .await?;
 
             let mut indexes = Vec::new();
             for row in &index_rows {
@@ -360,12 +783,10 @@ pub async fn list_indexes(
                     .map(|v| v == 1)
                     .unwrap_or(false);
 
-                let col_rows = sqlx::query(&format!(
-                    "PRAGMA index_info(\"{}\")",
-                    name.replace('"', "\"\"")
-                ))
-                .fetch_all(pool)
-                .await?;
+                let index = QualifiedName::new(table.schema.as_deref(), &name);
+                let col_rows = sqlx::query(&sqlite_pragma(&index, "index_info"))
+                    .fetch_all(pool)
+                    .await?;
                 let columns: Vec<String> = col_rows
                     .iter()
                     .filter_map(|r| r.try_get::<String, _>("name").ok())
@@ -381,10 +802,12 @@ pub async fn list_indexes(
         }
         DbBackend::Postgres => {
             let rows = sqlx::query(
-                "SELECT indexname, indexdef FROM pg_indexes \
-                 WHERE tablename = $1 ORDER BY indexname",
+                "SELECT indexname::text AS indexname, indexdef FROM pg_indexes \
+                 WHERE schemaname = COALESCE($1, current_schema()) AND tablename = $2 \
+                 ORDER BY indexname",
             )
-            .bind(&table)
+            .bind(table.schema.as_deref())
+            .bind(&table.name)
             .fetch_all(pool)
             .await?;
 
@@ -405,10 +828,11 @@ pub async fn list_indexes(
             let rows = sqlx::query(
                 "SELECT INDEX_NAME, COLUMN_NAME, NON_UNIQUE \
                  FROM information_schema.STATISTICS \
-                 WHERE TABLE_NAME = ? AND TABLE_SCHEMA = DATABASE() \
+                 WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ? \
                  ORDER BY INDEX_NAME, SEQ_IN_INDEX",
             )
-            .bind(&table)
+            .bind(table.schema.as_deref())
+            .bind(&table.name)
             .fetch_all(pool)
             .await?;
 
@@ -440,35 +864,431 @@ pub async fn list_indexes(
     }
 }
 
-/// Validate and sanitize a SQL identifier to prevent injection.
-fn sanitize_identifier(name: &str) -> Result<String, McpSqlError> {
-    // Allow alphanumeric, underscore, dot (for schema.table), and hyphen
-    if name
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-')
-        && !name.is_empty()
-    {
-        Ok(name.to_string())
-    } else {
-        Err(McpSqlError::InvalidSql(format!(
-            "Invalid identifier: '{name}'"
-        )))
+/// List stored functions and procedures, optionally only those in `schema`,
+/// with their arguments, return type and language. Routines installed by
+/// PostgreSQL extensions are left out. SQLite has none.
+pub async fn list_routines(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+) -> Result<Vec<Value>, McpSqlError> {
+    let rows = match backend {
+        DbBackend::Postgres => {
+            sqlx::query(
+                "SELECT n.nspname || '.' || p.proname AS routine_name, \
+                        CASE p.prokind WHEN 'p' THEN 'procedure' WHEN 'a' THEN 'aggregate' \
+                             WHEN 'w' THEN 'window' ELSE 'function' END AS kind, \
+                        pg_get_function_arguments(p.oid) AS arguments, \
+                        pg_get_function_result(p.oid) AS return_type, \
+                        l.lanname::text AS language \
+                 FROM pg_proc p \
+                 JOIN pg_namespace n ON n.oid = p.pronamespace \
+                 JOIN pg_language l ON l.oid = p.prolang \
+                 WHERE n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                   AND n.nspname NOT LIKE 'pg\\_toast%' \
+                   AND NOT EXISTS (SELECT 1 FROM pg_depend d \
+                                   WHERE d.classid = 'pg_proc'::regclass AND d.objid = p.oid \
+                                     AND d.deptype = 'e') \
+                   AND ($1 IS NULL OR n.nspname = $1) \
+                 ORDER BY routine_name, arguments",
+            )
+            .bind(schema)
+            .fetch_all(pool)
+            .await?
+        }
+        DbBackend::Mysql => {
+            sqlx::query(
+                "SELECT CASE WHEN r.routine_schema = DATABASE() THEN r.routine_name \
+                        ELSE CONCAT(r.routine_schema, '.', r.routine_name) END AS routine_name, \
+                        LOWER(r.routine_type) AS kind, \
+                        CAST(COALESCE((SELECT GROUP_CONCAT( \
+                                 CONCAT_WS(' ', p.parameter_mode, p.parameter_name, p.dtd_identifier) \
+                                 ORDER BY p.ordinal_position SEPARATOR ', ') \
+                             FROM information_schema.parameters p \
+                             WHERE p.specific_schema = r.routine_schema \
+                               AND p.specific_name = r.specific_name \
+                               AND p.ordinal_position > 0), '') AS CHAR) AS arguments, \
+                        CASE WHEN r.routine_type = 'FUNCTION' THEN r.dtd_identifier END AS return_type, \
+                        r.routine_body AS language \
+                 FROM information_schema.routines r \
+                 WHERE r.routine_schema = COALESCE(?, DATABASE()) \
+                 ORDER BY routine_name",
+            )
+            .bind(schema)
+            .fetch_all(pool)
+            .await?
+        }
+        DbBackend::Sqlite => return Ok(Vec::new()),
+    };
+
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+/// Returns the CREATE statement of a function or procedure, or of every
+/// overload sharing its name.
+pub async fn show_routine(
+    pool: &AnyPool,
+    backend: DbBackend,
+    routine: &QualifiedName,
+) -> Result<String, McpSqlError> {
+    let definitions: Vec<String> = match backend {
+        DbBackend::Postgres => {
+            // Aggregates have no CREATE FUNCTION to show
+            let rows: Vec<(String,)> = sqlx::query_as(
+                "SELECT pg_get_functiondef(p.oid) \
+                 FROM pg_proc p JOIN pg_namespace n ON n.oid = p.pronamespace \
+                 WHERE n.nspname = COALESCE($1, current_schema()) AND p.proname = $2 \
+                   AND p.prokind <> 'a' \
+                 ORDER BY p.oid",
+            )
+            .bind(routine.schema.as_deref())
+            .bind(&routine.name)
+            .fetch_all(pool)
+            .await?;
+            rows.into_iter().map(|(definition,)| definition.trim_end().to_string()).collect()
+        }
+        DbBackend::Mysql => {
+            let kinds: Vec<(String,)> = sqlx::query_as(
+                "SELECT routine_type FROM information_schema.routines \
+                 WHERE routine_schema = COALESCE(?, DATABASE()) AND routine_name = ? \
+                 ORDER BY routine_type",
+            )
+            .bind(routine.schema.as_deref())
+            .bind(&routine.name)
+            .fetch_all(pool)
+            .await?;
+            let mut definitions = Vec::new();
+            for (kind,) in kinds {
+                let sql = format!("SHOW CREATE {kind} {}", routine.quoted(backend));
+                let row = sqlx::query(&sql).fetch_one(pool).await?;
+                // The definition ("Create Function" or "Create Procedure") is
+                // NULL without the privileges to see it
+                let definition: Option<String> = row.try_get(2)?;
+                definitions.push(definition.ok_or_else(|| {
+                    McpSqlError::Other(format!("No permission to see the definition of '{routine}'"))
+                })?);
+            }
+            definitions
+        }
+        DbBackend::Sqlite => {
+            return Err(McpSqlError::Other(
+                "SQLite has no stored functions or procedures".to_string(),
+            ))
+        }
+    };
+
+    if definitions.is_empty() {
+        return Err(McpSqlError::Other(format!("Routine '{routine}' not found")));
+    }
+    Ok(definitions.join("\n\n"))
+}
+
+/// List triggers with the table they are on, their timing (`BEFORE`,
+/// `AFTER` or `INSTEAD OF`), the events that fire them and whether they run
+/// per row or per statement. `table` limits the list to one table in
+/// `schema` (or the current schema).
+pub async fn list_triggers(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+    table: Option<&str>,
+) -> Result<Vec<Value>, McpSqlError> {
+    let rows = match backend {
+        DbBackend::Postgres => {
+            sqlx::query(
+                "SELECT t.tgname::text AS trigger_name, \
+                        n.nspname || '.' || c.relname AS table_name, \
+                        CASE WHEN t.tgtype::int & 2 <> 0 THEN 'BEFORE' \
+                             WHEN t.tgtype::int & 64 <> 0 THEN 'INSTEAD OF' ELSE 'AFTER' END AS timing, \
+                        concat_ws(' OR ', \
+                            CASE WHEN t.tgtype::int & 4 <> 0 THEN 'INSERT' END, \
+                            CASE WHEN t.tgtype::int & 16 <> 0 THEN 'UPDATE' END, \
+                            CASE WHEN t.tgtype::int & 8 <> 0 THEN 'DELETE' END, \
+                            CASE WHEN t.tgtype::int & 32 <> 0 THEN 'TRUNCATE' END) AS event, \
+                        CASE WHEN t.tgtype::int & 1 <> 0 THEN 'ROW' ELSE 'STATEMENT' END AS level, \
+                        t.tgfoid::regproc::text AS function, \
+                        t.tgenabled <> 'D' AS enabled \
+                 FROM pg_trigger t \
+                 JOIN pg_class c ON c.oid = t.tgrelid \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE NOT t.tgisinternal \
+                   AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                   AND ($1 IS NULL OR n.nspname = $1) \
+                   AND ($2 IS NULL OR (c.relname = $2 AND n.nspname = COALESCE($1, current_schema()))) \
+                 ORDER BY table_name, trigger_name",
+            )
+            .bind(schema)
+            .bind(table)
+            .fetch_all(pool)
+            .await?
+        }
+        DbBackend::Mysql => {
+            sqlx::query(
+                "SELECT trigger_name, \
+                        CASE WHEN event_object_schema = DATABASE() THEN event_object_table \
+                        ELSE CONCAT(event_object_schema, '.', event_object_table) END AS table_name, \
+                        action_timing AS timing, \
+                        event_manipulation AS event, \
+                        action_orientation AS level \
+                 FROM information_schema.triggers \
+                 WHERE trigger_schema = COALESCE(?, DATABASE()) \
+                   AND (? IS NULL OR event_object_table = ?) \
+                 ORDER BY table_name, trigger_name",
+            )
+            .bind(schema)
+            .bind(table)
+            .bind(table)
+            .fetch_all(pool)
+            .await?
+        }
+        DbBackend::Sqlite => {
+            let (master, prefix) = match schema {
+                Some(schema) if schema != "main" => (
+                    format!("{}.sqlite_master", quote_identifier(backend, schema)),
+                    format!("{schema}."),
+                ),
+                _ => ("sqlite_master".to_string(), String::new()),
+            };
+            let rows: Vec<(String, String, String)> = sqlx::query_as(&format!(
+                "SELECT name, tbl_name, sql FROM {master} \
+                 WHERE type = 'trigger' AND (? IS NULL OR tbl_name = ?) \
+                 ORDER BY tbl_name, name"
+            ))
+            .bind(table)
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+            // SQLite only stores the statement; it has no statement-level triggers
+            return Ok(rows
+                .into_iter()
+                .map(|(name, table, sql)| {
+                    let (timing, event) = crate::sql::trigger_timing(&sql).unzip();
+                    serde_json::json!({
+                        "trigger_name": name,
+                        "table_name": format!("{prefix}{table}"),
+                        "timing": timing,
+                        "event": event,
+                        "level": "ROW",
+                    })
+                })
+                .collect());
+        }
+    };
+
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+/// Returns the CREATE TRIGGER statement of a trigger. On PostgreSQL, where a
+/// trigger runs a function and names are only unique per table, this is every
+/// trigger of that name followed by the function it calls.
+pub async fn show_trigger(
+    pool: &AnyPool,
+    backend: DbBackend,
+    trigger: &QualifiedName,
+) -> Result<String, McpSqlError> {
+    let not_found = || McpSqlError::Other(format!("Trigger '{trigger}' not found"));
+    match backend {
+        DbBackend::Postgres => {
+            let rows: Vec<(String, String)> = sqlx::query_as(
+                "SELECT pg_get_triggerdef(t.oid, true), pg_get_functiondef(t.tgfoid) \
+                 FROM pg_trigger t \
+                 JOIN pg_class c ON c.oid = t.tgrelid \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE NOT t.tgisinternal AND t.tgname = $2 \
+                   AND n.nspname = COALESCE($1, current_schema()) \
+                 ORDER BY c.relname",
+            )
+            .bind(trigger.schema.as_deref())
+            .bind(&trigger.name)
+            .fetch_all(pool)
+            .await?;
+            if rows.is_empty() {
+                return Err(not_found());
+            }
+            let mut definitions: Vec<String> = rows.iter().map(|(definition, _)| format!("{definition};")).collect();
+            let mut functions: Vec<String> = rows.into_iter().map(|(_, function)| function.trim_end().to_string()).collect();
+            functions.dedup();
+            definitions.extend(functions);
+            Ok(definitions.join("\n\n"))
+        }
+        DbBackend::Mysql => {
+            let sql = format!("SHOW CREATE TRIGGER {}", trigger.quoted(backend));
+            let row = sqlx::query(&sql).fetch_one(pool).await.map_err(|_| not_found())?;
+            // The definition is the third column ("SQL Original Statement")
+            let definition: String = row.try_get(2)?;
+            Ok(definition)
+        }
+        DbBackend::Sqlite => {
+            let master = match &trigger.schema {
+                Some(schema) => format!("{}.sqlite_master", quote_identifier(backend, schema)),
+                None => "sqlite_master".to_string(),
+            };
+            let sql = format!("SELECT sql FROM {master} WHERE type = 'trigger' AND name = ?");
+            let (definition,): (String,) = sqlx::query_as(&sql)
+                .bind(&trigger.name)
+                .fetch_optional(pool)
+                .await?
+                .ok_or_else(not_found)?;
+            Ok(definition)
+        }
     }
 }
 
+/// List PostgreSQL's user-defined types and sequences, optionally only those
+/// in `schema`: enums with their labels, domains with their base type and
+/// checks, composite types with their attributes, and sequences with their
+/// settings, current value and owning column. Objects belonging to extensions
+/// are left out. SQLite and MySQL have none of these.
+pub async fn list_types(
+    pool: &AnyPool,
+    backend: DbBackend,
+    schema: Option<&str>,
+) -> Result<Vec<Value>, McpSqlError> {
+    if backend != DbBackend::Postgres {
+        return Ok(Vec::new());
+    }
+
+    // Each kind carries its own fields as JSON text, merged in below
+    let user_schema = "n.nspname NOT IN ('pg_catalog', 'information_schema') \
+                       AND n.nspname NOT LIKE 'pg\\_%' AND ($1 IS NULL OR n.nspname = $1)";
+    let not_extension = |catalog: &str, oid: &str| {
+        format!(
+            "NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.classid = '{catalog}'::regclass \
+             AND d.objid = {oid} AND d.deptype = 'e')"
+        )
+    };
+    let types = format!(
+        "FROM pg_type t JOIN pg_namespace n ON n.oid = t.typnamespace \
+         WHERE {user_schema} AND {}",
+        not_extension("pg_type", "t.oid")
+    );
+    let sql = format!(
+        "SELECT n.nspname || '.' || t.typname AS name, 'enum' AS kind, \
+                json_build_object('labels', \
+                    (SELECT json_agg(e.enumlabel ORDER BY e.enumsortorder) \
+                     FROM pg_enum e WHERE e.enumtypid = t.oid))::text AS details \
+         {types} AND t.typtype = 'e' \
+         UNION ALL \
+         SELECT n.nspname || '.' || t.typname, 'domain', \
+                json_build_object('base_type', format_type(t.typbasetype, t.typtypmod), \
+                    'nullable', NOT t.typnotnull, 'default', t.typdefault, \
+                    'checks', (SELECT json_agg(pg_get_constraintdef(c.oid) ORDER BY c.conname) \
+                               FROM pg_constraint c WHERE c.contypid = t.oid))::text \
+         {types} AND t.typtype = 'd' \
+         UNION ALL \
+         SELECT n.nspname || '.' || t.typname, 'composite', \
+                json_build_object('attributes', \
+                    (SELECT json_agg(json_build_object('name', a.attname, \
+                                'type', format_type(a.atttypid, a.atttypmod)) ORDER BY a.attnum) \
+                     FROM pg_attribute a \
+                     WHERE a.attrelid = t.typrelid AND a.attnum > 0 AND NOT a.attisdropped))::text \
+         {types} AND t.typtype = 'c' \
+           AND (SELECT r.relkind FROM pg_class r WHERE r.oid = t.typrelid) = 'c' \
+         UNION ALL \
+         SELECT n.nspname || '.' || c.relname, 'sequence', \
+                json_build_object('data_type', format_type(s.seqtypid, NULL), \
+                    'start', s.seqstart, 'increment', s.seqincrement, \
+                    'min', s.seqmin, 'max', s.seqmax, 'cycle', s.seqcycle, \
+                    'last_value', CASE WHEN has_sequence_privilege(c.oid, 'SELECT,USAGE') \
+                                       THEN pg_sequence_last_value(c.oid) END, \
+                    'owned_by', (SELECT quote_ident(rn.nspname) || '.' || quote_ident(r.relname) \
+                                        || '.' || quote_ident(a.attname) \
+                                 FROM pg_depend d \
+                                 JOIN pg_class r ON r.oid = d.refobjid \
+                                 JOIN pg_namespace rn ON rn.oid = r.relnamespace \
+                                 JOIN pg_attribute a ON a.attrelid = r.oid AND a.attnum = d.refobjsubid \
+                                 WHERE d.classid = 'pg_class'::regclass AND d.objid = c.oid \
+                                   AND d.refclassid = 'pg_class'::regclass \
+                                   AND d.deptype IN ('a', 'i')))::text \
+         FROM pg_sequence s \
+         JOIN pg_class c ON c.oid = s.seqrelid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE {user_schema} AND {} \
+         ORDER BY kind, name",
+        not_extension("pg_class", "c.oid")
+    );
+
+    let rows: Vec<(String, String, String)> = sqlx::query_as(&sql).bind(schema).fetch_all(pool).await?;
+    Ok(rows
+        .into_iter()
+        .map(|(name, kind, details)| {
+            let mut object = serde_json::Map::new();
+            object.insert("name".to_string(), name.into());
+            object.insert("kind".to_string(), kind.into());
+            if let Ok(Value::Object(details)) = serde_json::from_str(&details) {
+                object.extend(details);
+            }
+            Value::Object(object)
+        })
+        .collect())
+}
+
+/// Quote a single identifier (a column, or one part of a table name) for the
+/// given backend. Use [`QualifiedName::quoted`] for table names.
+pub fn quote_identifier(backend: DbBackend, name: &str) -> String {
+    let quote = match backend {
+        DbBackend::Mysql => '`',
+        DbBackend::Postgres | DbBackend::Sqlite => '"',
+    };
+    format!("{quote}{}{quote}", name.replace(quote, &format!("{quote}{quote}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_sanitize_identifier() {
-        assert!(sanitize_identifier("users").is_ok());
-        assert!(sanitize_identifier("public.users").is_ok());
-        assert!(sanitize_identifier("my_table").is_ok());
-        assert!(sanitize_identifier("my-table").is_ok());
-        assert!(sanitize_identifier("").is_err());
-        assert!(sanitize_identifier("users; DROP TABLE users").is_err());
-        assert!(sanitize_identifier("users\"").is_err());
+    fn test_parse_enum_values() {
+        let mut columns = vec![
+            serde_json::json!({"name": "mood", "enum_values": "[\"sad\", \"happy, really\"]"}),
+            serde_json::json!({"name": "id", "enum_values": null}),
+        ];
+        parse_enum_values(&mut columns);
+        assert_eq!(columns[0]["enum_values"], serde_json::json!(["sad", "happy, really"]));
+        assert!(columns[1].get("enum_values").is_none());
+    }
+
+    #[test]
+    fn test_sample_data_sql() {
+        let table = |name| QualifiedName::parse(name).unwrap();
+        assert_eq!(
+            sample_data_sql(DbBackend::Postgres, &table("public.users"), 5),
+            "SELECT * FROM \"public\".\"users\" TABLESAMPLE BERNOULLI (100) LIMIT 5"
+        );
+        assert_eq!(
+            sample_data_sql(DbBackend::Mysql, &table("my-table"), 5),
+            "SELECT * FROM `my-table` ORDER BY RAND() LIMIT 5"
+        );
+        // Hostile names stay inside their quotes
+        assert_eq!(
+            sample_data_sql(DbBackend::Sqlite, &table("users; DROP TABLE users"), 1),
+            "SELECT * FROM \"users; DROP TABLE users\" LIMIT 1"
+        );
+        assert_eq!(
+            sample_data_sql(DbBackend::Sqlite, &table("users\""), 1),
+            "SELECT * FROM \"users\"\"\" LIMIT 1"
+        );
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier(DbBackend::Postgres, "public.users"), "\"public.users\"");
+        assert_eq!(quote_identifier(DbBackend::Sqlite, "my\"table"), "\"my\"\"table\"");
+        assert_eq!(quote_identifier(DbBackend::Mysql, "users"), "`users`");
+    }
+
+    #[test]
+    fn test_plan_rows_from_text() {
+        let plan: Vec<String> = [
+            "Update on users  (cost=0.00..35.50 rows=0 width=0)",
+            "  ->  Seq Scan on users  (cost=0.00..35.50 rows=1234 width=38)",
+            "        Filter: (active = 0)",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(plan_rows_from_text(&plan), Some(1234));
+        assert_eq!(plan_rows_from_text(&[]), None);
     }
 
     #[test]
@@ -477,4 +1297,55 @@ mod tests {
         assert_eq!(explain_prefix(DbBackend::Sqlite), "EXPLAIN QUERY PLAN ");
         assert_eq!(explain_prefix(DbBackend::Mysql), "EXPLAIN ");
     }
+
+    #[test]
+    fn test_summary_from_yaml_plan() {
+        let plan = r#"- Plan: 
+    Node Type: "Hash Join"
+    Startup Cost: 1.09
+    Total Cost: 38.25
+    Plan Rows: 2550
+    Plans: 
+      - Node Type: "Seq Scan"
+        Relation Name: "posts"
+        Schema: "billing"
+        Alias: "posts"
+        Total Cost: 35.50
+        Plan Rows: 2550
+      - Node Type: "Index Scan"
+        Relation Name: "users"
+        Total Cost: 8.15
+        Plan Rows: 1"#;
+        let summary = summary_from_yaml_plan(plan);
+        assert_eq!(summary.total_cost, Some(38.25));
+        assert_eq!(summary.estimated_rows, Some(2550.0));
+        assert_eq!(summary.full_scans.len(), 1);
+        assert_eq!(summary.full_scans[0].table, "billing.posts");
+
+        let plan = "- Plan: \n    Node Type: \"Seq Scan\"\n    Relation Name: \"users\"";
+        assert_eq!(summary_from_yaml_plan(plan).full_scans[0].table, "users");
+    }
+
+    #[test]
+    fn test_summary_from_tree_plan() {
+        let plan = "-> Filter: (users.active = 1)  (cost=1.25 rows=1)\n    -> Table scan on users  (cost=0.35..1.25 rows=10)";
+        let summary = summary_from_tree_plan(plan);
+        assert_eq!(summary.total_cost, Some(1.25));
+        assert_eq!(summary.estimated_rows, Some(1.0));
+        assert_eq!(summary.full_scans[0].table, "users");
+    }
+
+    #[test]
+    fn test_summary_from_query_plan() {
+        let details = vec![
+            "SCAN posts".to_string(),
+            "SEARCH users USING INTEGER PRIMARY KEY (rowid=?)".to_string(),
+            "SCAN TABLE comments".to_string(),
+            "SCAN CONSTANT ROW".to_string(),
+        ];
+        let summary = summary_from_query_plan(&details);
+        let tables: Vec<_> = summary.full_scans.iter().map(|s| s.table.as_str()).collect();
+        assert_eq!(tables, vec!["posts", "comments"]);
+        assert_eq!(summary.total_cost, None);
+    }
 }
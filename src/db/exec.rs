@@ -0,0 +1,528 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use serde_json::Value;
+use sqlx::any::AnyRow;
+use sqlx::pool::PoolConnection;
+use sqlx::{Any, AnyConnection, AnyPool, Connection, Either, Executor, Row};
+
+use crate::db::convert::row_to_json;
+use crate::db::ident::QualifiedName;
+use crate::db::{dialect, DatabaseEntry, DbBackend};
+use crate::error::McpSqlError;
+use crate::sql::{self, WriteKind, WriteTarget};
+
+/// How long we wait for the backend to acknowledge a cancel request.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of before/after rows included in a write preview.
+const PREVIEW_SAMPLE_ROWS: u32 = 10;
+
+/// Outcome of cancelling a statement that exceeded the query timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cancellation {
+    /// The server acknowledged the cancel request.
+    Confirmed,
+    /// A cancel request was attempted but the server did not acknowledge it.
+    Unconfirmed(String),
+    /// The backend offers no way to interrupt the statement from outside, so
+    /// it runs to completion.
+    Abandoned,
+}
+
+impl fmt::Display for Cancellation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cancellation::Confirmed => write!(f, "server-side cancellation confirmed"),
+            Cancellation::Unconfirmed(reason) => {
+                write!(f, "server-side cancellation not confirmed: {reason}")
+            }
+            Cancellation::Abandoned => write!(
+                f,
+                "SQLite cannot interrupt the statement; it keeps running until it completes"
+            ),
+        }
+    }
+}
+
+/// Run a statement on a dedicated connection, cancelling it on the server if
+/// it does not finish within `timeout`.
+///
+/// With `read_only`, the statement runs inside a read-only transaction on
+/// that same connection (`query_only` for SQLite) which is always rolled back,
+/// so the database itself rejects writes that slip past the keyword guard.
+pub async fn fetch_all(
+    entry: &DatabaseEntry,
+    sql: &str,
+    timeout: Duration,
+    read_only: bool,
+) -> Result<Vec<AnyRow>, McpSqlError> {
+    let mut conn = entry.pool.acquire().await?;
+    let (sql, tag) = tagged(entry.backend, sql);
+    let sql = sql.as_str();
+
+    let sqlite_read_only = read_only && entry.backend == DbBackend::Sqlite;
+    if sqlite_read_only {
+        sqlx::query("PRAGMA query_only = ON").execute(&mut *conn).await?;
+    }
+
+    let outcome = tokio::time::timeout(
+        timeout,
+        run_statement(&mut conn, entry.backend, sql, read_only),
+    )
+    .await;
+
+    match outcome {
+        Ok(rows) => {
+            // Whether the statement succeeded or not, the connection must not
+            // go back to the pool read-only; close it if that can't be undone.
+            if sqlite_read_only
+                && sqlx::query("PRAGMA query_only = OFF").execute(&mut *conn).await.is_err()
+            {
+                drop(conn.detach());
+            }
+            Ok(rows?)
+        }
+        Err(_) => Err(timed_out(entry, conn, sqlite_read_only, sql, tag.as_deref(), timeout).await),
+    }
+}
+
+/// Run `sql` on `conn`, inside a read-only transaction that is always rolled
+/// back when `read_only`.
+async fn run_statement(
+    conn: &mut AnyConnection,
+    backend: DbBackend,
+    sql: &str,
+    read_only: bool,
+) -> Result<Vec<AnyRow>, sqlx::Error> {
+    if !read_only {
+        return sqlx::query(sql).fetch_all(conn).await;
+    }
+    let mut tx = conn.begin_with(begin_read_only(backend)).await?;
+    let rows = sqlx::query(sql).fetch_all(&mut *tx).await;
+    tx.rollback().await?;
+    rows
+}
+
+/// Execute an INSERT, UPDATE or DELETE inside a transaction, capture a sample
+/// of the affected rows before and after it, and roll everything back.
+///
+/// Affected rows are matched by primary key. Sequence values consumed by a
+/// previewed INSERT on PostgreSQL are not returned by the rollback.
+pub async fn preview_write(
+    entry: &DatabaseEntry,
+    sql: &str,
+    timeout: Duration,
+) -> Result<Value, McpSqlError> {
+    // A second statement could end the transaction and escape the rollback
+    let target = sql::parse_write(sql).filter(|_| sql::classify(sql).len() == 1);
+    let target = target.ok_or_else(|| {
+        McpSqlError::InvalidSql(
+            "preview_write only supports a single INSERT, UPDATE or DELETE statement".to_string(),
+        )
+    })?;
+    let table = QualifiedName::parse(&target.table)?;
+    let primary_key: Vec<String> = dialect::describe_table(&entry.pool, entry.backend, &table)
+        .await?
+        .iter()
+        .filter(|c| c.get("primary_key").and_then(|v| v.as_str()) == Some("YES"))
+        .filter_map(|c| c.get("name").and_then(|v| v.as_str()).map(String::from))
+        .collect();
+
+    let mut conn = entry.pool.acquire().await?;
+    let (sql, tag) = tagged(entry.backend, sql);
+
+    let mut tx = conn.begin().await?;
+    let outcome = tokio::time::timeout(
+        timeout,
+        capture_preview(&mut tx, entry.backend, &sql, &target, &primary_key),
+    )
+    .await;
+
+    match outcome {
+        Ok(preview) => {
+            tx.rollback().await?;
+            preview
+        }
+        Err(_) => {
+            drop(tx);
+            Err(timed_out(entry, conn, false, &sql, tag.as_deref(), timeout).await)
+        }
+    }
+}
+
+/// Run the write on `conn` between two snapshots of the affected rows.
+async fn capture_preview(
+    conn: &mut AnyConnection,
+    backend: DbBackend,
+    sql: &str,
+    target: &WriteTarget,
+    primary_key: &[String],
+) -> Result<Value, McpSqlError> {
+    let table = QualifiedName::parse(&target.table)?.quoted(backend);
+
+    let before = match target.kind {
+        WriteKind::Insert => Vec::new(),
+        WriteKind::Update | WriteKind::Delete => {
+            // The condition may refer to the table by its alias
+            let alias = target.alias.as_ref().map(|a| format!(" {a}")).unwrap_or_default();
+            let select = format!(
+                "SELECT * FROM {table}{alias}{} LIMIT {PREVIEW_SAMPLE_ROWS}",
+                target.row_filter()
+            );
+            match probe(conn, &select).await? {
+                Ok(rows) => rows.iter().map(row_to_json).collect(),
+                Err(e) => {
+                    // A mistake in the statement is reported as the statement's
+                    // own error, located in its text rather than in the SELECT
+                    sqlx::query(sql).execute(&mut *conn).await?;
+                    return Err(McpSqlError::Other(format!(
+                        "Could not select the rows the statement affects: {e}"
+                    )));
+                }
+            }
+        }
+    };
+
+    // New rows of an auto-increment table are the ones above the current
+    // maximum. Keys without one, such as a uuid, leave the floor unknown.
+    let insert_floor = match (target.kind, primary_key) {
+        (WriteKind::Insert, [pk]) => {
+            let pk = dialect::quote_identifier(backend, pk);
+            probe(conn, &format!("SELECT MAX({pk}) FROM {table}"))
+                .await?
+                .ok()
+                .and_then(|rows| rows.first()?.try_get::<Option<i64>, _>(0).ok())
+        }
+        _ => None,
+    };
+
+    let rows_affected = sqlx::query(sql).execute(&mut *conn).await?.rows_affected();
+
+    let after = match target.kind {
+        _ if primary_key.is_empty() => None,
+        WriteKind::Update | WriteKind::Delete => {
+            Some(fetch_by_keys(conn, backend, &table, primary_key, &before).await?)
+        }
+        WriteKind::Insert => match insert_floor {
+            Some(floor) => {
+                let pk = dialect::quote_identifier(backend, &primary_key[0]);
+                let rows = sqlx::query(&format!(
+                    "SELECT * FROM {table} WHERE {pk} > {} ORDER BY {pk} LIMIT {PREVIEW_SAMPLE_ROWS}",
+                    floor.unwrap_or(i64::MIN)
+                ))
+                .fetch_all(&mut *conn)
+                .await?;
+                Some(rows.iter().map(row_to_json).collect())
+            }
+            None => None,
+        },
+    };
+
+    let mut preview = serde_json::json!({
+        "statement": target.kind.name(),
+        "table": target.table,
+        "rows_affected": rows_affected,
+        "primary_key": primary_key,
+        "before": before,
+        "after": after,
+        "rolled_back": true,
+    });
+    if after.is_none() {
+        preview["note"] = Value::String(
+            "Affected rows could not be identified after the write (no usable primary key)"
+                .to_string(),
+        );
+    }
+    Ok(preview)
+}
+
+/// Run `select` inside a savepoint, so that its failure leaves the
+/// surrounding transaction usable; PostgreSQL would abort it otherwise. The
+/// outer error is for the savepoint itself.
+async fn probe(
+    conn: &mut AnyConnection,
+    select: &str,
+) -> Result<Result<Vec<AnyRow>, sqlx::Error>, McpSqlError> {
+    sqlx::query("SAVEPOINT preview_probe").execute(&mut *conn).await?;
+    let rows = sqlx::query(select).fetch_all(&mut *conn).await;
+    let end = match rows {
+        Ok(_) => "RELEASE SAVEPOINT preview_probe",
+        Err(_) => "ROLLBACK TO SAVEPOINT preview_probe",
+    };
+    sqlx::query(end).execute(&mut *conn).await?;
+    Ok(rows)
+}
+
+/// Re-select the rows of `table` whose primary key matches one of `rows`.
+async fn fetch_by_keys(
+    conn: &mut AnyConnection,
+    backend: DbBackend,
+    table: &str,
+    primary_key: &[String],
+    rows: &[Value],
+) -> Result<Vec<Value>, McpSqlError> {
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut conditions = Vec::with_capacity(rows.len());
+    let mut values = Vec::new();
+    for row in rows {
+        let mut parts = Vec::with_capacity(primary_key.len());
+        for column in primary_key {
+            values.push(row.get(column).cloned().unwrap_or(Value::Null));
+            parts.push(format!(
+                "{} = {}",
+                dialect::quote_identifier(backend, column),
+                placeholder(backend, values.len())
+            ));
+        }
+        conditions.push(format!("({})", parts.join(" AND ")));
+    }
+
+    let select = format!("SELECT * FROM {table} WHERE {}", conditions.join(" OR "));
+    let mut query = sqlx::query(&select);
+    for value in values {
+        query = match value {
+            Value::Bool(b) => query.bind(b),
+            Value::Number(n) if n.is_i64() => query.bind(n.as_i64()),
+            Value::Number(n) => query.bind(n.as_f64()),
+            Value::String(s) => query.bind(s),
+            Value::Null => query.bind(None::<String>),
+            other => query.bind(other.to_string()),
+        };
+    }
+    let rows = query.fetch_all(&mut *conn).await?;
+    Ok(rows.iter().map(row_to_json).collect())
+}
+
+/// Positional bind placeholder (1-based) in the backend's syntax.
+fn placeholder(backend: DbBackend, index: usize) -> String {
+    match backend {
+        DbBackend::Postgres => format!("${index}"),
+        DbBackend::Sqlite | DbBackend::Mysql => "?".to_string(),
+    }
+}
+
+/// Cancel `sql`, tagged `tag`, after a timeout and build the error. With
+/// `query_only`, `conn` was made read-only for the statement.
+async fn timed_out(
+    entry: &DatabaseEntry,
+    mut conn: PoolConnection<Any>,
+    query_only: bool,
+    sql: &str,
+    tag: Option<&str>,
+    timeout: Duration,
+) -> McpSqlError {
+    let cancellation = cancel(&entry.pool, entry.backend, sql, tag).await;
+    tracing::warn!(
+        database = entry.name,
+        timeout_secs = timeout.as_secs(),
+        %cancellation,
+        "Query timed out"
+    );
+    // The connection was interrupted mid-protocol, or on SQLite is still
+    // running the statement; don't hand it back to the pool. An in-memory
+    // SQLite database is exempt, because its connection *is* the database:
+    // it returns to the pool once the statement is done, writable again.
+    if !is_sqlite_memory(entry) {
+        drop(conn.detach());
+    } else if query_only {
+        tokio::spawn(async move {
+            if sqlx::query("PRAGMA query_only = OFF").execute(&mut *conn).await.is_err() {
+                drop(conn.detach());
+            }
+        });
+    }
+    McpSqlError::QueryTimeout(timeout.as_secs(), cancellation)
+}
+
+fn is_sqlite_memory(entry: &DatabaseEntry) -> bool {
+    entry.backend == DbBackend::Sqlite
+        && (entry.url_redacted.contains(":memory:") || entry.url_redacted.contains("mode=memory"))
+}
+
+/// Run a write statement in a transaction on a dedicated connection and
+/// commit it only if it affected at most `max_affected_rows` rows; otherwise
+/// roll it back and report the count. Statements whose common table
+/// expressions modify data are refused, since the rows those change are not
+/// in the count.
+pub async fn fetch_all_capped(
+    entry: &DatabaseEntry,
+    sql: &str,
+    timeout: Duration,
+    max_affected_rows: u64,
+) -> Result<Vec<AnyRow>, McpSqlError> {
+    if sql::has_data_modifying_cte(sql) {
+        return Err(McpSqlError::PolicyViolation(format!(
+            "a WITH query that modifies data can't be checked against the limit of \
+             {max_affected_rows} affected rows; run the change as a plain statement"
+        )));
+    }
+    let mut conn = entry.pool.acquire().await?;
+    let (sql, tag) = tagged(entry.backend, sql);
+
+    let mut tx = conn.begin().await?;
+    let outcome = tokio::time::timeout(timeout, async {
+        let mut rows = Vec::new();
+        let mut affected = 0;
+        let mut stream = (&mut *tx).fetch_many(sqlx::query(&sql));
+        while let Some(step) = stream.try_next().await? {
+            match step {
+                Either::Left(result) => affected += result.rows_affected(),
+                Either::Right(row) => rows.push(row),
+            }
+        }
+        Ok::<_, sqlx::Error>((rows, affected))
+    })
+    .await;
+
+    match outcome {
+        Ok(Ok((rows, affected))) if affected <= max_affected_rows => {
+            tx.commit().await?;
+            Ok(rows)
+        }
+        Ok(Ok((_, affected))) => {
+            tx.rollback().await?;
+            Err(McpSqlError::TooManyRowsAffected(affected, max_affected_rows))
+        }
+        Ok(Err(e)) => {
+            tx.rollback().await?;
+            Err(e.into())
+        }
+        Err(_) => {
+            drop(tx);
+            Err(timed_out(entry, conn, false, &sql, tag.as_deref(), timeout).await)
+        }
+    }
+}
+
+/// Statement that opens a read-only transaction for each backend.
+fn begin_read_only(backend: DbBackend) -> &'static str {
+    match backend {
+        DbBackend::Postgres => "BEGIN READ ONLY",
+        DbBackend::Mysql => "START TRANSACTION READ ONLY",
+        // Writes are blocked by `PRAGMA query_only`; the transaction only
+        // guarantees the rollback.
+        DbBackend::Sqlite => "BEGIN",
+    }
+}
+
+/// Mark `sql` with a trailing comment unique to this call, by which a cancel
+/// request can find the session running it without a lookup before every
+/// statement. At the end, the comment leaves error positions unchanged.
+/// SQLite statements cannot be cancelled, so they are left as they are.
+fn tagged(backend: DbBackend, sql: &str) -> (String, Option<String>) {
+    static NEXT_TAG: AtomicU64 = AtomicU64::new(1);
+    if backend == DbBackend::Sqlite {
+        return (sql.to_string(), None);
+    }
+    let id = NEXT_TAG.fetch_add(1, Ordering::Relaxed);
+    let tag = format!("mcp-sql:{}:{id}", std::process::id());
+    (format!("{sql}\n/* {tag} */"), Some(tag))
+}
+
+/// Ask the server to stop the session running `sql`, tagged `tag`. The
+/// session is looked up only now, from another connection. PostgreSQL
+/// truncates long statements in `pg_stat_activity`, cutting off the tag, so
+/// a truncated statement is matched by its text instead.
+async fn cancel(
+    pool: &AnyPool,
+    backend: DbBackend,
+    sql: &str,
+    tag: Option<&str>,
+) -> Cancellation {
+    if backend == DbBackend::Sqlite {
+        return Cancellation::Abandoned;
+    }
+    let Some(tag) = tag else {
+        return Cancellation::Unconfirmed("statement was not tagged".to_string());
+    };
+    let pattern = format!("%{tag} */%");
+
+    let request = async {
+        match backend {
+            DbBackend::Postgres => {
+                let cancelled: Vec<(bool,)> = sqlx::query_as(
+                    "SELECT pg_cancel_backend(pid) FROM pg_stat_activity \
+                     WHERE pid <> pg_backend_pid() AND state = 'active' \
+                       AND (query LIKE $1 \
+                            OR (octet_length(query) >= \
+                                    (SELECT setting::int - 1 FROM pg_settings \
+                                     WHERE name = 'track_activity_query_size') \
+                                AND starts_with($2, query)))",
+                )
+                .bind(pattern)
+                .bind(sql)
+                .fetch_all(pool)
+                .await?;
+                Ok::<_, sqlx::Error>(cancelled.iter().any(|(c,)| *c))
+            }
+            DbBackend::Mysql => {
+                let ids: Vec<(i64,)> = sqlx::query_as(
+                    "SELECT CAST(id AS SIGNED) FROM information_schema.processlist \
+                     WHERE info LIKE ? AND id <> CONNECTION_ID()",
+                )
+                .bind(pattern)
+                .fetch_all(pool)
+                .await?;
+                for (id,) in &ids {
+                    // KILL does not accept bind parameters; `id` is an integer.
+                    sqlx::query(&format!("KILL QUERY {id}")).execute(pool).await?;
+                }
+                Ok(!ids.is_empty())
+            }
+            DbBackend::Sqlite => unreachable!(),
+        }
+    };
+
+    match tokio::time::timeout(CANCEL_TIMEOUT, request).await {
+        Ok(Ok(true)) => Cancellation::Confirmed,
+        Ok(Ok(false)) => Cancellation::Unconfirmed("server reported no matching session".to_string()),
+        Ok(Err(e)) => Cancellation::Unconfirmed(e.to_string()),
+        Err(_) => Cancellation::Unconfirmed("cancel request timed out".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_begin_read_only() {
+        assert_eq!(begin_read_only(DbBackend::Postgres), "BEGIN READ ONLY");
+        assert_eq!(begin_read_only(DbBackend::Mysql), "START TRANSACTION READ ONLY");
+        assert_eq!(begin_read_only(DbBackend::Sqlite), "BEGIN");
+    }
+
+    #[test]
+    fn test_tagged() {
+        assert_eq!(tagged(DbBackend::Sqlite, "SELECT 1"), ("SELECT 1".to_string(), None));
+        let (sql, tag) = tagged(DbBackend::Postgres, "SELECT 1 -- note");
+        let tag = tag.unwrap();
+        assert_eq!(sql, format!("SELECT 1 -- note\n/* {tag} */"));
+        assert_ne!(tagged(DbBackend::Mysql, "SELECT 1").1.unwrap(), tag);
+    }
+
+    #[test]
+    fn test_placeholder() {
+        assert_eq!(placeholder(DbBackend::Postgres, 2), "$2");
+        assert_eq!(placeholder(DbBackend::Mysql, 2), "?");
+    }
+
+    #[test]
+    fn test_cancellation_display() {
+        assert_eq!(
+            Cancellation::Confirmed.to_string(),
+            "server-side cancellation confirmed"
+        );
+        assert_eq!(
+            Cancellation::Unconfirmed("boom".to_string()).to_string(),
+            "server-side cancellation not confirmed: boom"
+        );
+        assert!(Cancellation::Abandoned.to_string().contains("keeps running"));
+    }
+}
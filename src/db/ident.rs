@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::db::dialect::quote_identifier;
+use crate::db::DbBackend;
+use crate::error::McpSqlError;
+
+/// A table name, optionally qualified by its schema: a PostgreSQL schema, a
+/// MySQL database, or an attached SQLite database such as `main`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QualifiedName {
+    pub schema: Option<String>,
+    pub name: String,
+}
+
+impl QualifiedName {
+    pub fn new(schema: Option<&str>, name: &str) -> Self {
+        Self {
+            schema: schema.map(str::to_string),
+            name: name.to_string(),
+        }
+    }
+
+    /// Parse `table`, `schema.table` or the quoted forms `"schema"."my.table"`
+    /// (backticks work too). Quotes are removed; dots inside them are kept.
+    pub fn parse(input: &str) -> Result<Self, McpSqlError> {
+        let invalid = || McpSqlError::InvalidSql(format!("Invalid identifier: '{input}'"));
+        let mut parts = Vec::new();
+        let mut chars = input.trim().chars().peekable();
+        loop {
+            let mut part = String::new();
+            match chars.peek() {
+                Some(&quote @ ('"' | '`')) => {
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            // A doubled quote is an escaped quote
+                            Some(c) if c == quote && chars.peek() == Some(&quote) => {
+                                chars.next();
+                                part.push(c);
+                            }
+                            Some(c) if c == quote => break,
+                            Some(c) => part.push(c),
+                            None => return Err(invalid()),
+                        }
+                    }
+                }
+                _ => {
+                    while let Some(&c) = chars.peek() {
+                        if c == '.' {
+                            break;
+                        }
+                        part.push(c);
+                        chars.next();
+                    }
+                    part = part.trim().to_string();
+                }
+            }
+            if part.is_empty() || part.contains('\0') {
+                return Err(invalid());
+            }
+            parts.push(part);
+            match chars.next() {
+                Some('.') => continue,
+                None => break,
+                Some(_) => return Err(invalid()),
+            }
+        }
+        match parts.as_slice() {
+            [name] => Ok(Self::new(None, name)),
+            [schema, name] => Ok(Self::new(Some(schema), name)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Parse `table` and qualify it with `schema` from a tool's `schema`
+    /// parameter. A name that is already qualified must agree with it.
+    pub fn resolve(table: &str, schema: Option<&str>) -> Result<Self, McpSqlError> {
+        let mut name = Self::parse(table)?;
+        match (&name.schema, schema) {
+            (Some(own), Some(schema)) if own != schema => {
+                return Err(McpSqlError::InvalidSql(format!(
+                    "Table '{table}' is qualified with schema '{own}' but schema '{schema}' was requested"
+                )));
+            }
+            (None, Some(schema)) => name.schema = Some(schema.to_string()),
+            _ => {}
+        }
+        Ok(name)
+    }
+
+    /// The name quoted for `backend`, each part separately.
+    pub fn quoted(&self, backend: DbBackend) -> String {
+        match &self.schema {
+            Some(schema) => format!(
+                "{}.{}",
+                quote_identifier(backend, schema),
+                quote_identifier(backend, &self.name)
+            ),
+            None => quote_identifier(backend, &self.name),
+        }
+    }
+}
+
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.schema {
+            Some(schema) => write!(f, "{schema}.{}", self.name),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(QualifiedName::parse("users").unwrap(), QualifiedName::new(None, "users"));
+        assert_eq!(
+            QualifiedName::parse("public.users").unwrap(),
+            QualifiedName::new(Some("public"), "users")
+        );
+        assert_eq!(
+            QualifiedName::parse("\"my.schema\".\"odd\"\"name\"").unwrap(),
+            QualifiedName::new(Some("my.schema"), "odd\"name")
+        );
+        assert_eq!(
+            QualifiedName::parse("`app`.orders").unwrap(),
+            QualifiedName::new(Some("app"), "orders")
+        );
+        assert!(QualifiedName::parse("").is_err());
+        assert!(QualifiedName::parse("a.b.c").is_err());
+        assert!(QualifiedName::parse("public.").is_err());
+        assert!(QualifiedName::parse("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            QualifiedName::resolve("users", Some("sales")).unwrap().to_string(),
+            "sales.users"
+        );
+        assert!(QualifiedName::resolve("public.users", Some("public")).is_ok());
+        assert!(QualifiedName::resolve("public.users", Some("sales")).is_err());
+    }
+
+    #[test]
+    fn test_quoted() {
+        let name = QualifiedName::new(Some("public"), "users");
+        assert_eq!(name.quoted(DbBackend::Postgres), "\"public\".\"users\"");
+        assert_eq!(name.quoted(DbBackend::Mysql), "`public`.`users`");
+        assert_eq!(
+            QualifiedName::new(None, "my\"table").quoted(DbBackend::Sqlite),
+            "\"my\"\"table\""
+        );
+    }
+}
@@ -1,5 +1,10 @@
+pub mod catalog;
+pub mod constraints;
 pub mod convert;
 pub mod dialect;
+pub mod exec;
+pub mod ident;
+pub mod pg_ddl;
 
 use sqlx::any::AnyPoolOptions;
 use sqlx::AnyPool;
@@ -51,16 +56,24 @@ pub struct DatabaseManager {
 }
 
 impl DatabaseManager {
-    pub async fn new(urls: &[String]) -> Result<Self, McpSqlError> {
+    /// Connect to every URL. With `read_only`, SQLite files are opened with
+    /// `mode=ro` so the file cannot be modified even if the SQL guard is bypassed.
+    pub async fn new(urls: &[String], read_only: bool) -> Result<Self, McpSqlError> {
         let mut databases = Vec::with_capacity(urls.len());
 
         for url in urls {
             let backend = DbBackend::from_url(url)?;
             let name = extract_db_name(url, backend);
 
+            let connect_url = if read_only && backend == DbBackend::Sqlite {
+                sqlite_read_only_url(url)
+            } else {
+                url.clone()
+            };
+
             let pool = AnyPoolOptions::new()
                 .max_connections(5)
-                .connect(url)
+                .connect(&connect_url)
                 .await?;
 
             databases.push(DatabaseEntry {
@@ -128,6 +141,28 @@ fn extract_db_name(url: &str, backend: DbBackend) -> String {
     }
 }
 
+/// Force `mode=ro` on a SQLite URL, keeping any other parameters (such as
+/// `immutable=1`). In-memory databases are left untouched.
+fn sqlite_read_only_url(url: &str) -> String {
+    let rest = url.strip_prefix("sqlite:").unwrap_or(url);
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let params: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let in_memory = path == ":memory:" || params.iter().any(|(k, v)| k == "mode" && v == "memory");
+    if in_memory {
+        return url.to_string();
+    }
+
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in params.iter().filter(|(k, _)| k != "mode") {
+        serializer.append_pair(key, value);
+    }
+    serializer.append_pair("mode", "ro");
+    format!("sqlite:{path}?{}", serializer.finish())
+}
+
 /// Redact password from a database URL.
 fn redact_url(url: &str) -> String {
     if let Ok(mut parsed) = url::Url::parse(url) {
@@ -189,6 +224,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sqlite_read_only_url() {
+        assert_eq!(sqlite_read_only_url("sqlite:test.db"), "sqlite:test.db?mode=ro");
+        assert_eq!(
+            sqlite_read_only_url("sqlite:data/app.db?mode=rwc"),
+            "sqlite:data/app.db?mode=ro"
+        );
+        assert_eq!(
+            sqlite_read_only_url("sqlite:app.db?immutable=1"),
+            "sqlite:app.db?immutable=1&mode=ro"
+        );
+        assert_eq!(sqlite_read_only_url("sqlite::memory:"), "sqlite::memory:");
+        assert_eq!(
+            sqlite_read_only_url("sqlite:shared?mode=memory"),
+            "sqlite:shared?mode=memory"
+        );
+    }
+
     #[test]
     fn test_redact_url() {
         assert_eq!(
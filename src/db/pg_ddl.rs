@@ -0,0 +1,599 @@
+use sqlx::{AnyConnection, AnyPool, Connection, Row};
+
+use crate::db::ident::QualifiedName;
+use crate::db::DbBackend;
+use crate::error::McpSqlError;
+
+/// Table-level facts needed to write its CREATE TABLE statement.
+#[derive(Debug, Default)]
+struct Relation {
+    /// `schema.table`, quoted where PostgreSQL would quote it.
+    name: String,
+    partitioned: bool,
+    unlogged: bool,
+    /// `RANGE (created_at)` for partitioned tables.
+    partition_key: Option<String>,
+    /// Parent and bound (`FOR VALUES ...`) of a partition.
+    partition_of: Option<(String, String)>,
+    /// Parents of a table using plain inheritance.
+    inherits: Option<String>,
+    options: Option<String>,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Column {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    default: Option<String>,
+    /// `a` (ALWAYS) or `d` (BY DEFAULT) for identity columns.
+    identity: Option<char>,
+    /// Expression of a stored generated column.
+    generated: Option<String>,
+    collation: Option<String>,
+    comment: Option<String>,
+    /// Declared only by an inheritance parent.
+    inherited: bool,
+}
+
+/// A sequence owned by a column, as `serial` columns create.
+#[derive(Debug)]
+struct OwnedSequence {
+    name: String,
+    column: String,
+    data_type: String,
+    start: i64,
+    increment: i64,
+    min: i64,
+    max: i64,
+    cache: i64,
+    cycle: bool,
+}
+
+#[derive(Debug)]
+struct Constraint {
+    name: String,
+    /// `p`, `u`, `f`, `c` or `x`, as in `pg_constraint.contype`.
+    kind: char,
+    definition: String,
+}
+
+/// Reconstruct the DDL `pg_dump --schema-only` would write for a table: the
+/// sequences behind its `serial` columns, the CREATE TABLE statement with
+/// columns, CHECK constraints and table options, then comments, key
+/// constraints, indexes and foreign keys. Ownership and privileges are left
+/// out. A view or materialized view gets its CREATE statement.
+///
+/// As in pg_dump, the definitions are read with an empty `search_path`, so
+/// the tables, sequences and types they refer to are schema-qualified.
+pub async fn show_create_table(pool: &AnyPool, table: &QualifiedName) -> Result<String, McpSqlError> {
+    let mut conn = pool.acquire().await?;
+    let mut tx = conn.begin().await?;
+    // Resolve the name on the caller's search_path before clearing it
+    let (regclass, kind): (String, String) = sqlx::query_as(
+        "SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname), c.relkind::text \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE c.oid = to_regclass($1) AND c.relkind IN ('r', 'p', 'v', 'm')",
+    )
+    .bind(table.quoted(DbBackend::Postgres))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| McpSqlError::TableNotFound(table.to_string()))?;
+    sqlx::query("SET LOCAL search_path = ''").execute(&mut *tx).await?;
+
+    let ddl = match kind.as_str() {
+        "v" | "m" => {
+            let (definition,): (String,) =
+                sqlx::query_as("SELECT pg_get_viewdef(to_regclass($1), true)")
+                    .bind(&regclass)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            let keyword = if kind == "m" { "MATERIALIZED VIEW" } else { "VIEW" };
+            format!("CREATE {keyword} {regclass} AS\n{}", definition.trim_end())
+        }
+        _ => {
+            let relation = fetch_relation(&mut tx, &regclass)
+                .await?
+                .ok_or_else(|| McpSqlError::TableNotFound(table.to_string()))?;
+            let columns = fetch_columns(&mut tx, &regclass).await?;
+            let sequences = fetch_owned_sequences(&mut tx, &regclass).await?;
+            let constraints = fetch_constraints(&mut tx, &regclass).await?;
+            let indexes = fetch_indexes(&mut tx, &regclass).await?;
+            render(&relation, &columns, &sequences, &constraints, &indexes)
+        }
+    };
+    tx.rollback().await?;
+    Ok(ddl)
+}
+
+async fn fetch_relation(
+    conn: &mut AnyConnection,
+    regclass: &str,
+) -> Result<Option<Relation>, McpSqlError> {
+    let row = sqlx::query(
+        "SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS name, \
+                c.relkind::text AS kind, c.relpersistence::text AS persistence, \
+                pg_get_partkeydef(c.oid) AS partition_key, \
+                CASE WHEN c.relispartition THEN pg_get_expr(c.relpartbound, c.oid) END AS partition_bound, \
+                (SELECT string_agg(quote_ident(pn.nspname) || '.' || quote_ident(p.relname), ', ' \
+                                   ORDER BY i.inhseqno) \
+                   FROM pg_inherits i \
+                   JOIN pg_class p ON p.oid = i.inhparent \
+                   JOIN pg_namespace pn ON pn.oid = p.relnamespace \
+                  WHERE i.inhrelid = c.oid) AS parents, \
+                array_to_string(c.reloptions, ', ') AS options, \
+                obj_description(c.oid, 'pg_class') AS comment \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE c.oid = to_regclass($1) AND c.relkind IN ('r', 'p')",
+    )
+    .bind(regclass)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let parents: Option<String> = row.try_get("parents")?;
+    let bound: Option<String> = row.try_get("partition_bound")?;
+    let (partition_of, inherits) = match bound {
+        Some(bound) => (Some((parents.unwrap_or_default(), bound)), None),
+        None => (None, parents),
+    };
+    Ok(Some(Relation {
+        name: row.try_get("name")?,
+        partitioned: row.try_get::<String, _>("kind")? == "p",
+        unlogged: row.try_get::<String, _>("persistence")? == "u",
+        partition_key: row.try_get("partition_key")?,
+        partition_of,
+        inherits,
+        options: row.try_get::<Option<String>, _>("options")?.filter(|o| !o.is_empty()),
+        comment: row.try_get("comment")?,
+    }))
+}
+
+async fn fetch_columns(
+    conn: &mut AnyConnection,
+    regclass: &str,
+) -> Result<Vec<Column>, McpSqlError> {
+    let rows = sqlx::query(
+        "SELECT quote_ident(a.attname) AS name, format_type(a.atttypid, a.atttypmod) AS data_type, \
+                a.attnotnull AS not_null, pg_get_expr(d.adbin, d.adrelid) AS default_value, \
+                a.attidentity::text AS identity, a.attgenerated::text AS generated, \
+                CASE WHEN a.attcollation <> t.typcollation THEN \
+                  (SELECT quote_ident(co.collname) FROM pg_collation co WHERE co.oid = a.attcollation) \
+                END AS collation, \
+                col_description(a.attrelid, a.attnum) AS comment, NOT a.attislocal AS inherited \
+         FROM pg_attribute a \
+         JOIN pg_type t ON t.oid = a.atttypid \
+         LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum \
+         WHERE a.attrelid = to_regclass($1) AND a.attnum > 0 AND NOT a.attisdropped \
+         ORDER BY a.attnum",
+    )
+    .bind(regclass)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut columns = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let default: Option<String> = row.try_get("default_value")?;
+        let identity: String = row.try_get("identity")?;
+        let generated: String = row.try_get("generated")?;
+        let is_generated = generated == "s";
+        columns.push(Column {
+            name: row.try_get("name")?,
+            data_type: row.try_get("data_type")?,
+            not_null: row.try_get("not_null")?,
+            default: if is_generated { None } else { default.clone() },
+            identity: identity.chars().next(),
+            generated: if is_generated { default } else { None },
+            collation: row.try_get("collation")?,
+            comment: row.try_get("comment")?,
+            inherited: row.try_get("inherited")?,
+        });
+    }
+    Ok(columns)
+}
+
+/// Sequences attached to a column with `OWNED BY`; identity sequences are
+/// part of the column definition instead.
+async fn fetch_owned_sequences(
+    conn: &mut AnyConnection,
+    regclass: &str,
+) -> Result<Vec<OwnedSequence>, McpSqlError> {
+    let rows = sqlx::query(
+        "SELECT quote_ident(n.nspname) || '.' || quote_ident(c.relname) AS name, \
+                quote_ident(a.attname) AS column_name, \
+                format_type(s.seqtypid, NULL) AS data_type, s.seqstart AS start, \
+                s.seqincrement AS increment, s.seqmin AS min, s.seqmax AS max, \
+                s.seqcache AS cache, s.seqcycle AS cycle \
+         FROM pg_depend d \
+         JOIN pg_sequence s ON s.seqrelid = d.objid \
+         JOIN pg_class c ON c.oid = d.objid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace \
+         JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid \
+         WHERE d.classid = 'pg_class'::regclass AND d.refclassid = 'pg_class'::regclass \
+           AND d.refobjid = to_regclass($1) AND d.deptype = 'a' \
+         ORDER BY a.attnum",
+    )
+    .bind(regclass)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut sequences = Vec::with_capacity(rows.len());
+    for row in &rows {
+        sequences.push(OwnedSequence {
+            name: row.try_get("name")?,
+            column: row.try_get("column_name")?,
+            data_type: row.try_get("data_type")?,
+            start: row.try_get("start")?,
+            increment: row.try_get("increment")?,
+            min: row.try_get("min")?,
+            max: row.try_get("max")?,
+            cache: row.try_get("cache")?,
+            cycle: row.try_get("cycle")?,
+        });
+    }
+    Ok(sequences)
+}
+
+async fn fetch_constraints(
+    conn: &mut AnyConnection,
+    regclass: &str,
+) -> Result<Vec<Constraint>, McpSqlError> {
+    // Constraints cloned onto partitions from their parent are left to the parent
+    let rows = sqlx::query(
+        "SELECT quote_ident(conname) AS name, contype::text AS kind, \
+                pg_get_constraintdef(oid, true) AS definition \
+         FROM pg_constraint \
+         WHERE conrelid = to_regclass($1) AND contype IN ('p', 'u', 'f', 'c', 'x') \
+           AND conislocal AND conparentid = 0 \
+         ORDER BY conname",
+    )
+    .bind(regclass)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut constraints = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let kind: String = row.try_get("kind")?;
+        constraints.push(Constraint {
+            name: row.try_get("name")?,
+            kind: kind.chars().next().unwrap_or('c'),
+            definition: row.try_get("definition")?,
+        });
+    }
+    Ok(constraints)
+}
+
+/// Indexes that don't back a primary key, unique or exclusion constraint.
+async fn fetch_indexes(
+    conn: &mut AnyConnection,
+    regclass: &str,
+) -> Result<Vec<String>, McpSqlError> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT pg_get_indexdef(i.indexrelid) \
+         FROM pg_index i \
+         WHERE i.indrelid = to_regclass($1) \
+           AND NOT EXISTS (SELECT 1 FROM pg_constraint c \
+                           WHERE c.conindid = i.indexrelid AND c.contype IN ('p', 'u', 'x')) \
+         ORDER BY 1",
+    )
+    .bind(regclass)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(rows.into_iter().map(|(definition,)| definition).collect())
+}
+
+fn render(
+    relation: &Relation,
+    columns: &[Column],
+    sequences: &[OwnedSequence],
+    constraints: &[Constraint],
+    indexes: &[String],
+) -> String {
+    let table = &relation.name;
+    let mut ddl = String::new();
+    for sequence in sequences {
+        ddl.push_str(&sequence_definition(sequence));
+        ddl.push_str("\n\n");
+    }
+
+    let unlogged = if relation.unlogged { "UNLOGGED " } else { "" };
+    ddl.push_str(&format!("CREATE {unlogged}TABLE {table}"));
+
+    let checks = constraints.iter().filter(|c| c.kind == 'c');
+    if let Some((parent, bound)) = &relation.partition_of {
+        // Partitions take their columns from the parent
+        ddl.push_str(&format!(" PARTITION OF {parent}"));
+        let lines: Vec<String> = checks
+            .map(|c| format!("    CONSTRAINT {} {}", c.name, c.definition))
+            .collect();
+        if !lines.is_empty() {
+            ddl.push_str(&format!(" (\n{}\n)", lines.join(",\n")));
+        }
+        ddl.push_str(&format!("\n{bound}"));
+    } else {
+        let lines: Vec<String> = columns
+            .iter()
+            .filter(|c| !c.inherited)
+            .map(column_definition)
+            .chain(checks.map(|c| format!("    CONSTRAINT {} {}", c.name, c.definition)))
+            .collect();
+        if lines.is_empty() {
+            ddl.push_str(" (\n)");
+        } else {
+            ddl.push_str(&format!(" (\n{}\n)", lines.join(",\n")));
+        }
+        if let Some(parents) = &relation.inherits {
+            ddl.push_str(&format!("\nINHERITS ({parents})"));
+        }
+    }
+    if let Some(key) = &relation.partition_key {
+        ddl.push_str(&format!("\nPARTITION BY {key}"));
+    }
+    if let Some(options) = &relation.options {
+        ddl.push_str(&format!("\nWITH ({options})"));
+    }
+    ddl.push_str(";\n");
+
+    for sequence in sequences {
+        ddl.push_str(&format!(
+            "\nALTER SEQUENCE {} OWNED BY {table}.{};\n",
+            sequence.name, sequence.column
+        ));
+    }
+    if let Some(comment) = &relation.comment {
+        ddl.push_str(&format!("\nCOMMENT ON TABLE {table} IS {};\n", quote_literal(comment)));
+    }
+    for column in columns {
+        if let Some(comment) = &column.comment {
+            ddl.push_str(&format!(
+                "\nCOMMENT ON COLUMN {table}.{} IS {};\n",
+                column.name,
+                quote_literal(comment)
+            ));
+        }
+    }
+
+    // ONLY keeps the constraint off existing partitions; a partitioned
+    // table's keys must cover its partitions
+    let alter = if relation.partitioned { "ALTER TABLE" } else { "ALTER TABLE ONLY" };
+    for kind in ['p', 'u', 'x'] {
+        for c in constraints.iter().filter(|c| c.kind == kind) {
+            ddl.push_str(&format!("\n{alter} {table}\n    ADD CONSTRAINT {} {};\n", c.name, c.definition));
+        }
+    }
+    for index in indexes {
+        ddl.push_str(&format!("\n{index};\n"));
+    }
+    for c in constraints.iter().filter(|c| c.kind == 'f') {
+        ddl.push_str(&format!("\n{alter} {table}\n    ADD CONSTRAINT {} {};\n", c.name, c.definition));
+    }
+    ddl.trim_end().to_string()
+}
+
+fn column_definition(column: &Column) -> String {
+    let mut line = format!("    {} {}", column.name, column.data_type);
+    if let Some(collation) = &column.collation {
+        line.push_str(&format!(" COLLATE {collation}"));
+    }
+    if let Some(expression) = &column.generated {
+        line.push_str(&format!(" GENERATED ALWAYS AS ({expression}) STORED"));
+    }
+    if let Some(default) = &column.default {
+        line.push_str(&format!(" DEFAULT {default}"));
+    }
+    match column.identity {
+        Some('a') => line.push_str(" GENERATED ALWAYS AS IDENTITY"),
+        Some('d') => line.push_str(" GENERATED BY DEFAULT AS IDENTITY"),
+        _ => {}
+    }
+    if column.not_null {
+        line.push_str(" NOT NULL");
+    }
+    line
+}
+
+fn sequence_definition(sequence: &OwnedSequence) -> String {
+    let mut ddl = format!("CREATE SEQUENCE {}", sequence.name);
+    if sequence.data_type != "bigint" {
+        ddl.push_str(&format!("\n    AS {}", sequence.data_type));
+    }
+    ddl.push_str(&format!(
+        "\n    START WITH {}\n    INCREMENT BY {}",
+        sequence.start, sequence.increment
+    ));
+    // Bounds at the defaults for the direction and type are left implicit
+    let type_max = match sequence.data_type.as_str() {
+        "smallint" => i64::from(i16::MAX),
+        "integer" => i64::from(i32::MAX),
+        _ => i64::MAX,
+    };
+    let (default_min, default_max) = if sequence.increment > 0 {
+        (1, type_max)
+    } else {
+        (-type_max - 1, -1)
+    };
+    if sequence.min == default_min {
+        ddl.push_str("\n    NO MINVALUE");
+    } else {
+        ddl.push_str(&format!("\n    MINVALUE {}", sequence.min));
+    }
+    if sequence.max == default_max {
+        ddl.push_str("\n    NO MAXVALUE");
+    } else {
+        ddl.push_str(&format!("\n    MAXVALUE {}", sequence.max));
+    }
+    ddl.push_str(&format!("\n    CACHE {}", sequence.cache));
+    if sequence.cycle {
+        ddl.push_str("\n    CYCLE");
+    }
+    ddl.push(';');
+    ddl
+}
+
+/// Quote a string literal the way PostgreSQL's `quote_literal` does.
+fn quote_literal(value: &str) -> String {
+    let escaped = value.replace('\'', "''");
+    if value.contains('\\') {
+        format!("E'{}'", escaped.replace('\\', "\\\\"))
+    } else {
+        format!("'{escaped}'")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_table() {
+        let relation = Relation {
+            name: "public.users".to_string(),
+            comment: Some("People who can log in".to_string()),
+            ..Default::default()
+        };
+        let columns = vec![
+            Column {
+                identity: Some('a'),
+                not_null: true,
+                ..column("id", "bigint")
+            },
+            Column {
+                not_null: true,
+                comment: Some("Login name".to_string()),
+                ..column("email", "character varying(255)")
+            },
+            Column {
+                default: Some("0".to_string()),
+                ..column("age", "integer")
+            },
+        ];
+        let constraints = vec![
+            Constraint {
+                name: "users_pkey".to_string(),
+                kind: 'p',
+                definition: "PRIMARY KEY (id)".to_string(),
+            },
+            Constraint {
+                name: "users_age_check".to_string(),
+                kind: 'c',
+                definition: "CHECK (age >= 0)".to_string(),
+            },
+            Constraint {
+                name: "users_org_fkey".to_string(),
+                kind: 'f',
+                definition: "FOREIGN KEY (org_id) REFERENCES public.orgs(id)".to_string(),
+            },
+        ];
+        let indexes = vec!["CREATE INDEX users_age_idx ON public.users USING btree (age)".to_string()];
+
+        let ddl = render(&relation, &columns, &[], &constraints, &indexes);
+        assert_eq!(
+            ddl,
+            "CREATE TABLE public.users (\n\
+             \x20   id bigint GENERATED ALWAYS AS IDENTITY NOT NULL,\n\
+             \x20   email character varying(255) NOT NULL,\n\
+             \x20   age integer DEFAULT 0,\n\
+             \x20   CONSTRAINT users_age_check CHECK (age >= 0)\n\
+             );\n\
+             \n\
+             COMMENT ON TABLE public.users IS 'People who can log in';\n\
+             \n\
+             COMMENT ON COLUMN public.users.email IS 'Login name';\n\
+             \n\
+             ALTER TABLE ONLY public.users\n\
+             \x20   ADD CONSTRAINT users_pkey PRIMARY KEY (id);\n\
+             \n\
+             CREATE INDEX users_age_idx ON public.users USING btree (age);\n\
+             \n\
+             ALTER TABLE ONLY public.users\n\
+             \x20   ADD CONSTRAINT users_org_fkey FOREIGN KEY (org_id) REFERENCES public.orgs(id);"
+        );
+    }
+
+    #[test]
+    fn test_render_partitions() {
+        let parent = Relation {
+            name: "public.events".to_string(),
+            partitioned: true,
+            partition_key: Some("RANGE (created_at)".to_string()),
+            ..Default::default()
+        };
+        let columns = vec![column("created_at", "timestamp with time zone")];
+        let ddl = render(&parent, &columns, &[], &[], &[]);
+        assert!(ddl.ends_with(")\nPARTITION BY RANGE (created_at);"));
+
+        let child = Relation {
+            name: "public.events_2024".to_string(),
+            partition_of: Some((
+                "events".to_string(),
+                "FOR VALUES FROM ('2024-01-01') TO ('2025-01-01')".to_string(),
+            )),
+            unlogged: true,
+            ..Default::default()
+        };
+        let ddl = render(&child, &columns, &[], &[], &[]);
+        assert_eq!(
+            ddl,
+            "CREATE UNLOGGED TABLE public.events_2024 PARTITION OF events\n\
+             FOR VALUES FROM ('2024-01-01') TO ('2025-01-01');"
+        );
+    }
+
+    #[test]
+    fn test_render_serial() {
+        let relation = Relation {
+            name: "public.tags".to_string(),
+            ..Default::default()
+        };
+        let columns = vec![Column {
+            default: Some("nextval('public.tags_id_seq'::regclass)".to_string()),
+            not_null: true,
+            ..column("id", "integer")
+        }];
+        let sequences = vec![OwnedSequence {
+            name: "public.tags_id_seq".to_string(),
+            column: "id".to_string(),
+            data_type: "integer".to_string(),
+            start: 1,
+            increment: 1,
+            min: 1,
+            max: i64::from(i32::MAX),
+            cache: 1,
+            cycle: false,
+        }];
+        let ddl = render(&relation, &columns, &sequences, &[], &[]);
+        assert_eq!(
+            ddl,
+            "CREATE SEQUENCE public.tags_id_seq\n\
+             \x20   AS integer\n\
+             \x20   START WITH 1\n\
+             \x20   INCREMENT BY 1\n\
+             \x20   NO MINVALUE\n\
+             \x20   NO MAXVALUE\n\
+             \x20   CACHE 1;\n\
+             \n\
+             CREATE TABLE public.tags (\n\
+             \x20   id integer DEFAULT nextval('public.tags_id_seq'::regclass) NOT NULL\n\
+             );\n\
+             \n\
+             ALTER SEQUENCE public.tags_id_seq OWNED BY public.tags.id;"
+        );
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("it's"), "'it''s'");
+        assert_eq!(quote_literal("a\\b"), "E'a\\\\b'");
+    }
+}
@@ -1,4 +1,8 @@
 use rmcp::model::ErrorData;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::exec::Cancellation;
 
 #[derive(Debug, thiserror::Error)]
 pub enum McpSqlError {
@@ -14,31 +18,361 @@ pub enum McpSqlError {
     #[error("Ambiguous database: multiple databases connected, specify the 'database' parameter")]
     AmbiguousDatabase,
 
+    #[error("Table '{0}' not found")]
+    TableNotFound(String),
+
     #[error("Invalid SQL: {0}")]
     InvalidSql(String),
 
-    #[error("Query timed out after {0} seconds")]
-    QueryTimeout(u64),
+    #[error("Query timed out after {0} seconds ({1})")]
+    QueryTimeout(u64, Cancellation),
+
+    #[error("Statement rejected by policy: {0}")]
+    PolicyViolation(String),
+
+    #[error("Write affected {0} rows, exceeding the limit of {1}; the change was rolled back")]
+    TooManyRowsAffected(u64, u64),
+
+    #[error("Query rejected by cost limit: {0}")]
+    QueryTooExpensive(String, Value),
+
+    #[error("Rate limit exceeded: {0}; retry after {1} seconds")]
+    RateLimited(String, u64),
+
+    #[error("Pending change not found: {0}")]
+    PendingChangeNotFound(String),
 
     #[error("{0}")]
     Other(String),
 }
 
+/// Machine-readable description of an error, sent as the MCP error `data`
+/// so clients can react to it without parsing the message.
+#[derive(Debug, Default, Serialize)]
+pub struct ErrorDetails {
+    /// Broad kind of failure, e.g. `syntax_error` or `undefined_column`.
+    pub category: &'static str,
+    /// SQLSTATE on PostgreSQL and MySQL, the extended result code on SQLite.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// MySQL's numeric error code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor_code: Option<u16>,
+    /// 1-based character position in the SQL where the error was detected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+    /// The table, column or constraint the error is about.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
 impl McpSqlError {
     pub fn to_mcp_error(&self) -> ErrorData {
+        self.mcp_error(None)
+    }
+
+    /// Like `to_mcp_error`, using `sql` to locate the error when the backend
+    /// doesn't report a position itself.
+    pub fn to_mcp_error_for_sql(&self, sql: &str) -> ErrorData {
+        self.mcp_error(Some(sql))
+    }
+
+    /// Like `to_mcp_error_for_sql`, for errors raised while running `sql`
+    /// behind `prefix`, such as `EXPLAIN `.
+    pub fn to_mcp_error_for_prefixed_sql(&self, prefix: &str, sql: &str) -> ErrorData {
+        self.to_mcp_error_with_details(self.details_for_prefixed_sql(prefix, sql))
+    }
+
+    fn mcp_error(&self, sql: Option<&str>) -> ErrorData {
+        self.to_mcp_error_with_details(self.details(sql))
+    }
+
+    /// Build the MCP error with `details` (e.g. extended with suggestions)
+    /// as its data.
+    pub fn to_mcp_error_with_details(&self, details: ErrorDetails) -> ErrorData {
+        let data = serde_json::to_value(details).ok();
         match self {
-            McpSqlError::ReadOnly(_) | McpSqlError::InvalidSql(_) => {
-                ErrorData::invalid_params(self.to_string(), None)
+            McpSqlError::ReadOnly(_)
+            | McpSqlError::InvalidSql(_)
+            | McpSqlError::PolicyViolation(_)
+            | McpSqlError::TooManyRowsAffected(..)
+            | McpSqlError::QueryTooExpensive(..)
+            | McpSqlError::RateLimited(..) => ErrorData::invalid_params(self.to_string(), data),
+            McpSqlError::DatabaseNotFound(_)
+            | McpSqlError::AmbiguousDatabase
+            | McpSqlError::TableNotFound(_)
+            | McpSqlError::PendingChangeNotFound(_) => {
+                ErrorData::invalid_params(self.to_string(), data)
             }
-            McpSqlError::DatabaseNotFound(_) | McpSqlError::AmbiguousDatabase => {
-                ErrorData::invalid_params(self.to_string(), None)
+            McpSqlError::QueryTimeout(..) => ErrorData::internal_error(self.to_string(), data),
+            McpSqlError::Database(_) | McpSqlError::Other(_) => {
+                ErrorData::internal_error(self.to_string(), data)
             }
-            McpSqlError::QueryTimeout(_) => {
-                ErrorData::internal_error(self.to_string(), None)
+        }
+    }
+
+    /// Structured details for an error raised while running `sql` behind
+    /// `prefix`. The position is given within `sql`, and left out if it
+    /// points into the prefix.
+    pub fn details_for_prefixed_sql(&self, prefix: &str, sql: &str) -> ErrorDetails {
+        let mut details = self.details(Some(&format!("{prefix}{sql}")));
+        let offset = prefix.chars().count();
+        details.position = details.position.and_then(|p| p.checked_sub(offset)).filter(|p| *p > 0);
+        details
+    }
+
+    /// Structured details for this error.
+    pub fn details(&self, sql: Option<&str>) -> ErrorDetails {
+        let category = match self {
+            McpSqlError::Database(e) => return database_details(e, sql),
+            McpSqlError::ReadOnly(_) => "read_only",
+            McpSqlError::DatabaseNotFound(_) => "database_not_found",
+            McpSqlError::AmbiguousDatabase => "ambiguous_database",
+            McpSqlError::TableNotFound(_) => "undefined_table",
+            McpSqlError::InvalidSql(_) => "invalid_sql",
+            McpSqlError::QueryTimeout(..) => "timeout",
+            McpSqlError::PolicyViolation(_) => "policy_violation",
+            McpSqlError::TooManyRowsAffected(..) => "too_many_rows_affected",
+            McpSqlError::QueryTooExpensive(..) => "cost_limit",
+            McpSqlError::RateLimited(..) => "rate_limited",
+            McpSqlError::PendingChangeNotFound(_) => "pending_change_not_found",
+            McpSqlError::Other(_) => "other",
+        };
+        let mut details = ErrorDetails {
+            category,
+            ..Default::default()
+        };
+        match self {
+            McpSqlError::TableNotFound(table) => details.object = Some(table.clone()),
+            McpSqlError::QueryTooExpensive(_, plan) => {
+                details.extra.insert("plan".to_string(), plan.clone());
             }
-            McpSqlError::Database(_) | McpSqlError::Other(_) => {
-                ErrorData::internal_error(self.to_string(), None)
+            McpSqlError::RateLimited(_, retry_after) => {
+                details
+                    .extra
+                    .insert("retry_after_secs".to_string(), Value::from(*retry_after));
+            }
+            McpSqlError::TooManyRowsAffected(affected, max) => {
+                details.extra.insert("rows_affected".to_string(), Value::from(*affected));
+                details.extra.insert("max_affected_rows".to_string(), Value::from(*max));
+            }
+            _ => {}
+        }
+        details
+    }
+}
+
+fn database_details(err: &sqlx::Error, sql: Option<&str>) -> ErrorDetails {
+    let db = match err {
+        sqlx::Error::Database(db) => db,
+        sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_) => return category_only("connection"),
+        sqlx::Error::RowNotFound => return category_only("not_found"),
+        sqlx::Error::ColumnDecode { .. } | sqlx::Error::Decode(_) => {
+            return category_only("unsupported_type")
+        }
+        _ => return category_only("database_error"),
+    };
+
+    let mut details = ErrorDetails {
+        code: db.code().map(|c| c.into_owned()),
+        object: db.constraint().or(db.table()).map(str::to_string),
+        ..Default::default()
+    };
+    if let Some(pg) = db.try_downcast_ref::<sqlx::postgres::PgDatabaseError>() {
+        details.hint = pg.hint().map(str::to_string);
+        if let Some(sqlx::postgres::PgErrorPosition::Original(position)) = pg.position() {
+            details.position = Some(position);
+        }
+        if let Some(column) = pg.column() {
+            details.object = Some(column.to_string());
+        }
+    } else if let Some(mysql) = db.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+        details.vendor_code = Some(mysql.number());
+    }
+
+    let message = db.message();
+    let sqlstate = details
+        .code
+        .as_deref()
+        .filter(|_| db.try_downcast_ref::<sqlx::sqlite::SqliteError>().is_none());
+    details.category = sqlstate
+        .and_then(sqlstate_category)
+        .unwrap_or_else(|| message_category(message));
+    if details.object.is_none() {
+        details.object = offending_object(message);
+    }
+    if details.position.is_none() {
+        if let Some(sql) = sql {
+            details.position = near_snippet(message)
+                .or(details.object.as_deref())
+                .and_then(|needle| char_position(sql, needle));
+        }
+    }
+    if details.hint.is_none() {
+        details.hint = match details.category {
+            "undefined_table" => Some("Use list_tables to see the available tables".to_string()),
+            "undefined_column" => {
+                Some("Use describe_table to see the table's columns".to_string())
             }
+            _ => None,
+        };
+    }
+    details
+}
+
+fn category_only(category: &'static str) -> ErrorDetails {
+    ErrorDetails {
+        category,
+        ..Default::default()
+    }
+}
+
+/// Map a SQLSTATE to a category. MySQL reuses some generic states (42000 for
+/// both syntax and access errors), so those fall back to the message.
+fn sqlstate_category(code: &str) -> Option<&'static str> {
+    let category = match code {
+        "42601" => "syntax_error",
+        "42P01" | "42S02" => "undefined_table",
+        "42703" | "42S22" => "undefined_column",
+        "42883" | "42704" => "undefined_object",
+        "42501" => "permission_denied",
+        "25006" => "read_only",
+        "57014" => "timeout",
+        "42804" | "42846" | "22P02" => "type_mismatch",
+        _ if code.starts_with("23") => "constraint_violation",
+        _ if code.starts_with("08") => "connection",
+        _ if code.starts_with("40") => "transaction_rollback",
+        _ => return None,
+    };
+    Some(category)
+}
+
+fn message_category(message: &str) -> &'static str {
+    let lower = message.to_lowercase();
+    if lower.contains("syntax error") || lower.contains("error in your sql syntax") {
+        "syntax_error"
+    } else if lower.contains("no such table") || lower.contains("doesn't exist") {
+        "undefined_table"
+    } else if lower.contains("no such column") || lower.contains("unknown column") {
+        "undefined_column"
+    } else if lower.contains("constraint failed") || lower.contains("duplicate entry") {
+        "constraint_violation"
+    } else if lower.contains("readonly database") || lower.contains("read-only") {
+        "read_only"
+    } else if lower.contains("access denied") || lower.contains("permission denied") {
+        "permission_denied"
+    } else {
+        "database_error"
+    }
+}
+
+/// Pull the table, column or constraint name out of common backend messages.
+fn offending_object(message: &str) -> Option<String> {
+    for prefix in [
+        "no such table: ",
+        "no such column: ",
+        "UNIQUE constraint failed: ",
+        "NOT NULL constraint failed: ",
+        "CHECK constraint failed: ",
+    ] {
+        if let Some(rest) = message.split_once(prefix).map(|(_, r)| r) {
+            return rest.split_whitespace().next().map(|s| s.trim_end_matches(',').to_string());
         }
     }
+    // PostgreSQL: relation "x" does not exist / column "x" does not exist
+    for prefix in ["relation ", "column "] {
+        if let Some(rest) = message.strip_prefix(prefix) {
+            if rest.contains("does not exist") {
+                return Some(rest.split_whitespace().next()?.trim_matches('"').to_string());
+            }
+        }
+    }
+    // MySQL: Table 'db.x' doesn't exist / Unknown column 'x' in 'field list'
+    for prefix in ["Table '", "Unknown column '"] {
+        if let Some(rest) = message.strip_prefix(prefix) {
+            let name = rest.split('\'').next()?;
+            let name = if prefix == "Table '" {
+                name.rsplit('.').next().unwrap_or(name)
+            } else {
+                name
+            };
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// The SQL fragment a syntax error points at: `near "FORM"` on SQLite,
+/// `near 'FORM users' at line 1` on MySQL.
+fn near_snippet(message: &str) -> Option<&str> {
+    let rest = &message[message.find("near ")? + "near ".len()..];
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    let snippet = &rest[..rest.rfind(quote)?];
+    (!snippet.is_empty()).then_some(snippet)
+}
+
+/// 1-based character position of `needle` in `sql`, matched case-insensitively.
+fn char_position(sql: &str, needle: &str) -> Option<usize> {
+    let byte = sql.to_lowercase().find(&needle.to_lowercase())?;
+    Some(sql.get(..byte)?.chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_parsing() {
+        assert_eq!(message_category("no such column: nme"), "undefined_column");
+        assert_eq!(message_category("near \"FORM\": syntax error"), "syntax_error");
+        assert_eq!(
+            message_category("UNIQUE constraint failed: users.email"),
+            "constraint_violation"
+        );
+
+        assert_eq!(offending_object("no such table: userz").as_deref(), Some("userz"));
+        assert_eq!(
+            offending_object("relation \"userz\" does not exist").as_deref(),
+            Some("userz")
+        );
+        assert_eq!(
+            offending_object("Table 'app.userz' doesn't exist").as_deref(),
+            Some("userz")
+        );
+        assert_eq!(
+            offending_object("Unknown column 'nme' in 'field list'").as_deref(),
+            Some("nme")
+        );
+
+        assert_eq!(near_snippet("near \"FORM\": syntax error"), Some("FORM"));
+        assert_eq!(
+            near_snippet("You have an error in your SQL syntax; check the manual near 'FORM users' at line 1"),
+            Some("FORM users")
+        );
+        assert_eq!(char_position("SELECT * FORM users", "form"), Some(10));
+    }
+
+    #[test]
+    fn test_sqlstate_category() {
+        assert_eq!(sqlstate_category("42P01"), Some("undefined_table"));
+        assert_eq!(sqlstate_category("23505"), Some("constraint_violation"));
+        assert_eq!(sqlstate_category("42000"), None);
+    }
+
+    #[test]
+    fn test_details_extra() {
+        let details = McpSqlError::RateLimited("busy".to_string(), 7).details(None);
+        let value = serde_json::to_value(details).unwrap();
+        assert_eq!(value["category"], "rate_limited");
+        assert_eq!(value["retry_after_secs"], 7);
+        assert!(value.get("code").is_none());
+    }
 }
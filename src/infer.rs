@@ -0,0 +1,265 @@
+//! Guessing the foreign keys a database does not declare, for schemas
+//! that join tables by convention rather than by constraint.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::Row;
+
+use crate::db::catalog::{ColumnInfo, TableInfo};
+use crate::db::dialect::quote_identifier;
+use crate::db::exec;
+use crate::db::ident::QualifiedName;
+use crate::db::{DatabaseEntry, DbBackend};
+use crate::suggest::unqualified;
+
+/// A column that looks like a foreign key: its name points at a table
+/// (`user_id` at `users`) whose key has a compatible type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InferredKey {
+    pub table: String,
+    pub column: String,
+    pub referenced_table: String,
+    pub referenced_column: String,
+    /// Distinct non-null values sampled from the column, when checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampled: Option<i64>,
+    /// How many of those values exist in the referenced column.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<i64>,
+}
+
+impl InferredKey {
+    /// The referenced `table.column`, as in [`ColumnInfo::foreign_key`].
+    pub fn reference(&self) -> String {
+        format!("{}.{}", self.referenced_table, self.referenced_column)
+    }
+}
+
+/// Relationships implied by column names in `tables`, for columns without a
+/// declared foreign key. A column `<name>_id` (or `<name>Id`) references the
+/// table named `<name>`, or its plural, through that table's single-column
+/// primary key, provided the types are compatible. Tables in the column's
+/// own schema are preferred.
+pub fn infer(tables: &[TableInfo]) -> Vec<InferredKey> {
+    let targets: Vec<(&TableInfo, &ColumnInfo)> = tables
+        .iter()
+        .filter(|t| !t.is_view())
+        .filter_map(|t| key_column(t).map(|key| (t, key)))
+        .collect();
+
+    let mut keys = Vec::new();
+    for table in tables.iter().filter(|t| !t.is_view()) {
+        for column in table.columns.iter().filter(|c| c.foreign_key.is_none()) {
+            let Some(stem) = stem(&column.name) else {
+                continue;
+            };
+            let names = table_names(&stem);
+            let matches = targets.iter().filter(|(target, key)| {
+                names.iter().any(|n| unqualified(&target.name).eq_ignore_ascii_case(n))
+                    && !(target.name == table.name && key.name == column.name)
+                    && compatible(&column.data_type, &key.data_type)
+            });
+            let schema = schema_of(&table.name);
+            let best = matches.min_by_key(|(target, _)| schema_of(&target.name) != schema);
+            if let Some((target, key)) = best {
+                keys.push(InferredKey {
+                    table: table.name.clone(),
+                    column: column.name.clone(),
+                    referenced_table: target.name.clone(),
+                    referenced_column: key.name.clone(),
+                    sampled: None,
+                    matched: None,
+                });
+            }
+        }
+    }
+    keys
+}
+
+/// Mark the columns of `table`, as returned by `describe_table`, that `keys`
+/// guess are foreign keys with an `inferred_foreign_key` of `table.column`.
+/// An unqualified `table` matches keys in any schema.
+pub fn apply_to_columns(keys: &[InferredKey], table: &QualifiedName, columns: &mut [Value]) {
+    let name = table.to_string();
+    let keys: Vec<&InferredKey> = keys
+        .iter()
+        .filter(|k| {
+            k.table == name || (table.schema.is_none() && unqualified(&k.table) == table.name)
+        })
+        .collect();
+    for column in columns {
+        let name = column.get("name").and_then(Value::as_str);
+        let Some(key) = keys.iter().find(|k| name == Some(k.column.as_str())) else {
+            continue;
+        };
+        if let Value::Object(map) = column {
+            if map.get("foreign_key").map_or(true, Value::is_null) {
+                map.insert("inferred_foreign_key".to_string(), Value::String(key.reference()));
+            }
+        }
+    }
+}
+
+/// Check each key against the data: sample up to `sample` distinct values of
+/// the column and count those found in the referenced column. Keys none of
+/// whose sampled values match are dropped. The checks run read-only and
+/// share `timeout` between them; a key whose check fails, or that is not
+/// reached in time, is kept unchecked.
+pub async fn verify(
+    entry: &DatabaseEntry,
+    keys: &mut Vec<InferredKey>,
+    sample: usize,
+    timeout: Duration,
+) {
+    let deadline = Instant::now() + timeout;
+    for key in keys.iter_mut() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let sql = verification_sql(entry.backend, key, sample);
+        let Ok(rows) = exec::fetch_all(entry, &sql, remaining, true).await else {
+            continue;
+        };
+        let Some(row) = rows.first() else {
+            continue;
+        };
+        if let (Ok(sampled), Ok(matched)) = (row.try_get::<i64, _>(0), row.try_get::<i64, _>(1)) {
+            key.sampled = Some(sampled);
+            key.matched = Some(matched);
+        }
+    }
+    keys.retain(|key| !matches!((key.sampled, key.matched), (Some(n), Some(0)) if n > 0));
+}
+
+/// The query [`verify`] runs for `key`, returning the number of sampled
+/// values and how many of them were found.
+pub fn verification_sql(backend: DbBackend, key: &InferredKey, sample: usize) -> String {
+    let column = quote_identifier(backend, &key.column);
+    format!(
+        "SELECT COUNT(*), COUNT(r.{referenced}) \
+         FROM (SELECT DISTINCT {column} AS v FROM {table} \
+               WHERE {column} IS NOT NULL LIMIT {sample}) s \
+         LEFT JOIN {referenced_table} r ON r.{referenced} = s.v",
+        referenced = quote_identifier(backend, &key.referenced_column),
+        table = table_name(&key.table).quoted(backend),
+        referenced_table = table_name(&key.referenced_table).quoted(backend),
+    )
+}
+
+/// The column other tables would reference: the primary key if it has a
+/// single column, or else a column named `id`.
+fn key_column(table: &TableInfo) -> Option<&ColumnInfo> {
+    let mut primary_key = table.columns.iter().filter(|c| c.primary_key);
+    match (primary_key.next(), primary_key.next()) {
+        (Some(key), None) => Some(key),
+        (Some(_), Some(_)) => None,
+        (None, _) => table.columns.iter().find(|c| c.name.eq_ignore_ascii_case("id")),
+    }
+}
+
+/// The name a key column refers to: `user` for `user_id`, `userId` or
+/// `UserID`.
+fn stem(column: &str) -> Option<String> {
+    let stem = column
+        .strip_suffix("_id")
+        .or_else(|| column.strip_suffix("_ID"))
+        .or_else(|| column.strip_suffix("Id"))
+        .or_else(|| column.strip_suffix("ID"))?;
+    let stem = stem.trim_end_matches('_');
+    (!stem.is_empty()).then(|| stem.to_lowercase())
+}
+
+/// Table names `stem` may refer to: itself and its plurals.
+fn table_names(stem: &str) -> Vec<String> {
+    let mut names = vec![stem.to_string(), format!("{stem}s"), format!("{stem}es")];
+    if let Some(base) = stem.strip_suffix('y') {
+        names.push(format!("{base}ies"));
+    }
+    names
+}
+
+/// Whether values of these column types can be equal: both integers, both
+/// strings, or the same type. SQLite columns may have no declared type,
+/// which is compatible with anything.
+fn compatible(a: &str, b: &str) -> bool {
+    a.is_empty() || b.is_empty() || type_family(a) == type_family(b)
+}
+
+fn type_family(data_type: &str) -> String {
+    let data_type = data_type.to_lowercase();
+    let base = data_type.split(['(', ' ']).next().unwrap_or_default();
+    if base.contains("int") || base.contains("serial") {
+        "integer".to_string()
+    } else if base.contains("char") || base.contains("text") || base == "string" {
+        "text".to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+fn schema_of(table: &str) -> Option<&str> {
+    table.split_once('.').map(|(schema, _)| schema)
+}
+
+/// A catalog table name as a name to query.
+fn table_name(table: &str) -> QualifiedName {
+    match table.split_once('.') {
+        Some((schema, name)) => QualifiedName::new(Some(schema), name),
+        None => QualifiedName::new(None, table),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("user_id").as_deref(), Some("user"));
+        assert_eq!(stem("categoryId").as_deref(), Some("category"));
+        assert_eq!(stem("OwnerID").as_deref(), Some("owner"));
+        assert_eq!(stem("id"), None);
+        assert_eq!(stem("paid"), None);
+        assert!(table_names("category").contains(&"categories".to_string()));
+    }
+
+    #[test]
+    fn test_infer() {
+        let declared = ColumnInfo {
+            foreign_key: Some("app.users.id".to_string()),
+            ..ColumnInfo::fixture("owner_id", "integer")
+        };
+        let tables = vec![
+            TableInfo::fixture(
+                "app.users",
+                vec![ColumnInfo::fixture("id", "integer"), ColumnInfo::fixture("name", "text")],
+            ),
+            TableInfo::fixture("archive.users", vec![ColumnInfo::fixture("id", "integer")]),
+            TableInfo::fixture("app.categories", vec![ColumnInfo::fixture("id", "bigint")]),
+            TableInfo::fixture("app.tags", vec![ColumnInfo::fixture("id", "uuid")]),
+            TableInfo::fixture(
+                "app.posts",
+                vec![
+                    ColumnInfo::fixture("id", "integer"),
+                    ColumnInfo::fixture("user_id", "int4"),
+                    ColumnInfo::fixture("categoryId", "integer"),
+                    ColumnInfo::fixture("tag_id", "integer"),
+                    ColumnInfo::fixture("session_id", "text"),
+                    declared,
+                ],
+            ),
+        ];
+        let keys: Vec<(String, String)> =
+            infer(&tables).iter().map(|k| (k.column.clone(), k.reference())).collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("user_id".to_string(), "app.users.id".to_string()),
+                ("categoryId".to_string(), "app.categories.id".to_string()),
+            ]
+        );
+    }
+}
@@ -1,5 +1,13 @@
+pub mod annotations;
 pub mod db;
 pub mod demo;
 pub mod error;
+pub mod infer;
+pub mod pending;
+pub mod policy;
+pub mod ratelimit;
 pub mod schema;
+pub mod search;
 pub mod server;
+pub mod sql;
+pub mod suggest;
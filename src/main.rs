@@ -1,6 +1,6 @@
 use anyhow::{bail, Result};
 use clap::Parser;
-use mcp_sql::{db, server};
+use mcp_sql::{annotations, db, policy, server};
 use rmcp::{transport::stdio, ServiceExt};
 use tracing_subscriber::EnvFilter;
 
@@ -27,10 +27,59 @@ struct Cli {
     #[arg(long, default_value = "100")]
     row_limit: u32,
 
-    /// Query timeout in seconds (default: 30)
+    /// Query timeout in seconds (default: 30). SQLite statements cannot be
+    /// interrupted and keep running in the background after a timeout
     #[arg(long, default_value = "30")]
     query_timeout: u64,
 
+    /// Hold write statements until they are confirmed with the confirm_write
+    /// tool. Requires --allow-write.
+    #[arg(long)]
+    confirm_writes: bool,
+
+    /// Seconds a write awaiting confirmation stays valid (default: 300)
+    #[arg(long, default_value = "300")]
+    confirm_ttl: u64,
+
+    /// Roll back any write statement that affects more than this many rows
+    #[arg(long)]
+    max_affected_rows: Option<u64>,
+
+    /// Reject UPDATE and DELETE statements that have no WHERE clause
+    #[arg(long)]
+    reject_unfiltered_writes: bool,
+
+    /// Statement kinds a database accepts, as [DATABASE=]KIND,KIND,...
+    /// (repeatable). Without DATABASE= it applies to every database that has
+    /// no policy of its own. Example: --allow-statements app=SELECT,INSERT
+    #[arg(long = "allow-statements")]
+    allow_statements: Vec<String>,
+
+    /// Reject statements whose EXPLAIN estimates exceed a limit, as
+    /// [DATABASE=]KEY=VALUE,... with keys cost, rows and full_scan_rows.
+    /// Example: --cost-limit app=cost=100000,full_scan_rows=50000
+    #[arg(long = "cost-limit")]
+    cost_limits: Vec<String>,
+
+    /// Per-database request limits, as [DATABASE=]KEY=VALUE,... with keys
+    /// concurrent, queries_per_minute and rows_per_minute.
+    /// Example: --rate-limit concurrent=2,queries_per_minute=60
+    #[arg(long = "rate-limit")]
+    rate_limits: Vec<String>,
+
+    /// YAML file describing tables and columns, as [DATABASE=]FILE
+    /// (repeatable). A SQLite database without one reads
+    /// <database file>.annotations.yaml when it exists.
+    /// Example: --annotations app=docs/app-annotations.yaml
+    #[arg(long = "annotations")]
+    annotations: Vec<String>,
+
+    /// Guess undeclared foreign keys from column names (user_id for
+    /// users.id) and show them, marked as inferred, in describe_table and
+    /// show_schema
+    #[arg(long)]
+    infer_relationships: bool,
+
     /// Start with a demo SQLite database pre-loaded with sample data
     #[arg(long)]
     demo: bool,
@@ -83,6 +132,10 @@ async fn main() -> Result<()> {
         }
     }
 
+    if cli.confirm_writes && !cli.allow_write {
+        bail!("--confirm-writes requires --allow-write");
+    }
+
     if all_urls.is_empty() {
         bail!("No database URLs provided. Use --url or --url-env to specify at least one database.");
     }
@@ -92,17 +145,90 @@ async fn main() -> Result<()> {
         allow_write = cli.allow_write,
         row_limit = cli.row_limit,
         query_timeout = cli.query_timeout,
+        confirm_writes = cli.confirm_writes,
+        max_affected_rows = ?cli.max_affected_rows,
         "Starting mcp-sql server"
     );
 
-    let db = db::DatabaseManager::new(&all_urls).await?;
+    let statement_policies = policy::StatementPolicies::parse(
+        &cli.allow_statements,
+        &[],
+        policy::StatementPolicy::parse,
+    )?;
+
+    let cost_limits = policy::CostLimits::parse(
+        &cli.cost_limits,
+        policy::CostLimit::KEYS,
+        policy::CostLimit::parse,
+    )?;
+
+    let rate_limits = policy::RateLimits::parse(
+        &cli.rate_limits,
+        policy::RateLimit::KEYS,
+        policy::RateLimit::parse,
+    )?;
+
+    let mut annotation_set = annotations::AnnotationSet::parse(&cli.annotations, &[], |path| {
+        annotations::Annotations::load(std::path::Path::new(path))
+    })?;
+
+    let db = db::DatabaseManager::new(&all_urls, !cli.allow_write).await?;
+
+    for name in statement_policies.databases() {
+        if db.resolve(Some(name)).is_err() {
+            bail!("--allow-statements refers to unknown database '{name}'");
+        }
+    }
+    for name in cost_limits.databases() {
+        if db.resolve(Some(name)).is_err() {
+            bail!("--cost-limit refers to unknown database '{name}'");
+        }
+    }
+    for name in rate_limits.databases() {
+        if db.resolve(Some(name)).is_err() {
+            bail!("--rate-limit refers to unknown database '{name}'");
+        }
+    }
+    for name in annotation_set.databases() {
+        if db.resolve(Some(name)).is_err() {
+            bail!("--annotations refers to unknown database '{name}'");
+        }
+    }
+
+    // Databases are connected in URL order
+    for (url, entry) in all_urls.iter().zip(&db.databases) {
+        if annotation_set.contains(&entry.name) {
+            continue;
+        }
+        if let Some(path) = annotations::sidecar_path(url).filter(|p| p.exists()) {
+            tracing::info!(database = entry.name, path = %path.display(), "Loading annotations");
+            annotation_set.insert(&entry.name, annotations::Annotations::load(&path)?);
+        }
+    }
 
     tracing::info!(
         databases = ?db.databases.iter().map(|d| format!("{}({})", d.name, d.backend.name())).collect::<Vec<_>>(),
         "Connected to databases"
     );
 
-    let service = server::McpSqlServer::new(db, cli.allow_write, cli.row_limit, cli.query_timeout);
+    let mut service = server::McpSqlServer::new(db, cli.allow_write, cli.row_limit, cli.query_timeout);
+    if cli.confirm_writes {
+        service = service.with_write_confirmation(std::time::Duration::from_secs(cli.confirm_ttl));
+    }
+    service = service
+        .with_statement_policies(statement_policies)
+        .with_cost_limits(cost_limits)
+        .with_rate_limits(rate_limits)
+        .with_annotations(annotation_set);
+    if let Some(max) = cli.max_affected_rows {
+        service = service.with_max_affected_rows(max);
+    }
+    if cli.reject_unfiltered_writes {
+        service = service.with_reject_unfiltered_writes();
+    }
+    if cli.infer_relationships {
+        service = service.with_inferred_relationships();
+    }
     let running = service.serve(stdio()).await?;
     running.waiting().await?;
 
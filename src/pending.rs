@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::McpSqlError;
+
+/// A write statement waiting for `confirm_write`.
+#[derive(Debug, Clone)]
+pub struct PendingWrite {
+    pub database: String,
+    pub sql: String,
+    created: Instant,
+}
+
+/// Write statements held back until they are explicitly confirmed.
+/// Entries expire `ttl` after they were submitted.
+pub struct PendingWrites {
+    ttl: Duration,
+    next_id: AtomicU64,
+    /// Per-process salt so ids from a previous server run never match.
+    salt: u64,
+    entries: Mutex<HashMap<String, PendingWrite>>,
+}
+
+impl PendingWrites {
+    pub fn new(ttl: Duration) -> Self {
+        let salt = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self {
+            ttl,
+            next_id: AtomicU64::new(1),
+            salt,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Hold `sql` for later confirmation and return its change id.
+    pub fn insert(&self, database: &str, sql: &str) -> String {
+        let seq = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let id = format!("chg-{seq}-{:06x}", (self.salt ^ seq.wrapping_mul(0x9e37_79b9_7f4a_7c15)) & 0xff_ffff);
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, p| p.created.elapsed() < self.ttl);
+        entries.insert(
+            id.clone(),
+            PendingWrite {
+                database: database.to_string(),
+                sql: sql.to_string(),
+                created: Instant::now(),
+            },
+        );
+        id
+    }
+
+    /// The database the pending write `id` targets, without consuming it.
+    pub fn database_of(&self, id: &str) -> Result<String, McpSqlError> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(id)
+            .map(|p| p.database.clone())
+            .ok_or_else(|| McpSqlError::PendingChangeNotFound(format!("'{id}' is unknown or was already confirmed")))
+    }
+
+    /// Remove and return the pending write `id`. Each id can be confirmed once.
+    pub fn take(&self, id: &str) -> Result<PendingWrite, McpSqlError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let pending = entries
+            .remove(id)
+            .ok_or_else(|| McpSqlError::PendingChangeNotFound(format!("'{id}' is unknown or was already confirmed")))?;
+        if pending.created.elapsed() >= self.ttl {
+            return Err(McpSqlError::PendingChangeNotFound(format!(
+                "'{id}' expired after {} seconds; submit the statement again",
+                self.ttl.as_secs()
+            )));
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_take() {
+        let pending = PendingWrites::new(Duration::from_secs(60));
+        let id = pending.insert("app", "DELETE FROM users WHERE id = 1");
+        let other = pending.insert("app", "DELETE FROM users WHERE id = 2");
+        assert_ne!(id, other);
+
+        assert_eq!(pending.database_of(&id).unwrap(), "app");
+        let write = pending.take(&id).unwrap();
+        assert_eq!(write.database, "app");
+        assert_eq!(write.sql, "DELETE FROM users WHERE id = 1");
+
+        // Each change can only be confirmed once
+        assert!(pending.take(&id).is_err());
+        assert!(pending.take("chg-unknown").is_err());
+    }
+
+    #[test]
+    fn test_expired() {
+        let pending = PendingWrites::new(Duration::ZERO);
+        let id = pending.insert("app", "DELETE FROM users");
+        let err = pending.take(&id).unwrap_err();
+        assert!(err.to_string().contains("expired"));
+    }
+}
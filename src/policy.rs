@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use crate::db::dialect::PlanSummary;
+use crate::error::McpSqlError;
+use crate::sql::{self, StatementKind};
+
+/// The statement kinds a database accepts, e.g. `SELECT,INSERT`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementPolicy {
+    allowed: Vec<StatementKind>,
+}
+
+impl StatementPolicy {
+    /// Parse a comma-separated list of statement keywords, each one of
+    /// [`StatementKind::KEYWORDS`]. `WITH` is not a kind of its own: CTE
+    /// statements are classified by their main statement.
+    pub fn parse(spec: &str) -> Result<Self, McpSqlError> {
+        let mut allowed = Vec::new();
+        for keyword in spec.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            match StatementKind::from_keyword(keyword) {
+                StatementKind::Other(_) => {
+                    return Err(McpSqlError::Other(format!(
+                        "Unknown statement kind '{keyword}' in '{spec}'; use any of: {}",
+                        StatementKind::KEYWORDS.join(", ")
+                    )))
+                }
+                kind => allowed.push(kind),
+            }
+        }
+        if allowed.is_empty() {
+            return Err(McpSqlError::Other(format!(
+                "Statement policy '{spec}' does not list any statement kinds"
+            )));
+        }
+        Ok(Self { allowed })
+    }
+
+    /// Check every statement in `sql` against the policy for `database`.
+    pub fn check(&self, database: &str, sql: &str) -> Result<(), McpSqlError> {
+        for kind in sql::classify(sql) {
+            if !self.allowed.contains(&kind) {
+                return Err(McpSqlError::PolicyViolation(format!(
+                    "{} statements are not allowed on database '{database}' (allowed: {})",
+                    kind.name(),
+                    self.describe()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        self.allowed
+            .iter()
+            .map(|k| k.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Settings keyed by database name, with an optional default for databases
+/// that don't have their own.
+#[derive(Debug, Clone)]
+pub struct PerDatabase<T> {
+    by_database: HashMap<String, T>,
+    default: Option<T>,
+}
+
+impl<T> Default for PerDatabase<T> {
+    fn default() -> Self {
+        Self {
+            by_database: HashMap::new(),
+            default: None,
+        }
+    }
+}
+
+impl<T> PerDatabase<T> {
+    /// Parse CLI values of the form `[DATABASE=]SPEC`. Specs that themselves
+    /// start with `KEY=` list their keys in `spec_keys`, so `rows=10` is read
+    /// as a default rather than a database named `rows`.
+    pub fn parse(
+        specs: &[String],
+        spec_keys: &[&str],
+        parse_spec: impl Fn(&str) -> Result<T, McpSqlError>,
+    ) -> Result<Self, McpSqlError> {
+        let mut settings = Self::default();
+        for spec in specs {
+            match spec.split_once('=') {
+                Some((database, rest)) if !spec_keys.contains(&database.trim()) => {
+                    settings
+                        .by_database
+                        .insert(database.trim().to_string(), parse_spec(rest)?);
+                }
+                _ => settings.default = Some(parse_spec(spec)?),
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Database names that have a setting of their own.
+    pub fn databases(&self) -> impl Iterator<Item = &str> {
+        self.by_database.keys().map(String::as_str)
+    }
+
+    /// Whether `database` has a setting of its own.
+    pub fn contains(&self, database: &str) -> bool {
+        self.by_database.contains_key(database)
+    }
+
+    /// Give `database` a setting of its own.
+    pub fn insert(&mut self, database: &str, value: T) {
+        self.by_database.insert(database.to_string(), value);
+    }
+
+    pub fn get(&self, database: &str) -> Option<&T> {
+        self.by_database.get(database).or(self.default.as_ref())
+    }
+}
+
+/// Statement policies for every database.
+pub type StatementPolicies = PerDatabase<StatementPolicy>;
+
+impl StatementPolicies {
+    /// Check `sql` against the policy for `database`, if there is one.
+    pub fn check(&self, database: &str, sql: &str) -> Result<(), McpSqlError> {
+        match self.get(database) {
+            Some(policy) => policy.check(database, sql),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Planner estimates a statement must stay under, e.g.
+/// `cost=100000,rows=1000000,full_scan_rows=50000`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostLimit {
+    pub max_cost: Option<f64>,
+    pub max_rows: Option<f64>,
+    /// Reject full scans of tables larger than this many rows.
+    pub max_full_scan_rows: Option<i64>,
+}
+
+impl CostLimit {
+    /// Keys accepted by `parse`.
+    pub const KEYS: &'static [&'static str] = &["cost", "rows", "full_scan_rows"];
+
+    pub fn parse(spec: &str) -> Result<Self, McpSqlError> {
+        let mut limit = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let invalid = || McpSqlError::Other(format!("Invalid cost limit '{part}' in '{spec}'"));
+            let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+            match key.trim() {
+                "cost" => limit.max_cost = Some(value.trim().parse().map_err(|_| invalid())?),
+                "rows" => limit.max_rows = Some(value.trim().parse().map_err(|_| invalid())?),
+                "full_scan_rows" => {
+                    limit.max_full_scan_rows = Some(value.trim().parse().map_err(|_| invalid())?)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if limit == Self::default() {
+            return Err(McpSqlError::Other(format!(
+                "Cost limit '{spec}' does not set any of: {}",
+                Self::KEYS.join(", ")
+            )));
+        }
+        Ok(limit)
+    }
+
+    /// Explain why `plan` exceeds this limit, or `None` if it doesn't.
+    pub fn violation(&self, plan: &PlanSummary) -> Option<String> {
+        if let (Some(max), Some(cost)) = (self.max_cost, plan.total_cost) {
+            if cost > max {
+                return Some(format!("estimated cost {cost} exceeds the limit of {max}"));
+            }
+        }
+        if let (Some(max), Some(rows)) = (self.max_rows, plan.estimated_rows) {
+            if rows > max {
+                return Some(format!("estimated {rows} rows exceeds the limit of {max}"));
+            }
+        }
+        if let Some(max) = self.max_full_scan_rows {
+            for scan in &plan.full_scans {
+                if let Some(rows) = scan.table_rows.filter(|&rows| rows > max) {
+                    return Some(format!(
+                        "full scan of '{}' (~{rows} rows) exceeds the limit of {max} rows; filter on an indexed column",
+                        scan.table
+                    ));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Cost limits for every database.
+pub type CostLimits = PerDatabase<CostLimit>;
+
+/// Per-database request limits, e.g.
+/// `concurrent=4,queries_per_minute=60,rows_per_minute=100000`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub max_concurrent: Option<u32>,
+    pub queries_per_minute: Option<u64>,
+    /// Budget of rows returned to the client over any one-minute window.
+    pub rows_per_minute: Option<u64>,
+}
+
+impl RateLimit {
+    /// Keys accepted by `parse`.
+    pub const KEYS: &'static [&'static str] = &["concurrent", "queries_per_minute", "rows_per_minute"];
+
+    pub fn parse(spec: &str) -> Result<Self, McpSqlError> {
+        let mut limit = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let invalid = || McpSqlError::Other(format!("Invalid rate limit '{part}' in '{spec}'"));
+            let (key, value) = part.split_once('=').ok_or_else(invalid)?;
+            // A limit of zero would refuse every request
+            let value: u64 = value.trim().parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+            match key.trim() {
+                "concurrent" => {
+                    limit.max_concurrent = Some(value.try_into().map_err(|_| invalid())?)
+                }
+                "queries_per_minute" => limit.queries_per_minute = Some(value),
+                "rows_per_minute" => limit.rows_per_minute = Some(value),
+                _ => return Err(invalid()),
+            }
+        }
+        if limit == Self::default() {
+            return Err(McpSqlError::Other(format!(
+                "Rate limit '{spec}' does not set any of: {}",
+                Self::KEYS.join(", ")
+            )));
+        }
+        Ok(limit)
+    }
+}
+
+/// Rate limits for every database.
+pub type RateLimits = PerDatabase<RateLimit>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_policy() {
+        let policy = StatementPolicy::parse("select, insert").unwrap();
+        assert!(policy.check("app", "SELECT * FROM users").is_ok());
+        assert!(policy.check("app", "INSERT INTO users (name) VALUES ('x')").is_ok());
+
+        let err = policy.check("app", "DELETE FROM users").unwrap_err().to_string();
+        assert!(err.contains("DELETE statements are not allowed on database 'app'"));
+        assert!(err.contains("allowed: SELECT, INSERT"));
+
+        // Every statement is checked, and CTEs count as their main statement
+        assert!(policy.check("app", "SELECT 1; DROP TABLE users").is_err());
+        assert!(policy.check("app", "WITH x AS (SELECT 1) DELETE FROM users").is_err());
+        assert!(policy.check("app", "WITH d AS (DELETE FROM users RETURNING *) SELECT 1").is_err());
+        assert!(policy.check("app", "EXPLAIN ANALYZE DELETE FROM users").is_err());
+
+        assert!(StatementPolicy::parse(" , ").is_err());
+        let err = StatementPolicy::parse("SELCT,INSERT").unwrap_err().to_string();
+        assert!(err.contains("Unknown statement kind 'SELCT'"));
+        assert!(err.contains("SELECT, VALUES, TABLE, INSERT"));
+        assert!(StatementPolicy::parse("select,with").is_err());
+    }
+
+    #[test]
+    fn test_statement_policies() {
+        let policies = StatementPolicies::parse(
+            &["SELECT".to_string(), "app=SELECT,INSERT,UPDATE".to_string()],
+            &[],
+            StatementPolicy::parse,
+        )
+        .unwrap();
+
+        assert!(policies.check("app", "UPDATE users SET x = 1").is_ok());
+        assert!(policies.check("other", "UPDATE users SET x = 1").is_err());
+        assert!(policies.check("other", "SELECT 1").is_ok());
+        assert_eq!(policies.databases().collect::<Vec<_>>(), vec!["app"]);
+
+        assert!(StatementPolicies::default().check("any", "DROP TABLE users").is_ok());
+    }
+
+    #[test]
+    fn test_cost_limit() {
+        let limit = CostLimit::parse("cost=1000, full_scan_rows=100").unwrap();
+        assert_eq!(limit.max_cost, Some(1000.0));
+        assert_eq!(limit.max_rows, None);
+
+        let mut plan = PlanSummary {
+            total_cost: Some(50.0),
+            estimated_rows: Some(1e9),
+            full_scans: vec![crate::db::dialect::FullScan {
+                table: "events".to_string(),
+                table_rows: Some(10),
+            }],
+        };
+        assert_eq!(limit.violation(&plan), None);
+
+        plan.full_scans[0].table_rows = Some(5000);
+        assert!(limit.violation(&plan).unwrap().contains("full scan of 'events'"));
+
+        plan.total_cost = Some(2000.0);
+        assert!(limit.violation(&plan).unwrap().contains("estimated cost 2000"));
+
+        assert!(CostLimit::parse("").is_err());
+        assert!(CostLimit::parse("cost=lots").is_err());
+        assert!(CostLimit::parse("speed=1").is_err());
+
+        let limits = CostLimits::parse(
+            &["rows=10".to_string(), "app=cost=5".to_string()],
+            CostLimit::KEYS,
+            CostLimit::parse,
+        )
+        .unwrap();
+        assert_eq!(limits.get("other").unwrap().max_rows, Some(10.0));
+        assert_eq!(limits.get("app").unwrap().max_cost, Some(5.0));
+    }
+}
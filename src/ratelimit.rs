@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::McpSqlError;
+use crate::policy::{RateLimit, RateLimits};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Recent activity against one database.
+#[derive(Default)]
+struct Usage {
+    in_flight: u32,
+    queries: VecDeque<Instant>,
+    rows: VecDeque<(Instant, u64)>,
+}
+
+impl Usage {
+    fn expire(&mut self, now: Instant) {
+        while self.queries.front().is_some_and(|t| now - *t >= WINDOW) {
+            self.queries.pop_front();
+        }
+        while self.rows.front().is_some_and(|(t, _)| now - *t >= WINDOW) {
+            self.rows.pop_front();
+        }
+    }
+}
+
+/// Enforces per-database concurrency caps and per-minute query and row
+/// budgets over a sliding one-minute window.
+pub struct RateLimiter {
+    limits: RateLimits,
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            limits,
+            usage: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Admit one request against `database`, or fail with how long to wait.
+    /// The request counts as in flight until the returned permit is dropped.
+    pub fn acquire(&self, database: &str) -> Result<RatePermit, McpSqlError> {
+        let limit = self.limits.get(database).cloned().unwrap_or_default();
+        let now = Instant::now();
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = usage.entry(database.to_string()).or_default();
+        entry.expire(now);
+        check(database, &limit, entry, now)?;
+
+        entry.in_flight += 1;
+        entry.queries.push_back(now);
+        Ok(RatePermit {
+            database: database.to_string(),
+            usage: Arc::clone(&self.usage),
+        })
+    }
+}
+
+fn check(database: &str, limit: &RateLimit, usage: &Usage, now: Instant) -> Result<(), McpSqlError> {
+    if let Some(max) = limit.max_concurrent {
+        if usage.in_flight >= max {
+            return Err(McpSqlError::RateLimited(
+                format!("database '{database}' already has {max} queries running"),
+                1,
+            ));
+        }
+    }
+    if let Some(max) = limit.queries_per_minute {
+        if usage.queries.len() as u64 >= max {
+            // The window frees up once enough of the oldest queries age out
+            let oldest = usage.queries[usage.queries.len() - max as usize];
+            return Err(McpSqlError::RateLimited(
+                format!("database '{database}' allows {max} queries per minute"),
+                retry_after(oldest, now),
+            ));
+        }
+    }
+    if let Some(max) = limit.rows_per_minute {
+        let mut total: u64 = usage.rows.iter().map(|(_, n)| n).sum();
+        if total >= max {
+            let mut until = now;
+            for (t, n) in &usage.rows {
+                total -= n;
+                until = *t;
+                if total < max {
+                    break;
+                }
+            }
+            return Err(McpSqlError::RateLimited(
+                format!("database '{database}' allows {max} rows returned per minute"),
+                retry_after(until, now),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whole seconds until `started` leaves the window, at least one.
+fn retry_after(started: Instant, now: Instant) -> u64 {
+    let remaining = (started + WINDOW).saturating_duration_since(now);
+    remaining.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// A request admitted by `RateLimiter`.
+pub struct RatePermit {
+    database: String,
+    usage: Arc<Mutex<HashMap<String, Usage>>>,
+}
+
+impl RatePermit {
+    /// Charge `rows` returned rows against the database's row budget.
+    pub fn record_rows(&self, rows: u64) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = usage.get_mut(&self.database) {
+            entry.rows.push_back((Instant::now(), rows));
+        }
+    }
+}
+
+impl Drop for RatePermit {
+    fn drop(&mut self) {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = usage.get_mut(&self.database) {
+            entry.in_flight = entry.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(spec: &str) -> RateLimiter {
+        RateLimiter::new(
+            RateLimits::parse(&[spec.to_string()], RateLimit::KEYS, RateLimit::parse).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_concurrency() {
+        let limiter = limiter("concurrent=1");
+        let permit = limiter.acquire("app").unwrap();
+        let err = limiter.acquire("app").err().unwrap();
+        assert!(err.to_string().contains("already has 1 queries running"));
+
+        // Other databases have their own usage
+        assert!(limiter.acquire("other").is_ok());
+
+        drop(permit);
+        assert!(limiter.acquire("app").is_ok());
+    }
+
+    #[test]
+    fn test_queries_per_minute() {
+        let limiter = limiter("queries_per_minute=2");
+        limiter.acquire("app").unwrap();
+        limiter.acquire("app").unwrap();
+        match limiter.acquire("app") {
+            Err(McpSqlError::RateLimited(_, retry)) => assert!((1..=60).contains(&retry)),
+            Err(e) => panic!("expected RateLimited, got {e}"),
+            Ok(_) => panic!("expected RateLimited"),
+        }
+    }
+
+    #[test]
+    fn test_rows_per_minute() {
+        let limiter = limiter("rows_per_minute=100");
+        limiter.acquire("app").unwrap().record_rows(60);
+        limiter.acquire("app").unwrap().record_rows(60);
+        let err = limiter.acquire("app").err().unwrap();
+        assert!(err.to_string().contains("100 rows returned per minute"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            RateLimit::parse("concurrent=4, queries_per_minute=60").unwrap(),
+            RateLimit {
+                max_concurrent: Some(4),
+                queries_per_minute: Some(60),
+                rows_per_minute: None,
+            }
+        );
+        assert!(RateLimit::parse("queries_per_minute=0").is_err());
+        assert!(RateLimit::parse("concurrent=0").is_err());
+        assert!(RateLimit::parse("rows_per_minute=-1").is_err());
+        assert!(RateLimit::parse("burst=5").is_err());
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let limiter = RateLimiter::new(RateLimits::default());
+        for _ in 0..100 {
+            limiter.acquire("app").unwrap().record_rows(1_000_000);
+        }
+    }
+}
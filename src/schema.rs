@@ -1,96 +1,919 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use sqlx::AnyPool;
 
-use crate::db::dialect;
+use crate::annotations::Annotations;
+use crate::db::catalog::{self, ColumnInfo, TableInfo};
 use crate::db::DbBackend;
 use crate::error::McpSqlError;
+use crate::infer;
+use crate::search::Pattern;
+use crate::suggest::unqualified;
 
-/// Generate a Mermaid ER diagram for all tables in a database.
+/// Which part of a database a diagram shows. The default is every table
+/// with all its columns.
+#[derive(Debug, Clone, Default)]
+pub struct DiagramScope {
+    /// Only tables in this schema.
+    pub schema: Option<String>,
+    /// Only tables whose name matches this glob (`*` and `?`).
+    pub pattern: Option<String>,
+    /// Tables to centre the diagram on; empty means all tables.
+    pub focus: Vec<String>,
+    /// How many foreign key hops from the focus tables to include.
+    pub hops: usize,
+    /// Show only primary and foreign key columns.
+    pub keys_only: bool,
+    /// Also draw the relationships [`infer::infer`] guesses from column
+    /// names, and follow them when looking for neighbours.
+    pub inferred: bool,
+}
+
+/// A notation an ER diagram can be rendered in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiagramFormat {
+    #[default]
+    Mermaid,
+    /// DBML, as used by dbdiagram.io.
+    Dbml,
+    PlantUml,
+    /// Graphviz DOT.
+    Dot,
+}
+
+impl DiagramFormat {
+    pub fn parse(format: &str) -> Result<Self, McpSqlError> {
+        match format.to_ascii_lowercase().as_str() {
+            "mermaid" => Ok(Self::Mermaid),
+            "dbml" => Ok(Self::Dbml),
+            "plantuml" => Ok(Self::PlantUml),
+            "dot" | "graphviz" => Ok(Self::Dot),
+            other => Err(McpSqlError::Other(format!(
+                "Unknown diagram format '{other}'; use mermaid, dbml, plantuml or dot"
+            ))),
+        }
+    }
+}
+
+/// The tables, columns and foreign keys an ER diagram shows, independent of
+/// notation.
+#[derive(Debug, Clone, Default)]
+pub struct Diagram {
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entity {
+    pub name: String,
+    pub comment: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub data_type: String,
+    pub primary_key: bool,
+    pub foreign_key: bool,
+    pub comment: Option<String>,
+}
+
+/// A foreign key: `columns` of `from` reference `referenced_columns` of `to`.
+#[derive(Debug, Clone)]
+pub struct Relationship {
+    pub from: String,
+    /// The foreign key's name, if the database gives it one.
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    pub to: String,
+    pub referenced_columns: Vec<String>,
+    /// A row of `from` need not reference one of `to`: a key column is
+    /// nullable.
+    pub optional: bool,
+    /// A row of `to` is referenced at most once: the key columns are unique
+    /// or form the primary key.
+    pub one_to_one: bool,
+    /// Guessed from column names rather than declared.
+    pub inferred: bool,
+}
+
+impl Relationship {
+    /// The foreign key columns, which label the edge.
+    fn label(&self) -> String {
+        let columns = self.columns.join(", ");
+        if self.inferred {
+            format!("{columns} (inferred)")
+        } else {
+            columns
+        }
+    }
+
+    /// The crow's foot notation for this relationship as used by Mermaid and
+    /// PlantUML, written from `to` to `from`. Inferred relationships are
+    /// dotted.
+    fn crows_foot(&self) -> String {
+        let parent = if self.optional { "|o" } else { "||" };
+        let line = if self.inferred { ".." } else { "--" };
+        let child = if self.one_to_one { "o|" } else { "o{" };
+        format!("{parent}{line}{child}")
+    }
+}
+
+impl Diagram {
+    /// Build the diagram of the tables of `catalog` in `scope`. Table and
+    /// column comments come from the database, or else from `annotations`.
+    pub fn build(
+        catalog: &[TableInfo],
+        annotations: &Annotations,
+        scope: &DiagramScope,
+    ) -> Result<Self, McpSqlError> {
+        let tables = select_tables(catalog, scope)?;
+        let inferred = if scope.inferred { infer::infer(catalog) } else { Vec::new() };
+        let mut diagram = Diagram::default();
+        for table in &tables {
+            let first = diagram.relationships.len();
+            for key in &table.foreign_keys {
+                if !tables.iter().any(|t| t.name == key.referenced_table) {
+                    continue;
+                }
+                let optional = table
+                    .columns
+                    .iter()
+                    .any(|c| c.nullable && key.columns.contains(&c.name));
+                diagram.relationships.push(Relationship {
+                    from: table.name.clone(),
+                    name: key.name.clone(),
+                    columns: key.columns.clone(),
+                    to: key.referenced_table.clone(),
+                    referenced_columns: key.referenced_columns.clone(),
+                    optional,
+                    one_to_one: false,
+                    inferred: false,
+                });
+            }
+            for key in inferred.iter().filter(|k| k.table == table.name) {
+                if !tables.iter().any(|t| t.name == key.referenced_table) {
+                    continue;
+                }
+                let nullable = table.columns.iter().any(|c| c.name == key.column && c.nullable);
+                diagram.relationships.push(Relationship {
+                    from: table.name.clone(),
+                    name: None,
+                    columns: vec![key.column.clone()],
+                    to: key.referenced_table.clone(),
+                    referenced_columns: vec![key.referenced_column.clone()],
+                    optional: nullable,
+                    one_to_one: false,
+                    inferred: true,
+                });
+            }
+            let primary_key: Vec<&str> =
+                table.columns.iter().filter(|c| c.primary_key).map(|c| c.name.as_str()).collect();
+            for relationship in &mut diagram.relationships[first..] {
+                relationship.one_to_one = match relationship.columns.as_slice() {
+                    [name] => table.columns.iter().any(|c| c.name == *name && c.unique),
+                    _ => false,
+                } || (primary_key.len() == relationship.columns.len()
+                    && relationship.columns.iter().all(|c| primary_key.contains(&c.as_str())));
+            }
+
+            let attributes = table
+                .columns
+                .iter()
+                .filter(|c| {
+                    !scope.keys_only
+                        || c.primary_key
+                        || c.foreign_key.is_some()
+                        || inferred.iter().any(|k| k.table == table.name && k.column == c.name)
+                })
+                .map(|column| Attribute {
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    primary_key: column.primary_key,
+                    foreign_key: column.foreign_key.is_some(),
+                    comment: column_comment(table, column, annotations).map(|c| single_line(&c)),
+                })
+                .collect();
+            let comment = table
+                .comment
+                .as_deref()
+                .or_else(|| annotations.table_comment(&table.name));
+            diagram.entities.push(Entity {
+                name: table.name.clone(),
+                comment: comment.map(single_line),
+                attributes,
+            });
+        }
+        Ok(diagram)
+    }
+
+    pub fn render(&self, format: DiagramFormat) -> String {
+        match format {
+            DiagramFormat::Mermaid => self.to_mermaid(),
+            DiagramFormat::Dbml => self.to_dbml(),
+            DiagramFormat::PlantUml => self.to_plantuml(),
+            DiagramFormat::Dot => self.to_dot(),
+        }
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        if self.entities.is_empty() {
+            return "erDiagram\n    %% No tables found".to_string();
+        }
+        let mut out = String::from("erDiagram\n");
+        for entity in &self.entities {
+            if let Some(comment) = &entity.comment {
+                out.push_str(&format!("    %% {}: {}\n", entity.name, comment));
+            }
+            out.push_str(&format!("    {} {{\n", mermaid_name(&entity.name)));
+            for attribute in &entity.attributes {
+                let mut suffix = String::new();
+                if attribute.primary_key {
+                    suffix.push_str(" PK");
+                }
+                if attribute.foreign_key {
+                    suffix.push_str(" FK");
+                }
+                if let Some(comment) = &attribute.comment {
+                    // Attribute comments are double-quoted and cannot escape quotes
+                    suffix.push_str(&format!(" \"{}\"", comment.replace('"', "'")));
+                }
+                // Mermaid ER format: TYPE name CONSTRAINT
+                // Type names cannot contain spaces, dots (schema-qualified
+                // PostgreSQL types) or commas, so replace them with underscores.
+                out.push_str(&format!(
+                    "        {} {}{}\n",
+                    attribute.data_type.to_uppercase().replace([' ', '.', ','], "_"),
+                    attribute.name,
+                    suffix
+                ));
+            }
+            out.push_str("    }\n");
+        }
+        for relationship in &self.relationships {
+            out.push_str(&format!(
+                "    {} {} {} : \"{}\"\n",
+                mermaid_name(&relationship.to),
+                relationship.crows_foot(),
+                mermaid_name(&relationship.from),
+                relationship.label()
+            ));
+        }
+        out
+    }
+
+    pub fn to_dbml(&self) -> String {
+        if self.entities.is_empty() {
+            return "// No tables found".to_string();
+        }
+        let mut out = String::new();
+        for entity in &self.entities {
+            out.push_str(&format!("Table {} {{\n", dbml_name(&entity.name)));
+            let keys: Vec<String> =
+                entity.attributes.iter().filter(|a| a.primary_key).map(|a| a.name.clone()).collect();
+            for attribute in &entity.attributes {
+                let mut settings = Vec::new();
+                if attribute.primary_key && keys.len() == 1 {
+                    settings.push("pk".to_string());
+                }
+                if let Some(comment) = &attribute.comment {
+                    settings.push(format!("note: {}", dbml_string(comment)));
+                }
+                let settings = if settings.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [{}]", settings.join(", "))
+                };
+                out.push_str(&format!(
+                    "  {} {}{}\n",
+                    dbml_identifier(&attribute.name),
+                    dbml_type(&attribute.data_type),
+                    settings
+                ));
+            }
+            if keys.len() > 1 {
+                // A composite primary key is declared as an index
+                out.push_str(&format!("  indexes {{\n    {} [pk]\n  }}\n", dbml_columns(&keys)));
+            }
+            if let Some(comment) = &entity.comment {
+                out.push_str(&format!("  Note: {}\n", dbml_string(comment)));
+            }
+            out.push_str("}\n\n");
+        }
+        for relationship in &self.relationships {
+            // `>` is many-to-one, `-` one-to-one
+            out.push_str(&format!(
+                "Ref: {}.{} {} {}.{}{}\n",
+                dbml_name(&relationship.from),
+                dbml_columns(&relationship.columns),
+                if relationship.one_to_one { "-" } else { ">" },
+                dbml_name(&relationship.to),
+                dbml_columns(&relationship.referenced_columns),
+                if relationship.inferred { " // inferred" } else { "" }
+            ));
+        }
+        out
+    }
+
+    pub fn to_plantuml(&self) -> String {
+        let mut out = String::from("@startuml\nhide circle\nskinparam linetype ortho\n\n");
+        if self.entities.is_empty() {
+            out.push_str("' No tables found\n");
+        }
+        for entity in &self.entities {
+            let alias = plantuml_alias(&entity.name);
+            out.push_str(&format!("entity \"{}\" as {} {{\n", entity.name, alias));
+            let (keys, others): (Vec<&Attribute>, Vec<&Attribute>) =
+                entity.attributes.iter().partition(|a| a.primary_key);
+            // Primary key columns go above the separator, as in IE notation
+            for attribute in &keys {
+                out.push_str(&format!("  * {}\n", plantuml_attribute(attribute)));
+            }
+            if !keys.is_empty() && !others.is_empty() {
+                out.push_str("  --\n");
+            }
+            for attribute in &others {
+                out.push_str(&format!("  {}\n", plantuml_attribute(attribute)));
+            }
+            out.push_str("}\n");
+            if let Some(comment) = &entity.comment {
+                out.push_str(&format!("note top of {} : {}\n", alias, comment));
+            }
+            out.push('\n');
+        }
+        for relationship in &self.relationships {
+            out.push_str(&format!(
+                "{} {} {} : {}\n",
+                plantuml_alias(&relationship.to),
+                relationship.crows_foot(),
+                plantuml_alias(&relationship.from),
+                relationship.label()
+            ));
+        }
+        out.push_str("@enduml\n");
+        out
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out =
+            String::from("digraph schema {\n    rankdir=LR;\n    node [shape=plaintext];\n");
+        if self.entities.is_empty() {
+            out.push_str("    // No tables found\n");
+        }
+        for entity in &self.entities {
+            let mut rows = format!(
+                "<tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+                html_escape(&entity.name)
+            );
+            for attribute in &entity.attributes {
+                let mut text = format!("{} {}", attribute.name, attribute.data_type);
+                if attribute.primary_key {
+                    text.push_str(" PK");
+                }
+                if attribute.foreign_key {
+                    text.push_str(" FK");
+                }
+                rows.push_str(&format!(
+                    "<tr><td align=\"left\" port=\"{}\">{}</td></tr>",
+                    html_escape(&attribute.name),
+                    html_escape(&text)
+                ));
+            }
+            let tooltip = entity
+                .comment
+                .as_deref()
+                .map(|c| format!(", tooltip={}", dot_string(c)))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "    {} [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">{}</table>>{}];\n",
+                dot_string(&entity.name),
+                rows,
+                tooltip
+            ));
+        }
+        for relationship in &self.relationships {
+            // Inferred references are dotted, optional ones dashed; one-to-one
+            // ones end in a bar
+            let mut style = format!("label={}", dot_string(&relationship.label()));
+            if relationship.inferred {
+                style.push_str(", style=dotted");
+            } else if relationship.optional {
+                style.push_str(", style=dashed");
+            }
+            if relationship.one_to_one {
+                style.push_str(", arrowhead=tee");
+            }
+            out.push_str(&format!(
+                "    {} -> {} [{}];\n",
+                dot_string(&relationship.from),
+                dot_string(&relationship.to),
+                style
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Generate an ER diagram in `format` for the tables of a database in
+/// `scope`. Table and column comments, from the database or `annotations`,
+/// are included.
+pub async fn generate_er(
+    pool: &AnyPool,
+    backend: DbBackend,
+    annotations: &Annotations,
+    scope: &DiagramScope,
+    format: DiagramFormat,
+) -> Result<String, McpSqlError> {
+    let catalog = catalog::load(pool, backend, scope.schema.as_deref()).await?;
+    Ok(Diagram::build(&catalog, annotations, scope)?.render(format))
+}
+
+/// Generate a Mermaid ER diagram for the tables of a database in `scope`.
 pub async fn generate_mermaid_er(
     pool: &AnyPool,
     backend: DbBackend,
+    annotations: &Annotations,
+    scope: &DiagramScope,
 ) -> Result<String, McpSqlError> {
-    // Get all tables
-    let table_rows = dialect::list_tables(pool, backend).await?;
-    let table_names: Vec<String> = table_rows
+    generate_er(pool, backend, annotations, scope, DiagramFormat::Mermaid).await
+}
+
+/// The tables of `catalog` in `scope`, in catalog order. Views carry no
+/// foreign keys, so they are left out.
+pub fn select_tables<'a>(
+    catalog: &'a [TableInfo],
+    scope: &DiagramScope,
+) -> Result<Vec<&'a TableInfo>, McpSqlError> {
+    let pattern = scope
+        .pattern
+        .as_deref()
+        .map(|glob| Pattern::parse(glob, Some("glob")))
+        .transpose()?;
+    let candidates: Vec<&TableInfo> = catalog
         .iter()
-        .filter_map(|r| r.get("table_name").and_then(|v| v.as_str()).map(String::from))
+        .filter(|t| !t.is_view())
+        .filter(|t| {
+            pattern
+                .as_ref()
+                .map_or(true, |p| p.is_match(&t.name) || p.is_match(unqualified(&t.name)))
+        })
         .collect();
+    if scope.focus.is_empty() {
+        return Ok(candidates);
+    }
 
-    if table_names.is_empty() {
-        return Ok("erDiagram\n    %% No tables found".to_string());
-    }
-
-    let mut diagram = String::from("erDiagram\n");
-
-    // FK relationships: (from_table, to_table, from_col, to_col)
-    let mut relationships: Vec<(String, String, String, String)> = Vec::new();
-
-    // Describe each table
-    for table in &table_names {
-        let columns = dialect::describe_table(pool, backend, table).await?;
-        diagram.push_str(&format!("    {} {{\n", table));
-        for col in &columns {
-            let name = col
-                .get("name")
-                .and_then(|v| v.as_str())
-                .or_else(|| col.get("column_name").and_then(|v| v.as_str()))
-                .unwrap_or("?");
-            let dtype = col
-                .get("type")
-                .and_then(|v| v.as_str())
-                .or_else(|| col.get("data_type").and_then(|v| v.as_str()))
-                .unwrap_or("?");
-            let is_pk = col
-                .get("primary_key")
-                .and_then(|v| v.as_str())
-                .map(|s| s == "YES")
-                .or_else(|| col.get("is_primary_key").and_then(|v| v.as_bool()))
-                .unwrap_or(false);
-            let fk = col.get("foreign_key").and_then(|v| v.as_str());
-
-            let mut suffix = String::new();
-            if is_pk {
-                suffix.push_str(" PK");
-            }
-            if fk.is_some() {
-                suffix.push_str(" FK");
-            }
-            // Mermaid ER format: TYPE name CONSTRAINT
-            // Type names cannot contain spaces, so replace spaces with underscores.
-            diagram.push_str(&format!(
-                "        {} {}{}\n",
-                dtype.to_uppercase().replace(' ', "_"),
-                name,
-                suffix
-            ));
+    let mut selected: HashSet<&str> = HashSet::new();
+    for name in &scope.focus {
+        let table = candidates
+            .iter()
+            .find(|t| t.name == *name)
+            .or_else(|| candidates.iter().find(|t| unqualified(&t.name) == unqualified(name)))
+            .ok_or_else(|| McpSqlError::TableNotFound(name.clone()))?;
+        selected.insert(&table.name);
+    }
 
-            // Track FK relationships
-            if let Some(fk_ref) = fk {
-                if let Some((ref_table, ref_col)) = fk_ref.split_once('.') {
-                    relationships.push((
-                        table.clone(),
-                        ref_table.to_string(),
-                        name.to_string(),
-                        ref_col.to_string(),
-                    ));
-                }
+    // Follow foreign keys in both directions, one hop at a time
+    let mut edges: Vec<(&str, &str)> = Vec::new();
+    for table in &candidates {
+        for key in &table.foreign_keys {
+            edges.push((&table.name, &key.referenced_table));
+        }
+    }
+    let inferred = if scope.inferred { infer::infer(catalog) } else { Vec::new() };
+    for key in &inferred {
+        edges.push((&key.table, &key.referenced_table));
+    }
+    for _ in 0..scope.hops {
+        let next: Vec<&str> = edges
+            .iter()
+            .filter_map(|&(from, to)| match (selected.contains(from), selected.contains(to)) {
+                (true, false) => Some(to),
+                (false, true) => Some(from),
+                _ => None,
+            })
+            .collect();
+        if next.is_empty() {
+            break;
+        }
+        selected.extend(next);
+    }
+
+    Ok(candidates.into_iter().filter(|t| selected.contains(t.name.as_str())).collect())
+}
+
+/// A column's comment, or its annotated description, followed by the meaning
+/// of its annotated values: `Status (1 = pending, 3 = refunded)`.
+fn column_comment(
+    table: &TableInfo,
+    column: &ColumnInfo,
+    annotations: &Annotations,
+) -> Option<String> {
+    let annotation = annotations.column(&table.name, &column.name);
+    let description = column
+        .comment
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .or_else(|| annotation.and_then(|a| a.description()));
+    let values = annotation.and_then(|a| a.values()).map(|values| {
+        values
+            .iter()
+            .map(|(value, meaning)| format!("{value} = {meaning}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    match (description, values) {
+        (Some(description), Some(values)) => Some(format!("{description} ({values})")),
+        (description, values) => description.map(String::from).or(values),
+    }
+}
+
+/// A dense text summary of a database: one line per table with its row count
+/// and columns, each with its type and markers for keys, NOT NULL and
+/// defaults. When the text would exceed `max_chars`, the tables with the
+/// fewest foreign key links and rows are reduced to their name in a final
+/// line, the least important first.
+pub fn schema_summary(tables: &[TableInfo], max_chars: usize) -> String {
+    let header = format!(
+        "{} tables and views. Columns are `name type`; PK primary key, UQ unique, \
+         FK>table.column foreign key, ! not null, =default.\n",
+        tables.len()
+    );
+    let lines: Vec<String> = tables.iter().map(summary_line).collect();
+
+    // Foreign keys in either direction make a table worth keeping
+    let mut links: HashMap<&str, usize> = HashMap::new();
+    for table in tables {
+        for key in &table.foreign_keys {
+            *links.entry(table.name.as_str()).or_default() += 1;
+            *links.entry(key.referenced_table.as_str()).or_default() += 1;
+        }
+    }
+    let mut order: Vec<usize> = (0..tables.len()).collect();
+    order.sort_by_key(|&i| {
+        let table = &tables[i];
+        (links.get(table.name.as_str()).copied().unwrap_or(0), table.row_count.unwrap_or(0))
+    });
+
+    const COLLAPSED: &str = "Collapsed to save space: ";
+    let mut collapsed = vec![false; tables.len()];
+    let mut collapsed_names: Vec<(usize, String)> = Vec::new();
+    let mut total = header.len() + lines.iter().map(|l| l.len() + 1).sum::<usize>();
+    for &i in &order {
+        if total <= max_chars {
+            break;
+        }
+        let entry = match tables[i].row_count {
+            Some(rows) => format!("{} ({rows} rows)", tables[i].name),
+            None => tables[i].name.clone(),
+        };
+        total -= lines[i].len() + 1;
+        total += entry.len() + if collapsed_names.is_empty() { COLLAPSED.len() + 1 } else { 2 };
+        collapsed[i] = true;
+        collapsed_names.push((i, entry));
+    }
+
+    let mut text = header;
+    for (line, _) in lines.iter().zip(&collapsed).filter(|(_, collapsed)| !**collapsed) {
+        text.push_str(line);
+        text.push('\n');
+    }
+    if !collapsed_names.is_empty() {
+        // Keep catalog order, and cut the list itself if it alone is too long
+        collapsed_names.sort();
+        let mut line = String::from(COLLAPSED);
+        for (shown, (_, entry)) in collapsed_names.iter().enumerate() {
+            let remaining = collapsed_names.len() - shown;
+            if shown > 0 && remaining > 1 && text.len() + line.len() + entry.len() + 20 > max_chars {
+                line.push_str(&format!("and {remaining} more"));
+                break;
+            }
+            line.push_str(entry);
+            if remaining > 1 {
+                line.push_str(", ");
             }
         }
-        diagram.push_str("    }\n");
+        text.push_str(&line);
+        text.push('\n');
     }
+    text
+}
 
-    // Add relationships
-    // Deduplicate by (from_table, to_table) pair
-    let mut seen: HashMap<(String, String), String> = HashMap::new();
-    for (from, to, from_col, _to_col) in &relationships {
-        seen.entry((from.clone(), to.clone()))
-            .or_insert_with(|| from_col.clone());
+/// `users (1204 rows): id integer PK, email text! UQ, org_id integer FK>orgs.id`
+fn summary_line(table: &TableInfo) -> String {
+    let size = match (table.is_view(), table.row_count) {
+        (true, _) => format!(" ({})", table.kind.replace('_', " ")),
+        (false, Some(rows)) => format!(" ({rows} rows)"),
+        (false, None) => String::new(),
+    };
+    let columns: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| {
+            let mut text = format!("{} {}", column.name, column.data_type);
+            if !column.nullable && !column.primary_key {
+                text.push('!');
+            }
+            if column.primary_key {
+                text.push_str(" PK");
+            }
+            if column.unique && !column.primary_key {
+                text.push_str(" UQ");
+            }
+            if let Some(fk) = &column.foreign_key {
+                text.push_str(&format!(" FK>{fk}"));
+            }
+            if let Some(default) = &column.default {
+                text.push_str(&format!(" ={}", shorten(default, 24)));
+            }
+            text
+        })
+        .collect();
+    format!("{}{size}: {}", table.name, columns.join(", "))
+}
+
+/// Cut `text` to at most `max` characters, marking the cut with `…`.
+fn shorten(text: &str, max: usize) -> String {
+    let text = single_line(text);
+    if text.chars().count() <= max {
+        return text;
     }
-    for (from, to) in seen.keys() {
-        diagram.push_str(&format!("    {} ||--o{{ {} : \"\"\n", to, from));
+    let mut cut: String = text.chars().take(max - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Collapse a comment onto one line for the diagram.
+fn single_line(comment: &str) -> String {
+    comment.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A Mermaid entity name, double-quoted unless it is a plain identifier:
+/// schema-qualified names contain a dot, and Mermaid cannot escape quotes.
+fn mermaid_name(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "'"))
     }
+}
 
-    Ok(diagram)
+/// A DBML table name, `schema.table` when qualified.
+fn dbml_name(name: &str) -> String {
+    match name.split_once('.') {
+        Some((schema, table)) => format!("{}.{}", dbml_identifier(schema), dbml_identifier(table)),
+        None => dbml_identifier(name),
+    }
+}
+
+fn dbml_identifier(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\\\""))
+    }
+}
+
+/// One column, or a composite `(a, b)`.
+fn dbml_columns(columns: &[String]) -> String {
+    let names: Vec<String> = columns.iter().map(|c| dbml_identifier(c)).collect();
+    match names.as_slice() {
+        [name] => name.clone(),
+        _ => format!("({})", names.join(", ")),
+    }
+}
+
+/// Types with spaces, such as `character varying(50)`, must be quoted.
+fn dbml_type(data_type: &str) -> String {
+    if data_type.contains([' ', ',']) {
+        format!("\"{}\"", data_type.replace('"', "\\\""))
+    } else {
+        data_type.to_string()
+    }
+}
+
+fn dbml_string(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// PlantUML aliases are plain identifiers; the entity shows the real name.
+fn plantuml_alias(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+fn plantuml_attribute(attribute: &Attribute) -> String {
+    let mut text = format!("{} : {}", attribute.name, attribute.data_type);
+    if attribute.foreign_key {
+        text.push_str(" <<FK>>");
+    }
+    if let Some(comment) = &attribute.comment {
+        text.push_str(&format!(" // {comment}"));
+    }
+    text
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_summary() {
+        let id = ColumnInfo {
+            primary_key: true,
+            nullable: false,
+            ..ColumnInfo::fixture("id", "integer")
+        };
+        let email = ColumnInfo {
+            nullable: false,
+            unique: true,
+            default: Some("'nobody@example.com'::text".to_string()),
+            ..ColumnInfo::fixture("email", "text")
+        };
+        let org = ColumnInfo::fixture("org_id", "integer");
+        let mut view =
+            TableInfo::fixture("active_users", vec![ColumnInfo::fixture("id", "integer")]);
+        view.kind = "materialized_view".to_string();
+        let sized = |rows, name, columns| TableInfo {
+            row_count: Some(rows),
+            ..TableInfo::fixture(name, columns)
+        };
+        let tables = vec![
+            view,
+            sized(5000, "audit", vec![ColumnInfo::fixture("note", "text")]),
+            sized(3, "orgs", vec![id.clone()]),
+            sized(20, "users", vec![id, email, org]).with_foreign_key(
+                "users_org_fk",
+                &["org_id"],
+                "orgs",
+                &["id"],
+            ),
+        ];
+
+        let full = schema_summary(&tables, 10_000);
+        let lines: Vec<&str> = full.lines().collect();
+        assert!(lines[0].starts_with("4 tables and views."));
+        assert_eq!(
+            &lines[1..],
+            [
+                "active_users (materialized view): id integer",
+                "audit (5000 rows): note text",
+                "orgs (3 rows): id integer PK",
+                "users (20 rows): id integer PK, email text! UQ ='nobody@example.com'::t…, \
+                 org_id integer FK>orgs.id",
+            ]
+        );
+
+        // Unlinked tables go first, the view before the bigger table
+        let budget = full.len() - 5;
+        let short = schema_summary(&tables, budget);
+        assert!(short.len() <= budget);
+        assert!(!short.contains("active_users (materialized view):"));
+        assert!(short.contains("audit (5000 rows): note text"));
+        assert!(short.ends_with("Collapsed to save space: active_users\n"));
+
+        let tiny = schema_summary(&tables, 0);
+        assert!(tiny.ends_with("Collapsed to save space: active_users, and 3 more\n"));
+    }
+
+    #[test]
+    fn test_diagram_formats() {
+        let id = ColumnInfo { primary_key: true, ..ColumnInfo::fixture("id", "integer") };
+        let name = ColumnInfo::fixture("name", "character varying(50)");
+        let mut orgs = TableInfo::fixture("orgs", vec![id.clone(), name]);
+        orgs.comment = Some("Customer\n accounts".to_string());
+        let org = ColumnInfo { nullable: false, ..ColumnInfo::fixture("org_id", "integer") };
+        let users =
+            TableInfo::fixture("app.users", vec![id, org, ColumnInfo::fixture("note", "text")])
+                .with_foreign_key("users_org_fk", &["org_id"], "orgs", &["id"]);
+        let annotations =
+            Annotations::parse("tables:\n  users:\n    columns:\n      note: Free \"text\"\n").unwrap();
+        let catalog = vec![orgs, users];
+
+        let diagram = Diagram::build(&catalog, &annotations, &DiagramScope::default()).unwrap();
+        assert_eq!(diagram.entities.len(), 2);
+        assert_eq!(diagram.relationships.len(), 1);
+
+        let mermaid = diagram.render(DiagramFormat::Mermaid);
+        assert!(mermaid.contains("    %% orgs: Customer accounts\n"));
+        assert!(mermaid.contains("        CHARACTER_VARYING(50) name\n"));
+        assert!(mermaid.contains("        TEXT note \"Free 'text'\"\n"));
+        assert!(mermaid.contains("    \"app.users\" {\n"));
+        assert!(mermaid.contains("    orgs ||--o{ \"app.users\" : \"org_id\"\n"));
+        let spaced =
+            TableInfo::fixture("public.Mixed Case", vec![ColumnInfo::fixture("id", "int")]);
+        let spaced = Diagram::build(&[spaced], &annotations, &DiagramScope::default()).unwrap();
+        assert!(spaced.to_mermaid().contains("    \"public.Mixed Case\" {\n"));
+
+        let dbml = diagram.render(DiagramFormat::Dbml);
+        assert!(dbml.contains(
+            "Table orgs {\n  id integer [pk]\n  name \"character varying(50)\"\n  \
+             Note: 'Customer accounts'\n}"
+        ));
+        assert!(dbml.contains("Table app.users {"));
+        assert!(dbml.contains("  note text [note: 'Free \"text\"']\n"));
+        assert!(dbml.contains("Ref: app.users.org_id > orgs.id\n"));
+
+        let plantuml = diagram.render(DiagramFormat::PlantUml);
+        assert!(plantuml.starts_with("@startuml\n") && plantuml.ends_with("@enduml\n"));
+        assert!(plantuml.contains(
+            "entity \"app.users\" as app_users {\n  * id : integer\n  --\n  org_id : integer <<FK>>\n"
+        ));
+        assert!(plantuml.contains("note top of orgs : Customer accounts\n"));
+        assert!(plantuml.contains("orgs ||--o{ app_users : org_id\n"));
+
+        let dot = diagram.render(DiagramFormat::Dot);
+        assert!(dot.starts_with("digraph schema {"));
+        assert!(dot.contains("<td align=\"left\" port=\"org_id\">org_id integer FK</td>"));
+        assert!(dot.contains("tooltip=\"Customer accounts\""));
+        assert!(dot.contains("    \"app.users\" -> \"orgs\" [label=\"org_id\"];\n"));
+
+        let composite = ColumnInfo { primary_key: true, ..ColumnInfo::fixture("year", "integer") };
+        let mut orgs = catalog[0].clone();
+        orgs.columns.insert(1, composite);
+        let dbml = Diagram::build(&[orgs], &annotations, &DiagramScope::default())
+            .unwrap()
+            .render(DiagramFormat::Dbml);
+        assert!(dbml.contains("  id integer\n  year integer\n"));
+        assert!(dbml.contains("  indexes {\n    (id, year) [pk]\n  }\n"));
+
+        let keys = DiagramScope { keys_only: true, ..Default::default() };
+        let keys = Diagram::build(&catalog, &annotations, &keys).unwrap();
+        assert_eq!(keys.entities[1].attributes.len(), 2);
+        assert!(DiagramFormat::parse("Graphviz").is_ok_and(|f| f == DiagramFormat::Dot));
+        assert!(DiagramFormat::parse("svg").is_err());
+    }
+
+    #[test]
+    fn test_relationship_cardinality() {
+        let column = |name| ColumnInfo { nullable: false, ..ColumnInfo::fixture(name, "integer") };
+        let key = |name| ColumnInfo { primary_key: true, ..column(name) };
+        let catalog = vec![
+            TableInfo::fixture("users", vec![key("id")]),
+            TableInfo::fixture(
+                "messages",
+                vec![
+                    key("id"),
+                    column("sender_id"),
+                    ColumnInfo { nullable: true, ..column("recipient_id") },
+                ],
+            )
+            .with_foreign_key("messages_sender_fk", &["sender_id"], "users", &["id"])
+            .with_foreign_key("messages_recipient_fk", &["recipient_id"], "users", &["id"]),
+            TableInfo::fixture("profiles", vec![ColumnInfo { unique: true, ..column("user_id") }])
+                .with_foreign_key("profiles_user_fk", &["user_id"], "users", &["id"]),
+            TableInfo::fixture("settings", vec![key("user_id")])
+                .with_foreign_key("settings_user_fk", &["user_id"], "users", &["id"]),
+            TableInfo::fixture("periods", vec![key("id"), key("year")]),
+            // `period_id` is in two keys, one of them composite
+            TableInfo::fixture("totals", vec![column("period_id"), column("period_year")])
+                .with_foreign_key(
+                    "totals_period_fk",
+                    &["period_id", "period_year"],
+                    "periods",
+                    &["id", "year"],
+                )
+                .with_foreign_key("totals_period_id_fk", &["period_id"], "periods", &["id"]),
+        ];
+        let diagram =
+            Diagram::build(&catalog, &Annotations::default(), &DiagramScope::default()).unwrap();
+        let edges: Vec<(&str, String, bool, bool)> = diagram
+            .relationships
+            .iter()
+            .map(|r| (r.from.as_str(), r.label(), r.optional, r.one_to_one))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("messages", "sender_id".to_string(), false, false),
+                ("messages", "recipient_id".to_string(), true, false),
+                ("profiles", "user_id".to_string(), false, true),
+                ("settings", "user_id".to_string(), false, true),
+                ("totals", "period_id, period_year".to_string(), false, false),
+                ("totals", "period_id".to_string(), false, false),
+            ]
+        );
+
+        let mermaid = diagram.to_mermaid();
+        assert!(mermaid.contains("    users ||--o{ messages : \"sender_id\"\n"));
+        assert!(mermaid.contains("    users |o--o{ messages : \"recipient_id\"\n"));
+        assert!(mermaid.contains("    users ||--o| profiles : \"user_id\"\n"));
+        assert!(mermaid.contains("    periods ||--o{ totals : \"period_id, period_year\"\n"));
+        assert!(mermaid.contains("    periods ||--o{ totals : \"period_id\"\n"));
+        let dbml = diagram.to_dbml();
+        assert!(dbml.contains("Ref: totals.(period_id, period_year) > periods.(id, year)\n"));
+        assert!(diagram.to_dbml().contains("Ref: profiles.user_id - users.id\n"));
+        assert!(diagram.to_dot().contains("[label=\"recipient_id\", style=dashed];"));
+    }
 }
@@ -0,0 +1,227 @@
+//! Finding tables and columns by name, comment or annotation across the
+//! catalog of a database.
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::annotations::Annotations;
+use crate::db::catalog::TableInfo;
+use crate::error::McpSqlError;
+use crate::suggest::unqualified;
+
+/// What a search term is matched as. All matching ignores case.
+#[derive(Debug)]
+pub enum Pattern {
+    /// The term appears anywhere in the text.
+    Substring(String),
+    /// The whole text matches a glob with `*` and `?` wildcards.
+    Glob(Regex),
+    /// The text contains a match of a regular expression.
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parse `term` as `mode` (`substring`, `glob` or `regex`). Without a
+    /// mode, a term with `*` or `?` is a glob and anything else a substring.
+    pub fn parse(term: &str, mode: Option<&str>) -> Result<Self, McpSqlError> {
+        let mode = mode.unwrap_or(if term.contains(['*', '?']) { "glob" } else { "substring" });
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| McpSqlError::Other(format!("Invalid pattern '{term}': {e}")))
+        };
+        match mode.to_ascii_lowercase().as_str() {
+            "substring" => Ok(Self::Substring(term.to_lowercase())),
+            "glob" => {
+                let mut pattern = String::from("^");
+                for c in term.chars() {
+                    match c {
+                        '*' => pattern.push_str(".*"),
+                        '?' => pattern.push('.'),
+                        c => pattern.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                pattern.push('$');
+                build(&pattern).map(Self::Glob)
+            }
+            "regex" => build(term).map(Self::Regex),
+            other => Err(McpSqlError::Other(format!(
+                "Unknown search mode '{other}'; use substring, glob or regex"
+            ))),
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(term) => text.to_lowercase().contains(term),
+            Self::Glob(regex) | Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// How well a table or column name matches: an exact substring match
+    /// beats a prefix, which beats a match anywhere. Schema-qualified names
+    /// are also tried without their schema.
+    fn name_score(&self, name: &str) -> Option<usize> {
+        let short = unqualified(name);
+        if let Self::Substring(term) = self {
+            let short = short.to_lowercase();
+            if short == *term {
+                return Some(100);
+            }
+            if short.starts_with(term.as_str()) {
+                return Some(75);
+            }
+        }
+        (self.is_match(short) || self.is_match(name)).then_some(50)
+    }
+}
+
+/// A table or column found by [`search_catalog`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaHit {
+    pub database: String,
+    /// `table` or `column`.
+    pub kind: &'static str,
+    pub table: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub data_type: Option<String>,
+    /// What matched: `name`, `comment` or `annotation`.
+    pub matched: &'static str,
+    /// The comment or annotation that matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Higher is better.
+    pub score: usize,
+}
+
+/// Score of a match in a comment or annotation rather than a name.
+const DESCRIPTION_SCORE: usize = 25;
+/// Bonus for tables over their columns when both match equally well.
+const TABLE_BONUS: usize = 5;
+
+/// Tables and columns of `database` matching `pattern`, in catalog order.
+/// Comments and annotations are searched as well as names; each table or
+/// column is reported once, for its best match.
+pub fn search_catalog(
+    database: &str,
+    pattern: &Pattern,
+    tables: &[TableInfo],
+    annotations: &Annotations,
+) -> Vec<SchemaHit> {
+    let mut hits = Vec::new();
+    for table in tables {
+        let annotation = annotations.table_comment(&table.name).map(String::from);
+        if let Some((matched, text, score)) = best_match(pattern, &table.name, table.comment.clone(), annotation) {
+            hits.push(SchemaHit {
+                database: database.to_string(),
+                kind: "table",
+                table: table.name.clone(),
+                column: None,
+                data_type: None,
+                matched,
+                text,
+                score: score + TABLE_BONUS,
+            });
+        }
+
+        for column in &table.columns {
+            let annotation = annotations.column(&table.name, &column.name).and_then(|a| a.summary());
+            if let Some((matched, text, score)) =
+                best_match(pattern, &column.name, column.comment.clone(), annotation)
+            {
+                hits.push(SchemaHit {
+                    database: database.to_string(),
+                    kind: "column",
+                    table: table.name.clone(),
+                    column: Some(column.name.clone()),
+                    data_type: Some(column.data_type.clone()),
+                    matched,
+                    text,
+                    score,
+                });
+            }
+        }
+    }
+    hits
+}
+
+fn best_match(
+    pattern: &Pattern,
+    name: &str,
+    comment: Option<String>,
+    annotation: Option<String>,
+) -> Option<(&'static str, Option<String>, usize)> {
+    if let Some(score) = pattern.name_score(name) {
+        return Some(("name", None, score));
+    }
+    if let Some(comment) = comment.filter(|c| pattern.is_match(c)) {
+        return Some(("comment", Some(comment), DESCRIPTION_SCORE));
+    }
+    annotation
+        .filter(|a| pattern.is_match(a))
+        .map(|annotation| ("annotation", Some(annotation), DESCRIPTION_SCORE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::catalog::ColumnInfo;
+
+    #[test]
+    fn test_pattern() {
+        let substring = Pattern::parse("Mail", None).unwrap();
+        assert!(substring.is_match("user_email"));
+        assert!(!substring.is_match("name"));
+
+        let glob = Pattern::parse("*_id", None).unwrap();
+        assert!(matches!(glob, Pattern::Glob(_)));
+        assert!(glob.is_match("USER_ID"));
+        assert!(!glob.is_match("user_id_old"));
+        assert!(Pattern::parse("a.b", Some("glob")).unwrap().is_match("A.B"));
+        assert!(!Pattern::parse("a.b", Some("glob")).unwrap().is_match("axb"));
+
+        let regex = Pattern::parse("^(created|updated)_at$", Some("regex")).unwrap();
+        assert!(regex.is_match("Created_At"));
+        assert!(Pattern::parse("(", Some("regex")).is_err());
+        assert!(Pattern::parse("x", Some("fuzzy")).is_err());
+    }
+
+    #[test]
+    fn test_search_catalog() {
+        let columns = |names: &[&str]| {
+            names.iter().map(|name| ColumnInfo::fixture(name, "integer")).collect()
+        };
+        let tables = vec![
+            TableInfo::fixture("public.users", columns(&["id", "user_email"])),
+            TableInfo {
+                comment: Some("Placed by users".to_string()),
+                ..TableInfo::fixture("public.orders", columns(&["acct_typ"]))
+            },
+        ];
+        let annotations =
+            Annotations::parse("tables:\n  orders:\n    columns:\n      acct_typ: Kind of user account\n").unwrap();
+
+        let hits = search_catalog("app", &Pattern::parse("users", None).unwrap(), &tables, &annotations);
+        let found: Vec<(&str, &str, usize)> = hits.iter().map(|h| (h.kind, h.matched, h.score)).collect();
+        assert_eq!(found, vec![("table", "name", 105), ("table", "comment", 30)]);
+        assert_eq!(hits[0].table, "public.users");
+
+        let hits = search_catalog("app", &Pattern::parse("user", None).unwrap(), &tables, &annotations);
+        let found: Vec<(&str, Option<&str>, &str)> =
+            hits.iter().map(|h| (h.kind, h.column.as_deref(), h.matched)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("table", None, "name"),
+                ("column", Some("user_email"), "name"),
+                ("table", None, "comment"),
+                ("column", Some("acct_typ"), "annotation"),
+            ]
+        );
+        assert_eq!(hits[1].score, 75);
+        assert_eq!(hits[3].text.as_deref(), Some("Kind of user account"));
+    }
+}
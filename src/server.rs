@@ -6,11 +6,22 @@ use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::*;
 use rmcp::{schemars, tool, tool_handler, tool_router, ServerHandler};
 use serde::Deserialize;
+use sqlx::any::AnyRow;
 
+use crate::annotations::{self, AnnotationSet, Annotations};
 use crate::db::convert::row_to_json;
-use crate::db::dialect;
-use crate::db::{DatabaseManager, DbBackend};
+use crate::db::ident::QualifiedName;
+use crate::db::{catalog, constraints, dialect, exec};
+use crate::db::{DatabaseEntry, DatabaseManager, DbBackend};
 use crate::error::McpSqlError;
+use crate::infer;
+use crate::pending::PendingWrites;
+use crate::policy::{CostLimits, RateLimits, StatementPolicies};
+use crate::ratelimit::{RateLimiter, RatePermit};
+use crate::schema::{DiagramFormat, DiagramScope};
+use crate::search;
+use crate::suggest;
+use crate::sql::{self, contains_keyword};
 
 #[derive(Clone)]
 pub struct McpSqlServer {
@@ -18,6 +29,23 @@ pub struct McpSqlServer {
     allow_write: bool,
     row_limit: u32,
     query_timeout: Duration,
+    /// Set when writes must be confirmed with `confirm_write` before running.
+    pending_writes: Option<Arc<PendingWrites>>,
+    /// Writes affecting more rows than this are rolled back.
+    max_affected_rows: Option<u64>,
+    /// Reject UPDATE and DELETE statements that have no WHERE clause.
+    reject_unfiltered_writes: bool,
+    /// Statement kinds each database accepts, on top of the read-only guard.
+    statement_policies: Arc<StatementPolicies>,
+    /// Planner estimates statements must stay under, per database.
+    cost_limits: Arc<CostLimits>,
+    /// Concurrency caps and per-minute budgets, per database.
+    rate_limiter: Arc<RateLimiter>,
+    /// Table and column descriptions from annotation files, per database.
+    annotations: Arc<AnnotationSet>,
+    /// Show relationships guessed from column names in `describe_table` and
+    /// `show_schema`.
+    infer_relationships: bool,
     tool_router: ToolRouter<Self>,
 }
 
@@ -30,6 +58,17 @@ pub struct DatabaseParam {
     pub database: Option<String>,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTablesParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Only list tables in this schema (PostgreSQL schema, MySQL database or attached SQLite database)")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct DescribeTableParams {
     #[schemars(description = "Table name to describe (use schema.table for PostgreSQL)")]
@@ -38,6 +77,10 @@ pub struct DescribeTableParams {
     #[schemars(description = "Database name (optional if only one database is connected)")]
     #[serde(default)]
     pub database: Option<String>,
+
+    #[schemars(description = "Schema the table is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -52,6 +95,10 @@ pub struct SampleDataParams {
     #[schemars(description = "Number of sample rows to return (default: 5)")]
     #[serde(default)]
     pub limit: Option<u32>,
+
+    #[schemars(description = "Schema the table is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -62,6 +109,24 @@ pub struct ShowCreateTableParams {
     #[schemars(description = "Database name (optional if only one database is connected)")]
     #[serde(default)]
     pub database: Option<String>,
+
+    #[schemars(description = "Schema the table is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListConstraintsParams {
+    #[schemars(description = "Table name")]
+    pub table: String,
+
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Schema the table is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -72,6 +137,172 @@ pub struct ListIndexesParams {
     #[schemars(description = "Database name (optional if only one database is connected)")]
     #[serde(default)]
     pub database: Option<String>,
+
+    #[schemars(description = "Schema the table is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListRoutinesParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Only list routines in this schema (PostgreSQL schema or MySQL database)")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTypesParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Only list types and sequences in this PostgreSQL schema")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ShowRoutineParams {
+    #[schemars(description = "Function or procedure name")]
+    pub name: String,
+
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Schema the routine is in (PostgreSQL schema or MySQL database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTriggersParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Only list triggers in this schema (PostgreSQL schema, MySQL database or attached SQLite database)")]
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    #[schemars(description = "Only list triggers on this table")]
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ShowTriggerParams {
+    #[schemars(description = "Trigger name")]
+    pub name: String,
+
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Schema the trigger is in (PostgreSQL schema, MySQL database or attached SQLite database); defaults to the current one")]
+    #[serde(default)]
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ShowSchemaParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Tables to centre the diagram on (default: all tables)")]
+    #[serde(default)]
+    pub tables: Option<Vec<String>>,
+
+    #[schemars(description = "How many foreign key hops from the given tables to include (default: 1)")]
+    #[serde(default)]
+    pub hops: Option<usize>,
+
+    #[schemars(description = "Only include tables in this schema (default: every schema on PostgreSQL, the current database on MySQL)")]
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    #[schemars(description = "Only include tables whose name matches this glob, e.g. 'billing_*'")]
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    #[schemars(description = "Show only primary and foreign key columns (default: false)")]
+    #[serde(default)]
+    pub keys_only: Option<bool>,
+
+    #[schemars(description = "Diagram format: 'mermaid' (default), 'dbml' (dbdiagram.io), 'plantuml' or 'dot' (Graphviz)")]
+    #[serde(default)]
+    pub format: Option<String>,
+
+    #[schemars(description = "Also draw relationships guessed from column names, such as user_id to users.id, marked as inferred (default: on when the server infers relationships)")]
+    #[serde(default)]
+    pub inferred: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct InferRelationshipsParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Schema to look in (default: all user schemas)")]
+    #[serde(default)]
+    pub schema: Option<String>,
+
+    #[schemars(description = "Only relationships from or to this table")]
+    #[serde(default)]
+    pub table: Option<String>,
+
+    #[schemars(description = "Check each guess by sampling the column's values and counting those found in the referenced table; guesses with no matches are dropped (default: false)")]
+    #[serde(default)]
+    pub verify: Option<bool>,
+
+    #[schemars(description = "Distinct values to sample per column when verifying (default: 100)")]
+    #[serde(default)]
+    pub sample: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SchemaSummaryParams {
+    #[schemars(description = "Database name (optional if only one database is connected)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Maximum length of the summary in characters (default: 8000); the least connected, smallest tables are reduced to their names to fit")]
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchSchemaParams {
+    #[schemars(description = "Text to find in table names, column names, comments and annotations")]
+    pub term: String,
+
+    #[schemars(description = "How to match the term: substring (default), glob (* and ?, whole name; the default when the term has wildcards) or regex. Matching ignores case")]
+    #[serde(default)]
+    pub mode: Option<String>,
+
+    #[schemars(description = "Database name (optional; searches every database when omitted)")]
+    #[serde(default)]
+    pub database: Option<String>,
+
+    #[schemars(description = "Maximum number of hits to return (default: 50)")]
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchAnnotationsParams {
+    #[schemars(description = "Words to look for, e.g. \"refund\" or \"account type\"; every word must match")]
+    pub query: String,
+
+    #[schemars(description = "Database name (optional; searches every database when omitted)")]
+    #[serde(default)]
+    pub database: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -84,6 +315,12 @@ pub struct QueryParams {
     pub database: Option<String>,
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ConfirmWriteParams {
+    #[schemars(description = "Change id returned by query for a write awaiting confirmation")]
+    pub change_id: String,
+}
+
 impl McpSqlServer {
     pub fn new(db: DatabaseManager, allow_write: bool, row_limit: u32, query_timeout_secs: u64) -> Self {
         Self {
@@ -91,13 +328,253 @@ impl McpSqlServer {
             allow_write,
             row_limit,
             query_timeout: Duration::from_secs(query_timeout_secs),
+            pending_writes: None,
+            max_affected_rows: None,
+            reject_unfiltered_writes: false,
+            statement_policies: Arc::new(StatementPolicies::default()),
+            cost_limits: Arc::new(CostLimits::default()),
+            rate_limiter: Arc::new(RateLimiter::new(RateLimits::default())),
+            annotations: Arc::new(AnnotationSet::default()),
+            infer_relationships: false,
             tool_router: Self::tool_router(),
         }
     }
 
+    /// Hold write statements submitted to `query` until they are confirmed
+    /// with `confirm_write`. Unconfirmed changes expire after `ttl`.
+    pub fn with_write_confirmation(mut self, ttl: Duration) -> Self {
+        self.pending_writes = Some(Arc::new(PendingWrites::new(ttl)));
+        self
+    }
+
+    /// Roll back any write that affects more than `max` rows.
+    pub fn with_max_affected_rows(mut self, max: u64) -> Self {
+        self.max_affected_rows = Some(max);
+        self
+    }
+
+    /// Reject UPDATE and DELETE statements without a WHERE clause.
+    pub fn with_reject_unfiltered_writes(mut self) -> Self {
+        self.reject_unfiltered_writes = true;
+        self
+    }
+
+    /// Restrict which statement kinds each database accepts.
+    pub fn with_statement_policies(mut self, policies: StatementPolicies) -> Self {
+        self.statement_policies = Arc::new(policies);
+        self
+    }
+
+    /// Reject statements whose EXPLAIN estimates exceed each database's limits.
+    pub fn with_cost_limits(mut self, limits: CostLimits) -> Self {
+        self.cost_limits = Arc::new(limits);
+        self
+    }
+
+    /// Cap concurrent queries and per-minute queries and returned rows for
+    /// each database.
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Self {
+        self.rate_limiter = Arc::new(RateLimiter::new(limits));
+        self
+    }
+
+    /// Describe tables and columns from annotation files, for databases (or
+    /// columns) that carry no comments of their own.
+    pub fn with_annotations(mut self, annotations: AnnotationSet) -> Self {
+        self.annotations = Arc::new(annotations);
+        self
+    }
+
+    /// Mark foreign keys guessed from column names (`user_id` for `users.id`)
+    /// in `describe_table` and draw them in `show_schema`, for databases
+    /// that do not declare their foreign keys.
+    pub fn with_inferred_relationships(mut self) -> Self {
+        self.infer_relationships = true;
+        self
+    }
+
+    /// Annotations for `database`; empty when it has none.
+    fn annotations_for(&self, database: &str) -> &Annotations {
+        self.annotations.get(database).unwrap_or(&annotations::EMPTY)
+    }
+
     fn err(&self, e: McpSqlError) -> ErrorData {
         e.to_mcp_error()
     }
+
+    /// Like `err`, for errors raised while running `sql`.
+    fn sql_err(&self, e: McpSqlError, sql: &str) -> ErrorData {
+        e.to_mcp_error_for_sql(sql)
+    }
+
+    /// Like `sql_err`, but when the error is about an unknown table or column,
+    /// look up the closest existing names and add them as `suggestions`.
+    async fn suggest_err(&self, entry: &DatabaseEntry, e: McpSqlError, sql: Option<&str>) -> ErrorData {
+        let mut details = e.details(sql);
+        let Some(object) = details.object.clone() else {
+            return e.to_mcp_error_with_details(details);
+        };
+        let suggestions = match details.category {
+            "undefined_table" => {
+                let tables = dialect::table_names(&entry.pool, entry.backend)
+                    .await
+                    .unwrap_or_default();
+                suggest::closest(&object, tables.iter().map(String::as_str))
+            }
+            "undefined_column" => {
+                let mut columns = Vec::new();
+                for table in sql.map(sql::referenced_tables).unwrap_or_default() {
+                    let Ok(table) = QualifiedName::parse(&table) else {
+                        continue;
+                    };
+                    let described = dialect::describe_table(&entry.pool, entry.backend, &table)
+                        .await
+                        .unwrap_or_default();
+                    columns.extend(
+                        described
+                            .iter()
+                            .filter_map(|c| c.get("name")?.as_str().map(str::to_string)),
+                    );
+                }
+                suggest::closest(
+                    suggest::unqualified(&object),
+                    columns.iter().map(String::as_str),
+                )
+            }
+            _ => Vec::new(),
+        };
+        if suggestions.is_empty() {
+            return e.to_mcp_error_with_details(details);
+        }
+
+        let message = format!("{e} (did you mean: {}?)", suggestions.join(", "));
+        details
+            .extra
+            .insert("suggestions".to_string(), serde_json::json!(suggestions));
+        let mut error = e.to_mcp_error_with_details(details);
+        error.message = message.into();
+        error
+    }
+
+    /// Admit a request against `database` under its rate limits. Called
+    /// before any pool connection is acquired.
+    fn admit(&self, database: &str) -> Result<RatePermit, ErrorData> {
+        self.rate_limiter.acquire(database).map_err(|e| self.err(e))
+    }
+
+    /// EXPLAIN `sql` and reject it when the plan exceeds the database's cost
+    /// limit. Statements the backend cannot EXPLAIN (e.g. DDL) are let through,
+    /// but a query or data change whose plan can't be read is rejected.
+    async fn check_cost(&self, entry: &DatabaseEntry, sql: &str) -> Result<(), McpSqlError> {
+        let Some(limit) = self.cost_limits.get(&entry.name) else {
+            return Ok(());
+        };
+        let kinds = sql::classify(sql);
+        if kinds.len() > 1 {
+            return Err(McpSqlError::PolicyViolation(format!(
+                "database '{}' has a cost limit, so statements must be submitted one at a time",
+                entry.name
+            )));
+        }
+        let plan = match dialect::explain_summary(&entry.pool, entry.backend, sql).await {
+            Ok(plan) => plan,
+            Err(e) if kinds.first().is_some_and(is_explainable) => {
+                tracing::warn!(error = %e, "Rejecting statement whose cost cannot be checked");
+                return Err(McpSqlError::PolicyViolation(format!(
+                    "database '{}' has a cost limit, but the statement's plan could not be \
+                     checked: {e}",
+                    entry.name
+                )));
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "Skipping cost check for statement that cannot be explained");
+                return Ok(());
+            }
+        };
+        match limit.violation(&plan) {
+            Some(reason) => {
+                // The plan goes in the message too, for clients that drop error data
+                let plan = serde_json::to_value(&plan).unwrap_or_default();
+                Err(McpSqlError::QueryTooExpensive(format!("{reason}; plan: {plan}"), plan))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Reject UPDATE/DELETE without a WHERE clause when configured to.
+    fn check_write_filter(&self, sql: &str) -> Result<(), ErrorData> {
+        if !self.reject_unfiltered_writes {
+            return Ok(());
+        }
+        match unfiltered_write(sql) {
+            Some(reason) => Err(self.err(McpSqlError::PolicyViolation(reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Execute a statement that modifies data, enforcing the affected-rows cap.
+    async fn execute_write(&self, entry: &DatabaseEntry, sql: &str) -> Result<Vec<AnyRow>, McpSqlError> {
+        match self.max_affected_rows {
+            Some(max) => exec::fetch_all_capped(entry, sql, self.query_timeout, max).await,
+            None => exec::fetch_all(entry, sql, self.query_timeout, false).await,
+        }
+    }
+
+    /// In read-only mode, refuse ATTACH/DETACH on SQLite so a statement can't
+    /// reach (or create) database files other than the one we opened read-only.
+    fn check_attach(&self, backend: DbBackend, sql: &str) -> Result<(), ErrorData> {
+        if self.allow_write || backend != DbBackend::Sqlite {
+            return Ok(());
+        }
+        if contains_keyword(sql, "ATTACH") || contains_keyword(sql, "DETACH") {
+            return Err(self.err(McpSqlError::ReadOnly(
+                "ATTACH and DETACH are not allowed in read-only mode".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks for the tools that EXPLAIN `sql` rather than run it: it must be
+    /// a single statement that `query` would accept.
+    fn check_explained(&self, entry: &DatabaseEntry, sql: &str) -> Result<(), ErrorData> {
+        check_single_statement(sql).map_err(|e| self.err(e))?;
+        if !self.allow_write {
+            check_read_only(sql).map_err(|e| self.err(e))?;
+        }
+        self.statement_policies
+            .check(&entry.name, sql)
+            .map_err(|e| self.err(e))?;
+        self.check_attach(entry.backend, sql)
+    }
+
+    /// The server instructions, followed by each database's annotated
+    /// description, gotchas and example queries.
+    fn instructions(&self) -> String {
+        let mut text = String::from(
+            "SQL database server. Use list_databases to see connected databases, \
+             list_schemas to see schemas, list_tables to see tables, describe_table for schema details (includes foreign keys), \
+             show_create_table for DDL statements, schema_summary for a compact overview of every table, \
+             show_schema for an ER diagram, \
+             infer_relationships for likely joins the database does not declare as foreign keys, \
+             list_indexes for index details, list_constraints for keys and CHECK constraints, \
+             list_types for PostgreSQL enums, domains, composite types and sequences, \
+             list_routines and show_routine for stored functions and procedures, \
+             list_triggers and show_trigger for triggers, \
+             search_schema to find tables and columns by name, comment or annotation, \
+             search_annotations to find the tables and columns behind a concept, \
+             sample_data to preview table contents, \
+             query to run SQL, explain for query plans, query_dry_run to validate SQL without executing, \
+             preview_write to see the effect of an INSERT/UPDATE/DELETE before running it, \
+             and confirm_write to execute a write that query held for confirmation.",
+        );
+        for entry in &self.db.databases {
+            if let Some(notes) = self.annotations_for(&entry.name).instructions(&entry.name) {
+                text.push_str("\n\n");
+                text.push_str(&notes);
+            }
+        }
+        text
+    }
 }
 
 #[tool_router]
@@ -127,79 +604,147 @@ impl McpSqlServer {
 
     #[tool(
         name = "list_tables",
-        description = "List all tables in a database with approximate row counts"
+        description = "List all tables, views and materialized views in a database with approximate row counts and comments; kind is table, view or materialized_view"
     )]
     async fn list_tables(
         &self,
-        Parameters(params): Parameters<DatabaseParam>,
+        Parameters(params): Parameters<ListTablesParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
-        let tables = dialect::list_tables(&entry.pool, entry.backend)
+        let _permit = self.admit(&entry.name)?;
+        let mut tables = dialect::list_tables(&entry.pool, entry.backend, params.schema.as_deref())
             .await
             .map_err(|e| self.err(e))?;
+        self.annotations_for(&entry.name).apply_to_tables(&mut tables);
 
         let text = serde_json::to_string_pretty(&tables)
             .unwrap_or_else(|_| "[]".to_string());
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
 
+    #[tool(
+        name = "list_schemas",
+        description = "List schemas (PostgreSQL schemas, MySQL databases, attached SQLite databases) with table counts; is_default marks where unqualified table names resolve"
+    )]
+    async fn list_schemas(
+        &self,
+        Parameters(params): Parameters<DatabaseParam>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let schemas = dialect::list_schemas(&entry.pool, entry.backend)
+            .await
+            .map_err(|e| self.err(e))?;
+
+        let text = serde_json::to_string_pretty(&schemas)
+            .unwrap_or_else(|_| "[]".to_string());
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
     #[tool(
         name = "describe_table",
-        description = "Describe a table's columns with name, type, nullable, default, and primary key info"
+        description = "Describe a table's columns with name, type, nullable, default, primary key, foreign key, comment, enum labels and the meaning of coded values; the table's comment and known gotchas, if any, come first"
     )]
     async fn describe_table(
         &self,
         Parameters(params): Parameters<DescribeTableParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
-        let columns = dialect::describe_table(&entry.pool, entry.backend, &params.table)
+        let _permit = self.admit(&entry.name)?;
+        let table = QualifiedName::resolve(&params.table, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let mut columns = match dialect::describe_table(&entry.pool, entry.backend, &table).await {
+            Ok(columns) => columns,
+            Err(e) => return Err(self.suggest_err(entry, e, None).await),
+        };
+        let annotations = self.annotations_for(&entry.name);
+        let name = table.to_string();
+        annotations.apply_to_columns(&name, &mut columns);
+        if self.infer_relationships {
+            let tables = catalog::load_columns(&entry.pool, entry.backend, table.schema.as_deref())
+                .await
+                .map_err(|e| self.err(e))?;
+            infer::apply_to_columns(&infer::infer(&tables), &table, &mut columns);
+        }
+        let comment = dialect::table_comment(&entry.pool, entry.backend, &table)
             .await
             .map_err(|e| self.err(e))?;
 
         let text = serde_json::to_string_pretty(&columns)
             .unwrap_or_else(|_| "[]".to_string());
-        Ok(CallToolResult::success(vec![Content::text(text)]))
+        let mut content: Vec<Content> = annotations
+            .table_notes(&name, comment.as_deref())
+            .into_iter()
+            .map(Content::text)
+            .collect();
+        content.push(Content::text(text));
+        Ok(CallToolResult::success(content))
     }
 
     #[tool(
         name = "query",
-        description = "Execute a SQL query and return results as JSON. Read-only by default (SELECT/WITH/SHOW/PRAGMA only). Use --allow-write flag to enable write operations."
+        description = "Execute a SQL query and return results as JSON. Read-only by default (SELECT/WITH/SHOW/PRAGMA only). Use --allow-write flag to enable write operations. A query that times out is cancelled on PostgreSQL and MySQL; on SQLite it cannot be interrupted and keeps running in the background until it completes."
     )]
     async fn query(
         &self,
         Parameters(params): Parameters<QueryParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let permit = self.admit(&entry.name)?;
         let sql = params.sql.trim();
 
         // Read-only guard
         if !self.allow_write {
             check_read_only(sql).map_err(|e| self.err(e))?;
         }
+        self.statement_policies
+            .check(&entry.name, sql)
+            .map_err(|e| self.err(e))?;
+        self.check_attach(entry.backend, sql)?;
 
-        // Set transaction read only for backends that support it
-        if !self.allow_write && entry.backend != DbBackend::Sqlite {
-            let read_only_sql = match entry.backend {
-                DbBackend::Postgres => "SET TRANSACTION READ ONLY",
-                DbBackend::Mysql => "SET TRANSACTION READ ONLY",
-                DbBackend::Sqlite => unreachable!(),
-            };
-            // Best effort — some connection states may not support this
-            let _ = sqlx::query(read_only_sql).execute(&entry.pool).await;
+        let is_write = check_read_only(sql).is_err();
+        if is_write {
+            self.check_write_filter(sql)?;
+        }
+        self.check_cost(entry, sql).await.map_err(|e| self.err(e))?;
+
+        // Two-phase writes: hold the statement until it is confirmed
+        if let Some(pending) = &self.pending_writes {
+            if is_write {
+                let change_id = pending.insert(&entry.name, sql);
+                let estimated_rows =
+                    dialect::estimate_affected_rows(entry, sql, self.query_timeout).await;
+                let text = serde_json::to_string_pretty(&serde_json::json!({
+                    "status": "pending_confirmation",
+                    "change_id": change_id,
+                    "database": entry.name,
+                    "statement": sql::statement_keyword(sql),
+                    "sql": sql,
+                    "tables": sql::referenced_tables(sql),
+                    "estimated_rows": estimated_rows,
+                    "expires_in_secs": pending.ttl().as_secs(),
+                    "next_step": "Call confirm_write with this change_id to execute the statement",
+                }))
+                .unwrap_or_else(|_| "{}".to_string());
+                return Ok(CallToolResult::success(vec![Content::text(text)]));
+            }
         }
 
         // Inject LIMIT if not present
         let limited_sql = inject_limit(sql, self.row_limit);
 
-        let rows = tokio::time::timeout(
-            self.query_timeout,
-            sqlx::query(&limited_sql).fetch_all(&entry.pool),
-        )
-        .await
-        .map_err(|_| self.err(McpSqlError::QueryTimeout(self.query_timeout.as_secs())))?
-        .map_err(|e| self.err(McpSqlError::Database(e)))?;
+        let rows = if is_write {
+            self.execute_write(entry, &limited_sql).await
+        } else {
+            exec::fetch_all(entry, &limited_sql, self.query_timeout, !self.allow_write).await
+        };
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(e) => return Err(self.suggest_err(entry, e, Some(&limited_sql)).await),
+        };
 
         let results: Vec<serde_json::Value> = rows.iter().map(row_to_json).collect();
+        permit.record_rows(results.len() as u64);
         let text = serde_json::to_string_pretty(&serde_json::json!({
             "rows": results,
             "count": results.len(),
@@ -209,6 +754,72 @@ impl McpSqlServer {
         Ok(CallToolResult::success(vec![Content::text(text)]))
     }
 
+    #[tool(
+        name = "confirm_write",
+        description = "Execute a write statement previously held by query for confirmation. Pass the change_id it returned. Only available when the server runs with --confirm-writes."
+    )]
+    async fn confirm_write(
+        &self,
+        Parameters(params): Parameters<ConfirmWriteParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let pending = self.pending_writes.as_ref().ok_or_else(|| {
+            self.err(McpSqlError::Other(
+                "Write confirmation is not enabled; start the server with --confirm-writes"
+                    .to_string(),
+            ))
+        })?;
+        let database = pending.database_of(&params.change_id).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&database)?;
+        let write = pending.take(&params.change_id).map_err(|e| self.err(e))?;
+        let entry = self.db.resolve(Some(&write.database)).map_err(|e| self.err(e))?;
+
+        let rows = self
+            .execute_write(entry, &write.sql)
+            .await
+            .map_err(|e| self.sql_err(e, &write.sql))?;
+
+        let results: Vec<serde_json::Value> = rows.iter().map(row_to_json).collect();
+        let text = serde_json::to_string_pretty(&serde_json::json!({
+            "change_id": params.change_id,
+            "status": "executed",
+            "rows": results,
+            "count": results.len(),
+        }))
+        .unwrap_or_else(|_| "{}".to_string());
+
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "preview_write",
+        description = "Preview an INSERT, UPDATE or DELETE: runs it inside a transaction that is always rolled back and returns the affected row count plus before/after samples of the affected rows (matched by primary key). Requires --allow-write."
+    )]
+    async fn preview_write(
+        &self,
+        Parameters(params): Parameters<QueryParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        if !self.allow_write {
+            return Err(self.err(McpSqlError::ReadOnly(
+                "preview_write is only available when the server is started with --allow-write"
+                    .to_string(),
+            )));
+        }
+
+        self.statement_policies
+            .check(&entry.name, &params.sql)
+            .map_err(|e| self.err(e))?;
+
+        let preview = exec::preview_write(entry, params.sql.trim(), self.query_timeout)
+            .await
+            .map_err(|e| self.sql_err(e, params.sql.trim()))?;
+
+        let text = serde_json::to_string_pretty(&preview)
+            .unwrap_or_else(|_| "{}".to_string());
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
     #[tool(
         name = "explain",
         description = "Show the query execution plan for a SQL statement. Uses the appropriate EXPLAIN syntax for the database type."
@@ -218,16 +829,15 @@ impl McpSqlServer {
         Parameters(params): Parameters<QueryParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        self.check_explained(entry, params.sql.trim())?;
         let prefix = dialect::explain_prefix(entry.backend);
         let explain_sql = format!("{}{}", prefix, params.sql.trim());
 
-        let rows = tokio::time::timeout(
-            self.query_timeout,
-            sqlx::query(&explain_sql).fetch_all(&entry.pool),
-        )
-        .await
-        .map_err(|_| self.err(McpSqlError::QueryTimeout(self.query_timeout.as_secs())))?
-        .map_err(|e| self.err(McpSqlError::Database(e)))?;
+        // EXPLAIN ANALYZE runs the statement, so plans are always read-only
+        let rows = exec::fetch_all(entry, &explain_sql, self.query_timeout, true)
+            .await
+            .map_err(|e| e.to_mcp_error_for_prefixed_sql(prefix, params.sql.trim()))?;
 
         let results: Vec<serde_json::Value> = rows.iter().map(row_to_json).collect();
         let text = serde_json::to_string_pretty(&results)
@@ -245,18 +855,21 @@ impl McpSqlServer {
         Parameters(params): Parameters<SampleDataParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let permit = self.admit(&entry.name)?;
         let limit = params.limit.unwrap_or(5);
 
-        let rows = tokio::time::timeout(
-            self.query_timeout,
-            dialect::sample_data(&entry.pool, entry.backend, &params.table, limit),
-        )
-        .await
-        .map_err(|_| self.err(McpSqlError::QueryTimeout(self.query_timeout.as_secs())))?
-        .map_err(|e| self.err(e))?;
+        let table = QualifiedName::resolve(&params.table, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let sql = dialect::sample_data_sql(entry.backend, &table, limit);
+        let rows: Vec<serde_json::Value> =
+            match exec::fetch_all(entry, &sql, self.query_timeout, true).await {
+                Ok(rows) => rows.iter().map(row_to_json).collect(),
+                Err(e) => return Err(self.suggest_err(entry, e, Some(&sql)).await),
+            };
+        permit.record_rows(rows.len() as u64);
 
         let text = serde_json::to_string_pretty(&serde_json::json!({
-            "table": params.table,
+            "table": table.to_string(),
             "rows": rows,
             "count": rows.len(),
         }))
@@ -267,34 +880,101 @@ impl McpSqlServer {
 
     #[tool(
         name = "show_create_table",
-        description = "Show the CREATE TABLE DDL statement for a table"
+        description = "Show the CREATE TABLE DDL statement for a table, or the CREATE VIEW definition for a view or materialized view"
     )]
     async fn show_create_table(
         &self,
         Parameters(params): Parameters<ShowCreateTableParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
-        let ddl = dialect::show_create_table(&entry.pool, entry.backend, &params.table)
-            .await
+        let _permit = self.admit(&entry.name)?;
+        let table = QualifiedName::resolve(&params.table, params.schema.as_deref())
             .map_err(|e| self.err(e))?;
+        let ddl = match dialect::show_create_table(&entry.pool, entry.backend, &table).await {
+            Ok(ddl) => ddl,
+            Err(e) => return Err(self.suggest_err(entry, e, None).await),
+        };
         Ok(CallToolResult::success(vec![Content::text(ddl)]))
     }
 
     #[tool(
         name = "show_schema",
-        description = "Show a Mermaid ER diagram of all tables and their relationships in the database"
+        description = "Show an ER diagram of tables and their relationships, with table and column comments, as Mermaid, DBML, PlantUML or Graphviz DOT. On large databases, focus on some tables and their foreign key neighbours, filter by schema or name glob, or show only key columns"
     )]
     async fn show_schema(
         &self,
-        Parameters(params): Parameters<DatabaseParam>,
+        Parameters(params): Parameters<ShowSchemaParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
-        let diagram = crate::schema::generate_mermaid_er(&entry.pool, entry.backend)
+        let _permit = self.admit(&entry.name)?;
+        let annotations = self.annotations_for(&entry.name);
+        let format = match params.format.as_deref() {
+            Some(format) => DiagramFormat::parse(format).map_err(|e| self.err(e))?,
+            None => DiagramFormat::default(),
+        };
+        let scope = DiagramScope {
+            schema: params.schema,
+            pattern: params.pattern,
+            focus: params.tables.unwrap_or_default(),
+            hops: params.hops.unwrap_or(1),
+            keys_only: params.keys_only.unwrap_or(false),
+            inferred: params.inferred.unwrap_or(self.infer_relationships),
+        };
+        let diagram =
+            crate::schema::generate_er(&entry.pool, entry.backend, annotations, &scope, format)
             .await
             .map_err(|e| self.err(e))?;
         Ok(CallToolResult::success(vec![Content::text(diagram)]))
     }
 
+    #[tool(
+        name = "schema_summary",
+        description = "Compact text summary of the whole database: one line per table with row count and columns (type, PK/UQ/FK markers, NOT NULL, defaults), within a size budget. Cheaper than show_schema for getting oriented"
+    )]
+    async fn schema_summary(
+        &self,
+        Parameters(params): Parameters<SchemaSummaryParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let mut tables = catalog::load(&entry.pool, entry.backend, None)
+            .await
+            .map_err(|e| self.err(e))?;
+        catalog::count_rows(entry, None, &mut tables).await;
+        let summary = crate::schema::schema_summary(&tables, params.max_chars.unwrap_or(8000));
+        Ok(CallToolResult::success(vec![Content::text(summary)]))
+    }
+
+    #[tool(
+        name = "infer_relationships",
+        description = "Guess foreign keys the database does not declare from column names and types, e.g. posts.user_id to users.id. Optionally verify each guess by sampling values. Results are guesses, not constraints"
+    )]
+    async fn infer_relationships(
+        &self,
+        Parameters(params): Parameters<InferRelationshipsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let tables = catalog::load(&entry.pool, entry.backend, params.schema.as_deref())
+            .await
+            .map_err(|e| self.err(e))?;
+        let mut keys = infer::infer(&tables);
+        if let Some(table) = params.table.as_deref() {
+            let same = |name: &str| name == table || suggest::unqualified(name) == table;
+            keys.retain(|k| same(&k.table) || same(&k.referenced_table));
+        }
+        if params.verify.unwrap_or(false) {
+            let sample = params.sample.unwrap_or(100).max(1);
+            for key in &keys {
+                let sql = infer::verification_sql(entry.backend, key, sample);
+                self.check_cost(entry, &sql).await.map_err(|e| self.err(e))?;
+            }
+            infer::verify(entry, &mut keys, sample, self.query_timeout).await;
+        }
+        let text = serde_json::to_string_pretty(&keys).unwrap_or_else(|_| "[]".to_string());
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
     #[tool(
         name = "list_indexes",
         description = "List all indexes on a table with column names and uniqueness"
@@ -304,13 +984,208 @@ impl McpSqlServer {
         Parameters(params): Parameters<ListIndexesParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
-        let indexes = dialect::list_indexes(&entry.pool, entry.backend, &params.table)
+        let _permit = self.admit(&entry.name)?;
+        let table = QualifiedName::resolve(&params.table, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let indexes = dialect::list_indexes(&entry.pool, entry.backend, &table)
             .await
             .map_err(|e| self.err(e))?;
         let json = serde_json::to_string_pretty(&indexes).unwrap_or_default();
         Ok(CallToolResult::success(vec![Content::text(json)]))
     }
 
+    #[tool(
+        name = "list_constraints",
+        description = "List the constraints on a table: primary key, unique, foreign keys (with referenced columns and ON DELETE/ON UPDATE actions), CHECK expressions and exclusion constraints"
+    )]
+    async fn list_constraints(
+        &self,
+        Parameters(params): Parameters<ListConstraintsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let table = QualifiedName::resolve(&params.table, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let constraints = constraints::list_constraints(&entry.pool, entry.backend, &table)
+            .await
+            .map_err(|e| self.err(e))?;
+        let json = serde_json::to_string_pretty(&constraints).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "list_routines",
+        description = "List stored functions and procedures with their kind, arguments, return type and language"
+    )]
+    async fn list_routines(
+        &self,
+        Parameters(params): Parameters<ListRoutinesParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let routines = dialect::list_routines(&entry.pool, entry.backend, params.schema.as_deref())
+            .await
+            .map_err(|e| self.err(e))?;
+        let json = serde_json::to_string_pretty(&routines).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "list_types",
+        description = "List PostgreSQL enums (with labels), domains (with base type and checks), composite types (with attributes) and sequences (with settings, last value and owning column)"
+    )]
+    async fn list_types(
+        &self,
+        Parameters(params): Parameters<ListTypesParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let types = dialect::list_types(&entry.pool, entry.backend, params.schema.as_deref())
+            .await
+            .map_err(|e| self.err(e))?;
+        let json = serde_json::to_string_pretty(&types).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "show_routine",
+        description = "Show the CREATE FUNCTION or CREATE PROCEDURE statement of a routine, including its body; overloads sharing the name are all shown"
+    )]
+    async fn show_routine(
+        &self,
+        Parameters(params): Parameters<ShowRoutineParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let routine = QualifiedName::resolve(&params.name, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let definition = dialect::show_routine(&entry.pool, entry.backend, &routine)
+            .await
+            .map_err(|e| self.err(e))?;
+        Ok(CallToolResult::success(vec![Content::text(definition)]))
+    }
+
+    #[tool(
+        name = "list_triggers",
+        description = "List triggers with their table, timing (BEFORE/AFTER/INSTEAD OF), firing events and level (ROW or STATEMENT), optionally for one table"
+    )]
+    async fn list_triggers(
+        &self,
+        Parameters(params): Parameters<ListTriggersParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let table = params
+            .table
+            .as_deref()
+            .map(|table| QualifiedName::resolve(table, params.schema.as_deref()))
+            .transpose()
+            .map_err(|e| self.err(e))?;
+        let schema = match &table {
+            Some(table) => table.schema.as_deref(),
+            None => params.schema.as_deref(),
+        };
+        let triggers = dialect::list_triggers(
+            &entry.pool,
+            entry.backend,
+            schema,
+            table.as_ref().map(|t| t.name.as_str()),
+        )
+        .await
+        .map_err(|e| self.err(e))?;
+        let json = serde_json::to_string_pretty(&triggers).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "show_trigger",
+        description = "Show the CREATE TRIGGER statement of a trigger; on PostgreSQL also the function it runs"
+    )]
+    async fn show_trigger(
+        &self,
+        Parameters(params): Parameters<ShowTriggerParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        let trigger = QualifiedName::resolve(&params.name, params.schema.as_deref())
+            .map_err(|e| self.err(e))?;
+        let definition = dialect::show_trigger(&entry.pool, entry.backend, &trigger)
+            .await
+            .map_err(|e| self.err(e))?;
+        Ok(CallToolResult::success(vec![Content::text(definition)]))
+    }
+
+    #[tool(
+        name = "search_schema",
+        description = "Find tables and columns whose name, comment or annotation matches a term (substring, glob or regex), in one or every database; hits are ranked with exact and prefix name matches first"
+    )]
+    async fn search_schema(
+        &self,
+        Parameters(params): Parameters<SearchSchemaParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let pattern = search::Pattern::parse(&params.term, params.mode.as_deref()).map_err(|e| self.err(e))?;
+        let entries: Vec<&DatabaseEntry> = match params.database.as_deref() {
+            Some(name) => vec![self.db.resolve(Some(name)).map_err(|e| self.err(e))?],
+            None => self.db.databases.iter().collect(),
+        };
+
+        let mut hits = Vec::new();
+        for entry in entries {
+            let _permit = self.admit(&entry.name)?;
+            let tables = catalog::load(&entry.pool, entry.backend, None)
+                .await
+                .map_err(|e| self.err(e))?;
+            hits.extend(search::search_catalog(
+                &entry.name,
+                &pattern,
+                &tables,
+                self.annotations_for(&entry.name),
+            ));
+        }
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits.truncate(params.limit.unwrap_or(50));
+
+        let json = serde_json::to_string_pretty(&hits).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
+    #[tool(
+        name = "search_annotations",
+        description = "Search the annotation files for tables, columns, coded values, example queries and gotchas mentioning a concept, best matches first"
+    )]
+    async fn search_annotations(
+        &self,
+        Parameters(params): Parameters<SearchAnnotationsParams>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let databases: Vec<&str> = match params.database.as_deref() {
+            Some(name) => vec![self.db.resolve(Some(name)).map_err(|e| self.err(e))?.name.as_str()],
+            None => self.db.databases.iter().map(|d| d.name.as_str()).collect(),
+        };
+        let mut hits: Vec<(&str, annotations::AnnotationHit)> = databases
+            .into_iter()
+            .flat_map(|db| {
+                self.annotations_for(db)
+                    .search(&params.query)
+                    .into_iter()
+                    .map(move |hit| (db, hit))
+            })
+            .collect();
+        hits.sort_by_key(|(_, hit)| std::cmp::Reverse(hit.score));
+
+        let results: Vec<serde_json::Value> = hits
+            .into_iter()
+            .map(|(database, hit)| {
+                let mut value = serde_json::to_value(hit).unwrap_or_default();
+                if let serde_json::Value::Object(map) = &mut value {
+                    map.insert("database".to_string(), database.into());
+                }
+                value
+            })
+            .collect();
+        let json = serde_json::to_string_pretty(&results).unwrap_or_default();
+        Ok(CallToolResult::success(vec![Content::text(json)]))
+    }
+
     #[tool(
         name = "query_dry_run",
         description = "Validate a SQL query without executing it. Returns the query plan and any warnings."
@@ -320,15 +1195,14 @@ impl McpSqlServer {
         Parameters(params): Parameters<QueryParams>,
     ) -> Result<CallToolResult, ErrorData> {
         let entry = self.db.resolve(params.database.as_deref()).map_err(|e| self.err(e))?;
+        let _permit = self.admit(&entry.name)?;
+        self.check_explained(entry, &params.sql)?;
 
         // Use EXPLAIN to validate without executing
-        let explain_sql = format!(
-            "{}{}",
-            dialect::explain_prefix(entry.backend),
-            params.sql
-        );
+        let prefix = dialect::explain_prefix(entry.backend);
+        let explain_sql = format!("{}{}", prefix, params.sql);
 
-        match sqlx::query(&explain_sql).fetch_all(&entry.pool).await {
+        match exec::fetch_all(entry, &explain_sql, self.query_timeout, true).await {
             Ok(rows) => {
                 let plan: Vec<serde_json::Value> = rows.iter().map(row_to_json).collect();
                 let result = serde_json::json!({
@@ -340,9 +1214,12 @@ impl McpSqlServer {
                 )]))
             }
             Err(e) => {
+                let message = e.to_string();
+                let details = e.details_for_prefixed_sql(prefix, &params.sql);
                 let result = serde_json::json!({
                     "valid": false,
-                    "error": e.to_string(),
+                    "error": message,
+                    "details": details,
                 });
                 Ok(CallToolResult::success(vec![Content::text(
                     serde_json::to_string_pretty(&result).unwrap_or_default(),
@@ -363,23 +1240,52 @@ impl ServerHandler for McpSqlServer {
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 ..Default::default()
             },
-            instructions: Some(
-                "SQL database server. Use list_databases to see connected databases, \
-                 list_tables to see tables, describe_table for schema details (includes foreign keys), \
-                 show_create_table for DDL statements, show_schema for a Mermaid ER diagram, \
-                 list_indexes for index details, sample_data to preview table contents, \
-                 query to run SQL, explain for query plans, and query_dry_run to validate SQL without executing."
-                    .to_string(),
-            ),
+            instructions: Some(self.instructions()),
+        }
+    }
+}
+
+/// Whether the planner can estimate a statement of this kind.
+fn is_explainable(kind: &sql::StatementKind) -> bool {
+    matches!(
+        kind,
+        sql::StatementKind::Select
+            | sql::StatementKind::Insert
+            | sql::StatementKind::Update
+            | sql::StatementKind::Delete
+            | sql::StatementKind::Merge
+    )
+}
+
+/// Why `sql` would change every row of a table, if it would: it holds an
+/// UPDATE or DELETE without a WHERE clause, or one too unusual to tell.
+fn unfiltered_write(sql: &str) -> Option<String> {
+    for statement in sql::data_modifying_statements(sql) {
+        let keyword = sql::statement_keyword(statement).unwrap_or_default();
+        if keyword != "UPDATE" && keyword != "DELETE" {
+            continue;
+        }
+        match sql::parse_write(statement) {
+            Some(target) if target.where_clause.is_some() => {}
+            Some(target) => {
+                return Some(format!(
+                    "{} on '{}' has no WHERE clause and would affect every row",
+                    target.kind.name(),
+                    target.table
+                ))
+            }
+            None => return Some(format!("Could not check {keyword} for a WHERE clause")),
         }
     }
+    None
 }
 
-/// Heuristic check: only allow SELECT, WITH, SHOW, PRAGMA, EXPLAIN.
+/// Heuristic check: only allow SELECT, WITH, SHOW, PRAGMA, EXPLAIN. Every
+/// statement in a multi-statement string must pass, and `WITH` counts as the
+/// statement that follows its CTEs.
 fn check_read_only(sql: &str) -> Result<(), McpSqlError> {
-    let upper = sql.trim_start().to_uppercase();
-    let allowed_prefixes = ["SELECT", "WITH", "SHOW", "PRAGMA", "EXPLAIN"];
-    if allowed_prefixes.iter().any(|p| upper.starts_with(p)) {
+    let kinds = sql::classify(sql);
+    if !kinds.is_empty() && kinds.iter().all(|k| k.is_read_only()) {
         Ok(())
     } else {
         Err(McpSqlError::ReadOnly(
@@ -390,6 +1296,16 @@ fn check_read_only(sql: &str) -> Result<(), McpSqlError> {
     }
 }
 
+/// Reject anything but exactly one statement, for tools that prefix it with
+/// EXPLAIN: the backend would run any statement after the first as it is.
+fn check_single_statement(sql: &str) -> Result<(), McpSqlError> {
+    if sql::classify(sql).len() == 1 {
+        Ok(())
+    } else {
+        Err(McpSqlError::InvalidSql("Expected exactly one SQL statement".to_string()))
+    }
+}
+
 /// Inject a LIMIT clause if the query doesn't already have one.
 fn inject_limit(sql: &str, limit: u32) -> String {
     let upper = sql.to_uppercase();
@@ -423,6 +1339,31 @@ mod tests {
         assert!(check_read_only("DELETE FROM users").is_err());
         assert!(check_read_only("DROP TABLE users").is_err());
         assert!(check_read_only("CREATE TABLE t (id INT)").is_err());
+        assert!(check_read_only("SELECT 1; DROP TABLE users").is_err());
+        assert!(check_read_only("WITH x AS (SELECT 1) DELETE FROM users").is_err());
+        assert!(check_read_only("WITH d AS (DELETE FROM users RETURNING *) SELECT 1").is_err());
+        assert!(check_read_only("EXPLAIN ANALYZE DELETE FROM users").is_err());
+        assert!(check_read_only("").is_err());
+    }
+
+    #[test]
+    fn test_check_single_statement() {
+        assert!(check_single_statement("SELECT * FROM users;").is_ok());
+        assert!(check_single_statement("DELETE FROM users WHERE id = 1").is_ok());
+        assert!(check_single_statement("SELECT 1; DELETE FROM comments").is_err());
+        assert!(check_single_statement("  ").is_err());
+    }
+
+    #[test]
+    fn test_unfiltered_write() {
+        assert_eq!(unfiltered_write("SELECT * FROM users"), None);
+        assert_eq!(unfiltered_write("UPDATE users SET a = 1 WHERE id = 2"), None);
+        assert_eq!(unfiltered_write("INSERT INTO users (a) VALUES (1)"), None);
+        assert!(unfiltered_write("DELETE FROM users").is_some());
+        assert!(unfiltered_write("WITH x AS (SELECT 1) DELETE FROM users").is_some());
+        assert!(unfiltered_write("SELECT 1; DELETE FROM users").is_some());
+        assert!(unfiltered_write("DELETE FROM users WHERE id = 1; UPDATE t SET a = 1").is_some());
+        assert!(unfiltered_write("DELETE;").is_some());
     }
 
     #[test]
@@ -0,0 +1,907 @@
+//! Lightweight lexical analysis of SQL text.
+//!
+//! This is not a parser: it only knows enough about quoting, comments and
+//! parentheses to find keywords and the target of a DML statement reliably.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A bare word: keyword, identifier or number.
+    Word,
+    /// A double-quoted or backtick-quoted identifier.
+    Quoted,
+    /// A `;` statement separator.
+    Semicolon,
+}
+
+/// A word or quoted identifier in a SQL statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The token text, without surrounding quotes.
+    pub text: &'a str,
+    /// Byte offset where the token starts (including any opening quote).
+    pub start: usize,
+    /// Byte offset just past the token (including any closing quote).
+    pub end: usize,
+    /// Parenthesis nesting depth at the token.
+    pub depth: usize,
+}
+
+impl Token<'_> {
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+/// Split SQL into words and quoted identifiers, skipping string literals and
+/// comments.
+pub fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'\'' => {
+                // Doubled quotes close and immediately reopen, which is equivalent
+                i = skip_past(bytes, i + 1, b'\'');
+            }
+            b'"' | b'`' => {
+                let close = skip_past(bytes, i + 1, c);
+                let text_end = if close > i + 1 && bytes[close - 1] == c { close - 1 } else { close };
+                tokens.push(Token {
+                    kind: TokenKind::Quoted,
+                    text: &sql[i + 1..text_end],
+                    start: i,
+                    end: close,
+                    depth,
+                });
+                i = close;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = skip_past(bytes, i + 2, b'\n');
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = sql[i + 2..].find("*/").map(|p| i + 2 + p + 2).unwrap_or(bytes.len());
+            }
+            b';' => {
+                tokens.push(Token {
+                    kind: TokenKind::Semicolon,
+                    text: &sql[i..i + 1],
+                    start: i,
+                    end: i + 1,
+                    depth,
+                });
+                i += 1;
+            }
+            b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            _ if is_word_byte(c) => {
+                let start = i;
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    text: &sql[start..i],
+                    start,
+                    end: i,
+                    depth,
+                });
+            }
+            _ => i += 1,
+        }
+    }
+
+    tokens
+}
+
+/// Whether `keyword` appears as a bare word in `sql`, ignoring string
+/// literals, quoted identifiers and comments.
+pub fn contains_keyword(sql: &str, keyword: &str) -> bool {
+    tokenize(sql).iter().any(|t| t.is_keyword(keyword))
+}
+
+/// The kind of a SQL statement, from its leading keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatementKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Merge,
+    Create,
+    Alter,
+    Drop,
+    Truncate,
+    Grant,
+    Revoke,
+    Show,
+    Pragma,
+    Explain,
+    /// Any other leading keyword, uppercased.
+    Other(String),
+}
+
+impl StatementKind {
+    /// Keywords `from_keyword` maps to a kind other than `Other`.
+    pub const KEYWORDS: &'static [&'static str] = &[
+        "SELECT", "VALUES", "TABLE", "INSERT", "REPLACE", "UPDATE", "DELETE", "MERGE", "CREATE",
+        "ALTER", "DROP", "TRUNCATE", "GRANT", "REVOKE", "SHOW", "PRAGMA", "EXPLAIN",
+    ];
+
+    /// Map a keyword (case-insensitive) to its statement kind.
+    pub fn from_keyword(keyword: &str) -> Self {
+        match keyword.to_uppercase().as_str() {
+            "SELECT" | "VALUES" | "TABLE" => StatementKind::Select,
+            "INSERT" | "REPLACE" => StatementKind::Insert,
+            "UPDATE" => StatementKind::Update,
+            "DELETE" => StatementKind::Delete,
+            "MERGE" => StatementKind::Merge,
+            "CREATE" => StatementKind::Create,
+            "ALTER" => StatementKind::Alter,
+            "DROP" => StatementKind::Drop,
+            "TRUNCATE" => StatementKind::Truncate,
+            "GRANT" => StatementKind::Grant,
+            "REVOKE" => StatementKind::Revoke,
+            "SHOW" => StatementKind::Show,
+            "PRAGMA" => StatementKind::Pragma,
+            "EXPLAIN" => StatementKind::Explain,
+            other => StatementKind::Other(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            StatementKind::Select => "SELECT",
+            StatementKind::Insert => "INSERT",
+            StatementKind::Update => "UPDATE",
+            StatementKind::Delete => "DELETE",
+            StatementKind::Merge => "MERGE",
+            StatementKind::Create => "CREATE",
+            StatementKind::Alter => "ALTER",
+            StatementKind::Drop => "DROP",
+            StatementKind::Truncate => "TRUNCATE",
+            StatementKind::Grant => "GRANT",
+            StatementKind::Revoke => "REVOKE",
+            StatementKind::Show => "SHOW",
+            StatementKind::Pragma => "PRAGMA",
+            StatementKind::Explain => "EXPLAIN",
+            StatementKind::Other(keyword) => keyword,
+        }
+    }
+
+    /// Statement kinds permitted in read-only mode.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            StatementKind::Select | StatementKind::Show | StatementKind::Pragma | StatementKind::Explain
+        )
+    }
+}
+
+/// Classify every `;`-separated statement in `sql`. A `WITH` statement is
+/// classified by the statement that follows its common table expressions,
+/// so `WITH x AS (...) DELETE ...` is a DELETE, unless one of the common
+/// table expressions modifies data: `WITH d AS (DELETE ...) SELECT ...` is a
+/// DELETE too. `EXPLAIN ANALYZE`, which runs its statement, is classified as
+/// that statement.
+pub fn classify(sql: &str) -> Vec<StatementKind> {
+    let tokens = tokenize(sql);
+    tokens
+        .split(|t| t.kind == TokenKind::Semicolon)
+        .filter_map(classify_statement)
+        .collect()
+}
+
+const DML_KEYWORDS: &[&str] = &["INSERT", "REPLACE", "UPDATE", "DELETE", "MERGE"];
+
+fn classify_statement(statement: &[Token<'_>]) -> Option<StatementKind> {
+    let start = statement.iter().position(|t| t.kind == TokenKind::Word)?;
+    let statement = &statement[start..];
+    let first = &statement[0];
+
+    if first.is_keyword("EXPLAIN") {
+        let inner = statement.iter().position(|t| {
+            t.depth == first.depth
+                && ["SELECT", "VALUES", "TABLE", "WITH", "CREATE", "EXECUTE", "DECLARE"]
+                    .iter()
+                    .chain(DML_KEYWORDS)
+                    .any(|k| t.is_keyword(k))
+        });
+        let options = &statement[..inner.unwrap_or(statement.len())];
+        if options.iter().any(|t| t.is_keyword("ANALYZE") || t.is_keyword("ANALYSE")) {
+            // Whatever it runs, it is not a plain EXPLAIN
+            return Some(match inner {
+                Some(inner) => classify_statement(&statement[inner..])?,
+                None => StatementKind::Other("EXPLAIN ANALYZE".to_string()),
+            });
+        }
+    }
+    if !first.is_keyword("WITH") {
+        return Some(StatementKind::from_keyword(first.text));
+    }
+    let write = (0..statement.len()).find(|&i| {
+        DML_KEYWORDS.iter().any(|k| statement[i].is_keyword(k)) && starts_statement(statement, i)
+    });
+    let main = statement.iter().find(|t| {
+        t.depth == first.depth
+            && ["SELECT", "INSERT", "UPDATE", "DELETE", "MERGE"].iter().any(|k| t.is_keyword(k))
+    });
+    Some(
+        write
+            .map(|i| &statement[i])
+            .or(main)
+            .map_or(StatementKind::Select, |t| StatementKind::from_keyword(t.text)),
+    )
+}
+
+/// The text of every data-modifying statement in `sql`: each `;`-separated
+/// INSERT, UPDATE, DELETE or MERGE, and those nested in a WITH, as its main
+/// statement or as the body of a common table expression.
+pub fn data_modifying_statements(sql: &str) -> Vec<&str> {
+    let tokens = tokenize(sql);
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| DML_KEYWORDS.iter().any(|k| t.is_keyword(k)))
+        .filter(|(i, _)| starts_statement(&tokens, *i))
+        .map(|(_, t)| &sql[t.start..statement_end(sql.as_bytes(), t.start)])
+        .collect()
+}
+
+/// Whether a common table expression in `sql` modifies data, as in
+/// `WITH d AS (DELETE ... RETURNING *) SELECT ...`. The rows it changes are
+/// not counted in the statement's affected rows.
+pub fn has_data_modifying_cte(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    tokens.iter().enumerate().any(|(i, t)| {
+        t.depth > 0
+            && DML_KEYWORDS.iter().any(|k| t.is_keyword(k))
+            && starts_statement(&tokens, i)
+    })
+}
+
+/// Whether the token at `index` begins a statement rather than appearing
+/// within one, as in `ON DELETE` or `FOR UPDATE`.
+fn starts_statement(tokens: &[Token<'_>], index: usize) -> bool {
+    let token = &tokens[index];
+    let Some(prev) = index.checked_sub(1).map(|p| &tokens[p]) else {
+        return true;
+    };
+    if prev.kind == TokenKind::Semicolon {
+        return true;
+    }
+    // The body of a common table expression: `AS (DELETE ...)`
+    if token.depth == prev.depth + 1 && (prev.is_keyword("AS") || prev.is_keyword("MATERIALIZED")) {
+        return true;
+    }
+    // The main statement of a WITH, after the last common table expression
+    prev.depth > token.depth
+        && tokens[..index]
+            .iter()
+            .rev()
+            .take_while(|t| t.depth >= token.depth && t.kind != TokenKind::Semicolon)
+            .any(|t| t.depth == token.depth && t.is_keyword("WITH"))
+}
+
+/// Index of the `;` or unmatched `)` ending the statement that starts at
+/// `from`, or the end of input.
+fn statement_end(bytes: &[u8], from: usize) -> usize {
+    let mut depth = 0usize;
+    let mut i = from;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' | b';' if depth == 0 => return i,
+            b')' => depth -= 1,
+            _ => {
+                if let Some(next) = skip_literal(bytes, i) {
+                    i = next;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Tables named after FROM, JOIN, INTO, UPDATE, USING or TABLE, in order of
+/// first appearance. Derived tables and CTE names are included if they follow
+/// one of these keywords; callers treat the result as a hint.
+pub fn referenced_tables(sql: &str) -> Vec<String> {
+    let tokens = tokenize(sql);
+    let mut tables: Vec<String> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let introduces_table = ["FROM", "JOIN", "INTO", "UPDATE", "USING", "TABLE"]
+            .iter()
+            .any(|k| token.is_keyword(k));
+        if !introduces_table {
+            continue;
+        }
+        let Some(next) = tokens.get(i + 1) else { continue };
+        // `FROM (SELECT ...)` starts a subquery, not a table name
+        if next.depth != token.depth || sql[token.end..next.start].contains('(') {
+            continue;
+        }
+        if next.kind == TokenKind::Word && is_reserved(next.text) {
+            continue;
+        }
+        if let Some((name, _)) = qualified_name(sql, &tokens, i + 1) {
+            if !tables.iter().any(|t| t.eq_ignore_ascii_case(&name)) {
+                tables.push(name);
+            }
+        }
+    }
+    tables
+}
+
+/// The leading keyword of a statement, uppercased (e.g. `UPDATE`).
+pub fn statement_keyword(sql: &str) -> Option<String> {
+    tokenize(sql)
+        .into_iter()
+        .find(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text.to_uppercase())
+}
+
+/// Keywords that can directly follow FROM/INTO/UPDATE/TABLE without naming a table.
+fn is_reserved(word: &str) -> bool {
+    ["SELECT", "LATERAL", "ONLY", "OR", "IGNORE", "LOW_PRIORITY", "IF", "EXISTS", "NOT", "SET"]
+        .iter()
+        .any(|k| word.eq_ignore_ascii_case(k))
+}
+
+/// The kind of data-modifying statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl WriteKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WriteKind::Insert => "INSERT",
+            WriteKind::Update => "UPDATE",
+            WriteKind::Delete => "DELETE",
+        }
+    }
+}
+
+/// What an INSERT/UPDATE/DELETE statement modifies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteTarget {
+    pub kind: WriteKind,
+    /// Target table, schema-qualified if the statement qualified it.
+    pub table: String,
+    /// Alias given to the table (`UPDATE users u`), as written.
+    pub alias: Option<String>,
+    /// Text of the top-level WHERE condition, if any.
+    pub where_clause: Option<String>,
+    /// Other tables the statement joins to: the list after an UPDATE's
+    /// `FROM` or a DELETE's `USING`.
+    pub from_clause: Option<String>,
+}
+
+impl WriteTarget {
+    /// A WHERE clause, with a leading space, selecting the rows of the target
+    /// table the statement affects: its own condition, or with joined tables
+    /// an EXISTS over them. Empty when every row is affected.
+    pub fn row_filter(&self) -> String {
+        match (&self.from_clause, &self.where_clause) {
+            (Some(from), Some(w)) => format!(" WHERE EXISTS (SELECT 1 FROM {from} WHERE {w})"),
+            (Some(from), None) => format!(" WHERE EXISTS (SELECT 1 FROM {from})"),
+            (None, Some(w)) => format!(" WHERE {w}"),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// Identify the statement kind, target table and WHERE condition of a single
+/// INSERT, UPDATE or DELETE. Returns `None` for anything else.
+pub fn parse_write(sql: &str) -> Option<WriteTarget> {
+    let tokens = tokenize(sql);
+    let first = tokens.first()?;
+
+    let (kind, after) = if first.is_keyword("INSERT") || first.is_keyword("REPLACE") {
+        // INSERT [OR action] INTO table
+        let into = tokens.iter().position(|t| t.is_keyword("INTO"))?;
+        (WriteKind::Insert, into + 1)
+    } else if first.is_keyword("UPDATE") {
+        // UPDATE [OR action] table
+        let skip = if tokens.get(1).is_some_and(|t| t.is_keyword("OR")) { 3 } else { 1 };
+        (WriteKind::Update, skip)
+    } else if first.is_keyword("DELETE") {
+        let from = tokens.iter().position(|t| t.is_keyword("FROM"))?;
+        (WriteKind::Delete, from + 1)
+    } else {
+        return None;
+    };
+
+    let (table, table_end) = qualified_name(sql, &tokens, after)?;
+    let alias = table_alias(sql, &tokens, table_end);
+
+    // The text after the top-level `keyword`, up to the next of `ends`
+    let clause = |keyword: &str, ends: &[&str]| {
+        let p = tokens[table_end..]
+            .iter()
+            .position(|t| t.depth == 0 && t.is_keyword(keyword))?
+            + table_end;
+        let start = tokens[p].end;
+        let end = tokens[p + 1..]
+            .iter()
+            .find(|t| t.depth == 0 && ends.iter().any(|k| t.is_keyword(k)))
+            .map(|t| t.start)
+            .unwrap_or(sql.len());
+        Some(sql[start..end].trim().trim_end_matches(';').trim().to_string())
+    };
+    let (where_clause, from_clause) = match kind {
+        WriteKind::Insert => (None, None),
+        WriteKind::Update | WriteKind::Delete => {
+            let joined = if kind == WriteKind::Update { "FROM" } else { "USING" };
+            (
+                clause("WHERE", &["RETURNING", "ORDER", "LIMIT"]),
+                clause(joined, &["WHERE", "RETURNING", "ORDER", "LIMIT"]),
+            )
+        }
+    };
+
+    Some(WriteTarget {
+        kind,
+        table,
+        alias,
+        where_clause,
+        from_clause,
+    })
+}
+
+/// The alias following a write's target table at token `index`, with or
+/// without `AS`.
+fn table_alias(sql: &str, tokens: &[Token<'_>], index: usize) -> Option<String> {
+    const CLAUSES: &[&str] = &[
+        "SET", "WHERE", "USING", "RETURNING", "ORDER", "LIMIT", "VALUES", "SELECT", "DEFAULT",
+        "ON", "INDEXED", "NOT", "PARTITION",
+    ];
+    let index = match tokens.get(index) {
+        Some(t) if t.is_keyword("AS") => index + 1,
+        _ => index,
+    };
+    let token = tokens
+        .get(index)
+        .filter(|t| t.depth == 0 && t.kind != TokenKind::Semicolon)
+        .filter(|t| !CLAUSES.iter().any(|k| t.is_keyword(k)))?;
+    Some(sql[token.start..token.end].to_string())
+}
+
+/// Read a possibly dotted name starting at token `index`. Returns the name and
+/// the index of the first token after it.
+fn qualified_name(sql: &str, tokens: &[Token<'_>], index: usize) -> Option<(String, usize)> {
+    let first = tokens.get(index).filter(|t| t.kind != TokenKind::Semicolon)?;
+    let mut parts = vec![first.text];
+    let mut i = index;
+    while let Some(next) = tokens.get(i + 1) {
+        if next.kind == TokenKind::Semicolon || sql[tokens[i].end..next.start].trim() != "." {
+            break;
+        }
+        parts.push(next.text);
+        i += 1;
+    }
+    Some((parts.join("."), i + 1))
+}
+
+/// A CHECK constraint written in a CREATE TABLE statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckClause {
+    /// The name given with `CONSTRAINT name`, if any.
+    pub name: Option<String>,
+    /// The column whose definition holds the check, for column constraints.
+    pub column: Option<String>,
+    /// The checked expression, without the surrounding parentheses.
+    pub expression: String,
+}
+
+/// CHECK constraints in a CREATE TABLE statement, in the order written.
+/// SQLite keeps no catalog of them, only the statement's text.
+pub fn check_constraints(create_table: &str) -> Vec<CheckClause> {
+    let bytes = create_table.as_bytes();
+    let Some(open) = first_paren(bytes, 0) else {
+        return Vec::new();
+    };
+    let Some(close) = matching_paren(bytes, open) else {
+        return Vec::new();
+    };
+
+    let mut checks = Vec::new();
+    for item in split_top_level(&create_table[open + 1..close]) {
+        let tokens = tokenize(item);
+        let is_column = tokens.first().is_some_and(|t| {
+            t.kind == TokenKind::Quoted
+                || !["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+                    .iter()
+                    .any(|k| t.is_keyword(k))
+        });
+        for (i, token) in tokens.iter().enumerate() {
+            if !token.is_keyword("CHECK") || token.depth != 0 {
+                continue;
+            }
+            let item_bytes = item.as_bytes();
+            let Some(start) = first_paren(item_bytes, token.end) else {
+                continue;
+            };
+            let Some(end) = matching_paren(item_bytes, start) else {
+                continue;
+            };
+            let name = (i >= 2 && tokens[i - 2].is_keyword("CONSTRAINT")).then(|| tokens[i - 1].text.to_string());
+            checks.push(CheckClause {
+                name,
+                column: is_column.then(|| tokens[0].text.to_string()),
+                expression: item[start + 1..end].trim().to_string(),
+            });
+        }
+    }
+    checks
+}
+
+/// When and on what a trigger fires, from its CREATE TRIGGER statement:
+/// `("AFTER", "UPDATE")`. SQLite keeps no catalog of triggers beyond the
+/// statement's text, and fires `BEFORE` when no timing is written.
+pub fn trigger_timing(create_trigger: &str) -> Option<(&'static str, &'static str)> {
+    let tokens = tokenize(create_trigger);
+    let start = tokens.iter().position(|t| t.is_keyword("TRIGGER"))?;
+    let mut timing = "BEFORE";
+    for token in &tokens[start + 1..] {
+        if token.is_keyword("AFTER") {
+            timing = "AFTER";
+        } else if token.is_keyword("INSTEAD") {
+            timing = "INSTEAD OF";
+        } else if let Some(event) = ["INSERT", "UPDATE", "DELETE"].into_iter().find(|e| token.is_keyword(e)) {
+            return Some((timing, event));
+        }
+    }
+    None
+}
+
+/// Split the body of a parenthesized list at its top-level commas.
+fn split_top_level(body: &str) -> Vec<&str> {
+    let bytes = body.as_bytes();
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => i = matching_paren(bytes, i).map_or(bytes.len(), |close| close + 1),
+            b',' => {
+                items.push(body[start..i].trim());
+                start = i + 1;
+                i += 1;
+            }
+            _ => i = skip_literal(bytes, i).unwrap_or(i + 1),
+        }
+    }
+    items.push(body[start..].trim());
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// Index of the first `(` at or after `from` outside literals and comments.
+fn first_paren(bytes: &[u8], from: usize) -> Option<usize> {
+    let mut i = from;
+    while i < bytes.len() {
+        if bytes[i] == b'(' {
+            return Some(i);
+        }
+        i = skip_literal(bytes, i).unwrap_or(i + 1);
+    }
+    None
+}
+
+/// Index of the `)` closing the `(` at `open`.
+fn matching_paren(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {
+                if let Some(next) = skip_literal(bytes, i) {
+                    i = next;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// If a string literal, quoted identifier or comment starts at `i`, the
+/// index just past it.
+fn skip_literal(bytes: &[u8], i: usize) -> Option<usize> {
+    match bytes[i] {
+        c @ (b'\'' | b'"' | b'`') => Some(skip_past(bytes, i + 1, c)),
+        b'[' => Some(skip_past(bytes, i + 1, b']')),
+        b'-' if bytes.get(i + 1) == Some(&b'-') => Some(skip_past(bytes, i + 2, b'\n')),
+        b'/' if bytes.get(i + 1) == Some(&b'*') => Some(
+            bytes[i + 2..]
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(bytes.len(), |p| i + 2 + p + 2),
+        ),
+        _ => None,
+    }
+}
+
+fn is_word_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+/// Index just past the next `delim` at or after `from`, or the end of input.
+fn skip_past(bytes: &[u8], from: usize, delim: u8) -> usize {
+    bytes[from.min(bytes.len())..]
+        .iter()
+        .position(|&b| b == delim)
+        .map(|p| from + p + 1)
+        .unwrap_or(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_keyword() {
+        assert!(contains_keyword("ATTACH 'other.db' AS o", "ATTACH"));
+        assert!(contains_keyword("SELECT 1; attach 'x' as y", "ATTACH"));
+        assert!(!contains_keyword("SELECT 'attach' FROM t", "ATTACH"));
+        assert!(!contains_keyword("SELECT \"attach\" FROM t", "ATTACH"));
+        assert!(!contains_keyword("SELECT attached FROM t", "ATTACH"));
+        assert!(!contains_keyword("SELECT 1 -- attach\n", "ATTACH"));
+        assert!(!contains_keyword("SELECT /* attach */ 1", "ATTACH"));
+    }
+
+    #[test]
+    fn test_tokenize_depth_and_quotes() {
+        let tokens = tokenize("SELECT (a) FROM \"my table\"");
+        assert_eq!(tokens[1].text, "a");
+        assert_eq!(tokens[1].depth, 1);
+        assert_eq!(tokens[3].kind, TokenKind::Quoted);
+        assert_eq!(tokens[3].text, "my table");
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("SELECT 1"), vec![StatementKind::Select]);
+        assert!(StatementKind::KEYWORDS
+            .iter()
+            .all(|k| !matches!(StatementKind::from_keyword(k), StatementKind::Other(_))));
+        assert_eq!(
+            classify("select 1; drop table users;"),
+            vec![StatementKind::Select, StatementKind::Drop]
+        );
+        assert_eq!(
+            classify("WITH old AS (SELECT id FROM users) DELETE FROM users WHERE id IN (SELECT id FROM old)"),
+            vec![StatementKind::Delete]
+        );
+        assert_eq!(
+            classify("WITH cte AS (SELECT 1) SELECT * FROM cte"),
+            vec![StatementKind::Select]
+        );
+        assert_eq!(
+            classify("WITH d AS (DELETE FROM users RETURNING *) SELECT * FROM d"),
+            vec![StatementKind::Delete]
+        );
+        assert_eq!(
+            classify("WITH a AS (SELECT 1), u AS (UPDATE t SET x = 1 RETURNING x) SELECT * FROM u"),
+            vec![StatementKind::Update]
+        );
+        assert_eq!(classify("EXPLAIN SELECT * FROM users"), vec![StatementKind::Explain]);
+        assert_eq!(classify("EXPLAIN DELETE FROM users"), vec![StatementKind::Explain]);
+        assert_eq!(classify("EXPLAIN ANALYZE DELETE FROM users"), vec![StatementKind::Delete]);
+        assert_eq!(
+            classify("explain (analyze, format json) insert into t values (1)"),
+            vec![StatementKind::Insert]
+        );
+        assert_eq!(
+            classify("EXPLAIN ANALYZE WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"),
+            vec![StatementKind::Delete]
+        );
+        assert_eq!(classify("EXPLAIN ANALYZE SELECT 1"), vec![StatementKind::Select]);
+        assert_eq!(classify("SELECT ';' AS semi"), vec![StatementKind::Select]);
+        assert_eq!(
+            classify("VACUUM"),
+            vec![StatementKind::Other("VACUUM".to_string())]
+        );
+        assert!(classify("  -- nothing\n").is_empty());
+    }
+
+    #[test]
+    fn test_referenced_tables() {
+        assert_eq!(
+            referenced_tables("SELECT * FROM users u JOIN posts p ON p.user_id = u.id"),
+            vec!["users", "posts"]
+        );
+        assert_eq!(
+            referenced_tables("DELETE FROM public.posts WHERE user_id IN (SELECT id FROM users)"),
+            vec!["public.posts", "users"]
+        );
+        assert_eq!(
+            referenced_tables("SELECT * FROM (SELECT 1) AS sub"),
+            Vec::<String>::new()
+        );
+        assert_eq!(referenced_tables("UPDATE \"Users\" SET x = 1"), vec!["Users"]);
+    }
+
+    #[test]
+    fn test_trigger_timing() {
+        assert_eq!(
+            trigger_timing("CREATE TRIGGER t AFTER UPDATE OF name ON users BEGIN SELECT 1; END"),
+            Some(("AFTER", "UPDATE"))
+        );
+        assert_eq!(
+            trigger_timing("CREATE TEMP TRIGGER IF NOT EXISTS \"delete\" INSTEAD OF DELETE ON v BEGIN SELECT 1; END"),
+            Some(("INSTEAD OF", "DELETE"))
+        );
+        assert_eq!(
+            trigger_timing("create trigger log insert on posts begin insert into audit values (1); end"),
+            Some(("BEFORE", "INSERT"))
+        );
+        assert_eq!(trigger_timing("CREATE TABLE t (id INTEGER)"), None);
+    }
+
+    #[test]
+    fn test_check_constraints() {
+        let checks = check_constraints(
+            "CREATE TABLE t (\n\
+               price REAL CHECK (price > 0), -- a comment, with commas\n\
+               \"code\" TEXT CONSTRAINT code_len CHECK(length(code) IN (2, 3)),\n\
+               note TEXT DEFAULT 'check (x)',\n\
+               CONSTRAINT ordered CHECK (a < b),\n\
+               CHECK (price < 1000)\n\
+             )",
+        );
+        assert_eq!(
+            checks,
+            vec![
+                CheckClause {
+                    name: None,
+                    column: Some("price".to_string()),
+                    expression: "price > 0".to_string(),
+                },
+                CheckClause {
+                    name: Some("code_len".to_string()),
+                    column: Some("code".to_string()),
+                    expression: "length(code) IN (2, 3)".to_string(),
+                },
+                CheckClause {
+                    name: Some("ordered".to_string()),
+                    column: None,
+                    expression: "a < b".to_string(),
+                },
+                CheckClause {
+                    name: None,
+                    column: None,
+                    expression: "price < 1000".to_string(),
+                },
+            ]
+        );
+        assert!(check_constraints("CREATE TABLE t (id INTEGER)").is_empty());
+    }
+
+    #[test]
+    fn test_statement_keyword() {
+        assert_eq!(statement_keyword("  -- note\n delete from t").as_deref(), Some("DELETE"));
+        assert_eq!(statement_keyword(""), None);
+    }
+
+    #[test]
+    fn test_data_modifying_statements() {
+        assert_eq!(
+            data_modifying_statements("SELECT 1; DELETE FROM users WHERE id = 1;"),
+            vec!["DELETE FROM users WHERE id = 1"]
+        );
+        assert_eq!(
+            data_modifying_statements("WITH x AS (SELECT 1) UPDATE users SET name = 'a'"),
+            vec!["UPDATE users SET name = 'a'"]
+        );
+        assert_eq!(
+            data_modifying_statements(
+                "WITH d AS (DELETE FROM users WHERE (id) = 1 RETURNING *), \
+                 e AS MATERIALIZED (SELECT 1) SELECT * FROM d"
+            ),
+            vec!["DELETE FROM users WHERE (id) = 1 RETURNING *"]
+        );
+        assert!(data_modifying_statements(
+            "CREATE TABLE t (a INT REFERENCES u ON DELETE CASCADE); SELECT * FROM t FOR UPDATE"
+        )
+        .is_empty());
+        assert_eq!(
+            data_modifying_statements("INSERT INTO t VALUES (1) ON CONFLICT DO UPDATE SET a = 2"),
+            vec!["INSERT INTO t VALUES (1) ON CONFLICT DO UPDATE SET a = 2"]
+        );
+    }
+
+    #[test]
+    fn test_has_data_modifying_cte() {
+        assert!(has_data_modifying_cte(
+            "WITH d AS (UPDATE legacy SET users_id = 0 RETURNING *) SELECT count(*) FROM d"
+        ));
+        assert!(has_data_modifying_cte(
+            "WITH a AS (SELECT 1), b AS MATERIALIZED (INSERT INTO t VALUES (1) RETURNING *) \
+             DELETE FROM u WHERE id IN (SELECT 1 FROM b)"
+        ));
+        assert!(!has_data_modifying_cte("WITH x AS (SELECT 1) DELETE FROM users WHERE id = 1"));
+        assert!(!has_data_modifying_cte("UPDATE users SET a = (SELECT 1) WHERE id = 1"));
+        assert!(!has_data_modifying_cte("CREATE TABLE t (a INT REFERENCES u ON DELETE CASCADE)"));
+    }
+
+    #[test]
+    fn test_parse_write() {
+        let update = parse_write("UPDATE users SET role = 'admin' WHERE id = 3;").unwrap();
+        assert_eq!(update.kind, WriteKind::Update);
+        assert_eq!(update.table, "users");
+        assert_eq!(update.where_clause.as_deref(), Some("id = 3"));
+
+        let delete = parse_write("DELETE FROM public.posts WHERE user_id IN (SELECT id FROM users WHERE active = 0) RETURNING id").unwrap();
+        assert_eq!(delete.kind, WriteKind::Delete);
+        assert_eq!(delete.table, "public.posts");
+        assert_eq!(
+            delete.where_clause.as_deref(),
+            Some("user_id IN (SELECT id FROM users WHERE active = 0)")
+        );
+
+        let insert = parse_write("INSERT OR IGNORE INTO `users` (name) VALUES ('x')").unwrap();
+        assert_eq!(insert.kind, WriteKind::Insert);
+        assert_eq!(insert.table, "users");
+        assert_eq!(insert.where_clause, None);
+
+        let no_where = parse_write("UPDATE OR REPLACE users SET active = 0").unwrap();
+        assert_eq!(no_where.table, "users");
+        assert_eq!(no_where.where_clause, None);
+        assert_eq!(no_where.alias, None);
+
+        let aliased = parse_write("UPDATE users u SET name = 'x' WHERE u.id = 1").unwrap();
+        assert_eq!(aliased.table, "users");
+        assert_eq!(aliased.alias.as_deref(), Some("u"));
+        assert_eq!(aliased.where_clause.as_deref(), Some("u.id = 1"));
+        let aliased = parse_write("DELETE FROM users AS \"U\" WHERE \"U\".id = 1").unwrap();
+        assert_eq!(aliased.alias.as_deref(), Some("\"U\""));
+        assert_eq!(parse_write("INSERT INTO users (name) VALUES ('x')").unwrap().alias, None);
+
+        let joined = parse_write(
+            "UPDATE posts SET title = 'x' FROM users u WHERE u.id = posts.user_id RETURNING *",
+        )
+        .unwrap();
+        assert_eq!(joined.from_clause.as_deref(), Some("users u"));
+        assert_eq!(joined.where_clause.as_deref(), Some("u.id = posts.user_id"));
+        assert_eq!(
+            joined.row_filter(),
+            " WHERE EXISTS (SELECT 1 FROM users u WHERE u.id = posts.user_id)"
+        );
+        let joined = parse_write("DELETE FROM posts p USING users u, roles r;").unwrap();
+        assert_eq!(joined.alias.as_deref(), Some("p"));
+        assert_eq!(joined.from_clause.as_deref(), Some("users u, roles r"));
+        assert_eq!(joined.row_filter(), " WHERE EXISTS (SELECT 1 FROM users u, roles r)");
+        assert_eq!(update.from_clause, None);
+        assert_eq!(update.row_filter(), " WHERE id = 3");
+        assert_eq!(no_where.row_filter(), "");
+
+        assert!(parse_write("SELECT * FROM users").is_none());
+        assert!(parse_write("DROP TABLE users").is_none());
+    }
+}
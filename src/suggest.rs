@@ -0,0 +1,72 @@
+/// Most suggestions returned for one unknown name.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Candidates closest to `name`, best first. Comparison ignores case and,
+/// when only one side is schema-qualified, the schema, so `Users` finds
+/// `users` and `users` finds `public.users`.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let name = name.to_lowercase();
+    // Allow roughly one typo per three characters, and at least two
+    let max_distance = (unqualified(&name).chars().count() / 3).max(2);
+
+    let mut scored: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let distance = edit_distance(&name, &lower)
+                .min(edit_distance(unqualified(&name), unqualified(&lower)));
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+    scored.sort();
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// The last part of a dotted name: `users` for `public.users`.
+pub fn unqualified(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Levenshtein distance between two strings, by character.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("users", "users"), 0);
+        assert_eq!(edit_distance("usres", "users"), 2);
+        assert_eq!(edit_distance("user", "users"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest() {
+        let tables = ["users", "posts", "user_roles", "public.orders"];
+        assert_eq!(closest("user", tables), vec!["users"]);
+        assert_eq!(closest("USERS", tables), vec!["users"]);
+        assert_eq!(closest("orders", tables), vec!["public.orders"]);
+        assert_eq!(closest("sales.orders", tables), vec!["public.orders"]);
+        assert!(closest("invoices", tables).is_empty());
+    }
+}
@@ -50,3 +50,13 @@ pub async fn setup_test_schema(pool: &AnyPool) {
         .await
         .expect("Failed to insert post");
 }
+
+#[allow(dead_code)]
+pub fn test_entry(pool: AnyPool) -> mcp_sql::db::DatabaseEntry {
+    mcp_sql::db::DatabaseEntry {
+        name: "test".to_string(),
+        pool,
+        backend: mcp_sql::db::DbBackend::Sqlite,
+        url_redacted: "sqlite::memory:".to_string(),
+    }
+}
@@ -1,3 +1,6 @@
+use mcp_sql::annotations::Annotations;
+use mcp_sql::db::ident::QualifiedName;
+use mcp_sql::schema::DiagramScope;
 use serde_json::Value;
 #[allow(unused_imports)]
 use sqlx::Row;
@@ -12,7 +15,7 @@ async fn test_list_tables() {
     let pool = create_test_pool().await;
     setup_test_schema(&pool).await;
 
-    let tables = mcp_sql::db::dialect::list_tables(&pool, mcp_sql::db::DbBackend::Sqlite)
+    let tables = mcp_sql::db::dialect::list_tables(&pool, mcp_sql::db::DbBackend::Sqlite, None)
         .await
         .unwrap();
 
@@ -48,7 +51,7 @@ async fn test_describe_table() {
     let columns = mcp_sql::db::dialect::describe_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
     )
     .await
     .unwrap();
@@ -80,7 +83,7 @@ async fn test_describe_nonexistent_table() {
     let result = mcp_sql::db::dialect::describe_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "nonexistent",
+        &QualifiedName::new(None, "nonexistent"),
     )
     .await;
 
@@ -137,7 +140,7 @@ async fn test_query_null_handling() {
 #[tokio::test]
 async fn test_database_manager_single_db() {
     sqlx::any::install_default_drivers();
-    let db = mcp_sql::db::DatabaseManager::new(&["sqlite::memory:".to_string()])
+    let db = mcp_sql::db::DatabaseManager::new(&["sqlite::memory:".to_string()], false)
         .await
         .unwrap();
 
@@ -157,7 +160,7 @@ async fn test_database_manager_multiple_dbs() {
     let db = mcp_sql::db::DatabaseManager::new(&[
         "sqlite::memory:".to_string(),
         "sqlite::memory:".to_string(),
-    ])
+    ], false)
     .await
     .unwrap();
 
@@ -168,7 +171,7 @@ async fn test_database_manager_multiple_dbs() {
 #[tokio::test]
 async fn test_database_manager_not_found() {
     sqlx::any::install_default_drivers();
-    let db = mcp_sql::db::DatabaseManager::new(&["sqlite::memory:".to_string()])
+    let db = mcp_sql::db::DatabaseManager::new(&["sqlite::memory:".to_string()], false)
         .await
         .unwrap();
 
@@ -198,7 +201,7 @@ async fn test_sample_data() {
     let rows = mcp_sql::db::dialect::sample_data(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
         5,
     )
     .await
@@ -223,7 +226,7 @@ async fn test_sample_data_with_limit() {
     let rows = mcp_sql::db::dialect::sample_data(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
         1,
     )
     .await
@@ -241,7 +244,7 @@ async fn test_sample_data_invalid_table() {
     let result = mcp_sql::db::dialect::sample_data(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "nonexistent",
+        &QualifiedName::new(None, "nonexistent"),
         5,
     )
     .await;
@@ -259,7 +262,7 @@ async fn test_describe_table_foreign_keys() {
     let columns = mcp_sql::db::dialect::describe_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "posts",
+        &QualifiedName::new(None, "posts"),
     )
     .await
     .unwrap();
@@ -296,7 +299,7 @@ async fn test_describe_table_no_foreign_keys() {
     let columns = mcp_sql::db::dialect::describe_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
     )
     .await
     .unwrap();
@@ -364,7 +367,7 @@ async fn test_show_create_table() {
     let ddl = mcp_sql::db::dialect::show_create_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
     )
     .await
     .unwrap();
@@ -383,7 +386,7 @@ async fn test_show_create_table_not_found() {
     let result = mcp_sql::db::dialect::show_create_table(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "nonexistent",
+        &QualifiedName::new(None, "nonexistent"),
     )
     .await;
 
@@ -445,6 +448,8 @@ async fn test_show_schema_mermaid() {
     let diagram = mcp_sql::schema::generate_mermaid_er(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
+        &Annotations::default(),
+        &DiagramScope::default(),
     )
     .await
     .unwrap();
@@ -455,7 +460,65 @@ async fn test_show_schema_mermaid() {
     assert!(diagram.contains("PK"), "should mark primary keys");
     assert!(diagram.contains("FK"), "should mark foreign keys");
     // FK relationship line
-    assert!(diagram.contains("||--o{"), "should contain relationship lines");
+    assert!(diagram.contains("users ||--o{ posts : \"user_id\""), "should label relationships");
+
+    sqlx::query(
+        "CREATE TABLE profiles (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER UNIQUE REFERENCES users(id),
+            editor_id INTEGER REFERENCES users(id)
+        )",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let diagram = mcp_sql::schema::generate_mermaid_er(
+        &pool,
+        mcp_sql::db::DbBackend::Sqlite,
+        &Annotations::default(),
+        &DiagramScope::default(),
+    )
+    .await
+    .unwrap();
+    // One edge per foreign key, optional and one-to-one where the columns say so
+    assert!(diagram.contains("users |o--o| profiles : \"user_id\""));
+    assert!(diagram.contains("users |o--o{ profiles : \"editor_id\""));
+
+    // A column in two keys, one of them composite, keeps both edges
+    sqlx::query(
+        "CREATE TABLE periods (id INTEGER, year INTEGER, PRIMARY KEY (id, year));
+         CREATE TABLE totals (
+            period_id INTEGER NOT NULL REFERENCES users(id),
+            period_year INTEGER NOT NULL,
+            FOREIGN KEY (period_id, period_year) REFERENCES periods(id, year)
+         )",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let tables = mcp_sql::db::catalog::load(&pool, backend, None).await.unwrap();
+    let totals = tables.iter().find(|t| t.name == "totals").unwrap();
+    assert_eq!(totals.foreign_keys.len(), 2);
+    let diagram = mcp_sql::schema::generate_mermaid_er(
+        &pool,
+        backend,
+        &Annotations::default(),
+        &DiagramScope::default(),
+    )
+    .await
+    .unwrap();
+    assert!(diagram.contains("users ||--o{ totals : \"period_id\""));
+    assert!(diagram.contains("periods ||--o{ totals : \"period_id, period_year\""));
+
+    let scope = DiagramScope::default();
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let format = mcp_sql::schema::DiagramFormat::Dbml;
+    let dbml = mcp_sql::schema::generate_er(&pool, backend, &Annotations::default(), &scope, format)
+        .await
+        .unwrap();
+    assert!(dbml.contains("Table users {\n  id INTEGER [pk]\n"));
+    assert!(dbml.contains("Ref: posts.user_id > users.id"));
 }
 
 #[tokio::test]
@@ -467,6 +530,8 @@ async fn test_show_schema_empty_database() {
     let diagram = mcp_sql::schema::generate_mermaid_er(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
+        &Annotations::default(),
+        &DiagramScope::default(),
     )
     .await
     .unwrap();
@@ -489,7 +554,7 @@ async fn test_list_indexes() {
     let indexes = mcp_sql::db::dialect::list_indexes(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
     )
     .await
     .unwrap();
@@ -518,7 +583,7 @@ async fn test_list_indexes_empty() {
     let indexes = mcp_sql::db::dialect::list_indexes(
         &pool,
         mcp_sql::db::DbBackend::Sqlite,
-        "users",
+        &QualifiedName::new(None, "users"),
     )
     .await
     .unwrap();
@@ -538,11 +603,13 @@ async fn test_query_dry_run_valid() {
     let pool = create_test_pool().await;
     setup_test_schema(&pool).await;
 
+    let entry = test_entry(pool);
     let explain_sql = format!(
         "{}SELECT * FROM users WHERE name = 'Alice'",
         mcp_sql::db::dialect::explain_prefix(mcp_sql::db::DbBackend::Sqlite),
     );
-    let rows = sqlx::query(&explain_sql).fetch_all(&pool).await;
+    let timeout = std::time::Duration::from_secs(5);
+    let rows = mcp_sql::db::exec::fetch_all(&entry, &explain_sql, timeout, true).await;
     assert!(rows.is_ok(), "valid SQL should produce a query plan");
 }
 
@@ -552,10 +619,1000 @@ async fn test_query_dry_run_invalid() {
     let pool = create_test_pool().await;
     setup_test_schema(&pool).await;
 
+    let entry = test_entry(pool);
     let explain_sql = format!(
         "{}SELECT * FROM nonexistent_table",
         mcp_sql::db::dialect::explain_prefix(mcp_sql::db::DbBackend::Sqlite),
     );
-    let result = sqlx::query(&explain_sql).fetch_all(&pool).await;
+    let timeout = std::time::Duration::from_secs(5);
+    let result = mcp_sql::db::exec::fetch_all(&entry, &explain_sql, timeout, true).await;
     assert!(result.is_err(), "invalid SQL should produce an error");
 }
+
+#[tokio::test]
+async fn test_query_dry_run_cannot_write() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+
+    // SQLite runs every statement in the string, so a plan must not be a way to write
+    let entry = test_entry(pool.clone());
+    let explain_sql = format!(
+        "{}SELECT 1; DELETE FROM posts",
+        mcp_sql::db::dialect::explain_prefix(mcp_sql::db::DbBackend::Sqlite),
+    );
+    let timeout = std::time::Duration::from_secs(5);
+    let result = mcp_sql::db::exec::fetch_all(&entry, &explain_sql, timeout, true).await;
+    assert!(result.is_err());
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(count.0 > 0);
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+
+    let rows = mcp_sql::db::exec::fetch_all(
+        &entry,
+        "SELECT name FROM users ORDER BY id",
+        std::time::Duration::from_secs(5),
+        false,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows.len(), 2);
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all_timeout() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    let entry = test_entry(pool);
+
+    let result = mcp_sql::db::exec::fetch_all(
+        &entry,
+        "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 100000000) \
+         SELECT COUNT(*) FROM c",
+        std::time::Duration::from_millis(100),
+        false,
+    )
+    .await;
+
+    match result {
+        Err(mcp_sql::error::McpSqlError::QueryTimeout(_, cancellation)) => {
+            assert_eq!(cancellation, mcp_sql::db::exec::Cancellation::Abandoned);
+        }
+        Err(e) => panic!("expected a timeout, got {e}"),
+        Ok(_) => panic!("expected a timeout, query succeeded"),
+    }
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all_timeout_closes_sqlite_connection() {
+    sqlx::any::install_default_drivers();
+    let path = std::env::temp_dir().join(format!("mcp-sql-timeout-{}.db", std::process::id()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let manager = mcp_sql::db::DatabaseManager::new(std::slice::from_ref(&url), false)
+        .await
+        .unwrap();
+    let entry = &manager.databases[0];
+
+    let result = mcp_sql::db::exec::fetch_all(
+        entry,
+        "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 100000000) \
+         SELECT COUNT(*) FROM c",
+        std::time::Duration::from_millis(100),
+        true,
+    )
+    .await;
+    assert!(matches!(result, Err(mcp_sql::error::McpSqlError::QueryTimeout(..))));
+
+    // The busy connection left the pool; the next statement gets a fresh one
+    assert_eq!(entry.pool.size(), 0);
+    mcp_sql::db::exec::fetch_all(
+        entry,
+        "CREATE TABLE t (id INTEGER)",
+        std::time::Duration::from_secs(5),
+        false,
+    )
+    .await
+    .unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all_timeout_restores_writable_memory_connection() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+
+    let result = mcp_sql::db::exec::fetch_all(
+        &entry,
+        "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 2000000) \
+         SELECT COUNT(*) FROM c",
+        std::time::Duration::from_millis(10),
+        true,
+    )
+    .await;
+    assert!(matches!(result, Err(mcp_sql::error::McpSqlError::QueryTimeout(..))));
+
+    // The only connection comes back once the statement ends, no longer read-only
+    mcp_sql::db::exec::fetch_all(
+        &entry,
+        "DELETE FROM posts",
+        std::time::Duration::from_secs(30),
+        false,
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all_read_only_rejects_writes() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+
+    // A write that slips past the keyword guard is still refused by SQLite
+    let result = mcp_sql::db::exec::fetch_all(
+        &entry,
+        "WITH x AS (SELECT 1) DELETE FROM users",
+        std::time::Duration::from_secs(5),
+        true,
+    )
+    .await;
+    assert!(result.is_err(), "write should fail in a read-only transaction");
+
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count.0, 2, "no rows should have been deleted");
+
+    // The connection is writable again for subsequent non-read-only use
+    sqlx::query("INSERT INTO users (name) VALUES ('Carol')")
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_exec_fetch_all_read_only_select() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+
+    let rows = mcp_sql::db::exec::fetch_all(
+        &entry,
+        "SELECT * FROM users",
+        std::time::Duration::from_secs(5),
+        true,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rows.len(), 2);
+}
+
+#[tokio::test]
+async fn test_database_manager_sqlite_read_only() {
+    sqlx::any::install_default_drivers();
+    let path = std::env::temp_dir().join(format!("mcp-sql-ro-{}.db", std::process::id()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+
+    let rw = mcp_sql::db::DatabaseManager::new(std::slice::from_ref(&url), false)
+        .await
+        .unwrap();
+    sqlx::query("CREATE TABLE t (id INTEGER)")
+        .execute(&rw.resolve(None).unwrap().pool)
+        .await
+        .unwrap();
+
+    let ro = mcp_sql::db::DatabaseManager::new(&[url], true).await.unwrap();
+    let pool = &ro.resolve(None).unwrap().pool;
+    let result = sqlx::query("INSERT INTO t VALUES (1)").execute(pool).await;
+    assert!(result.is_err(), "read-only SQLite file should reject writes");
+
+    let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM t")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(count.0, 0);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_preview_write_update() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "UPDATE users SET active = 0 WHERE name = 'Alice'",
+        std::time::Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(preview["statement"], "UPDATE");
+    assert_eq!(preview["rows_affected"], 1);
+    assert_eq!(preview["before"][0]["active"], 1);
+    assert_eq!(preview["after"][0]["active"], 0);
+    assert_eq!(preview["after"][0]["name"], "Alice");
+
+    // The change was rolled back
+    let (active,): (i64,) = sqlx::query_as("SELECT active FROM users WHERE name = 'Alice'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(active, 1);
+}
+
+#[tokio::test]
+async fn test_preview_write_delete_and_insert() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "DELETE FROM posts",
+        std::time::Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(preview["rows_affected"], 1);
+    assert_eq!(preview["before"].as_array().unwrap().len(), 1);
+    assert!(preview["after"].as_array().unwrap().is_empty());
+
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "INSERT INTO users (name, email) VALUES ('Carol', 'carol@example.com')",
+        std::time::Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(preview["rows_affected"], 1);
+    assert_eq!(preview["after"][0]["name"], "Carol");
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 2, "preview insert should be rolled back");
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1, "preview delete should be rolled back");
+}
+
+#[tokio::test]
+async fn test_preview_write_insert_without_integer_key() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    sqlx::query("CREATE TABLE codes (code TEXT PRIMARY KEY, label TEXT)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO codes VALUES ('a', 'A')").execute(&pool).await.unwrap();
+    let entry = test_entry(pool);
+
+    // No maximum key to find new rows above, but the write is still previewed
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "INSERT INTO codes VALUES ('b', 'B')",
+        std::time::Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(preview["rows_affected"], 1);
+    assert!(preview["after"].is_null());
+}
+
+#[tokio::test]
+async fn test_preview_write_aliased_table() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "UPDATE users AS u SET active = 0 WHERE u.name = 'Alice'",
+        std::time::Duration::from_secs(5),
+    )
+    .await
+    .unwrap();
+    assert_eq!(preview["rows_affected"], 1);
+    assert_eq!(preview["before"][0]["name"], "Alice");
+    assert_eq!(preview["after"][0]["active"], 0);
+}
+
+#[tokio::test]
+async fn test_preview_write_joined_tables() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+    let timeout = std::time::Duration::from_secs(5);
+
+    // The rows come from the target table only, matched through the joined ones
+    let preview = mcp_sql::db::exec::preview_write(
+        &entry,
+        "UPDATE users SET active = 0 FROM posts p WHERE p.user_id = users.id",
+        timeout,
+    )
+    .await
+    .unwrap();
+    assert_eq!(preview["rows_affected"], 1);
+    assert_eq!(preview["before"].as_array().unwrap().len(), 1);
+    assert_eq!(preview["before"][0]["name"], "Alice");
+    assert_eq!(preview["after"][0]["active"], 0);
+
+    // A mistake is reported by the statement itself, not the preview's SELECT
+    let result = mcp_sql::db::exec::preview_write(
+        &entry,
+        "UPDATE users SET active = 0 WHERE nmae = 'Bob'",
+        timeout,
+    )
+    .await;
+    assert!(matches!(result, Err(mcp_sql::error::McpSqlError::Database(_))));
+}
+
+#[tokio::test]
+async fn test_preview_write_rejects_non_dml() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+
+    let result = mcp_sql::db::exec::preview_write(
+        &entry,
+        "DROP TABLE users",
+        std::time::Duration::from_secs(5),
+    )
+    .await;
+    assert!(result.is_err());
+
+    // A second statement could commit the first and escape the rollback
+    let result = mcp_sql::db::exec::preview_write(
+        &entry,
+        "DELETE FROM posts WHERE id = 1; COMMIT; DELETE FROM users",
+        std::time::Duration::from_secs(5),
+    )
+    .await;
+    assert!(result.is_err());
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+        .fetch_one(&entry.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn test_estimate_affected_rows() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+    let timeout = std::time::Duration::from_secs(5);
+
+    let estimate = mcp_sql::db::dialect::estimate_affected_rows(
+        &entry,
+        "DELETE FROM users WHERE active = 0",
+        timeout,
+    )
+    .await;
+    assert_eq!(estimate, Some(1));
+
+    let estimate =
+        mcp_sql::db::dialect::estimate_affected_rows(&entry, "UPDATE users SET active = 1", timeout)
+            .await;
+    assert_eq!(estimate, Some(2));
+
+    let estimate = mcp_sql::db::dialect::estimate_affected_rows(
+        &entry,
+        "INSERT INTO users (name) VALUES ('x')",
+        timeout,
+    )
+    .await;
+    assert_eq!(estimate, None);
+
+    // Nothing spliced into the WHERE clause runs
+    let estimate = mcp_sql::db::dialect::estimate_affected_rows(
+        &entry,
+        "DELETE FROM users WHERE 1 = 1; DELETE FROM posts",
+        timeout,
+    )
+    .await;
+    assert_eq!(estimate, None);
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn test_fetch_all_capped() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool.clone());
+    let timeout = std::time::Duration::from_secs(5);
+
+    // Within the cap: committed
+    mcp_sql::db::exec::fetch_all_capped(&entry, "UPDATE users SET active = 1 WHERE name = 'Bob'", timeout, 1)
+        .await
+        .unwrap();
+    let (active,): (i64,) = sqlx::query_as("SELECT active FROM users WHERE name = 'Bob'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(active, 1);
+
+    // Over the cap: rolled back with the count in the error
+    let result =
+        mcp_sql::db::exec::fetch_all_capped(&entry, "UPDATE users SET active = 0", timeout, 1).await;
+    match result {
+        Err(mcp_sql::error::McpSqlError::TooManyRowsAffected(affected, max)) => {
+            assert_eq!((affected, max), (2, 1));
+        }
+        Err(e) => panic!("expected TooManyRowsAffected, got {e}"),
+        Ok(_) => panic!("expected TooManyRowsAffected, write succeeded"),
+    }
+    let (active,): (i64,) = sqlx::query_as("SELECT SUM(active) FROM users")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(active, 2, "capped update should be rolled back");
+
+    // Rows changed in a CTE are not counted, so such statements are refused
+    let cte = "WITH d AS (UPDATE users SET active = 0 RETURNING *) SELECT COUNT(*) FROM d";
+    let result = mcp_sql::db::exec::fetch_all_capped(&entry, cte, timeout, 1).await;
+    assert!(matches!(result, Err(mcp_sql::error::McpSqlError::PolicyViolation(_))));
+}
+
+#[tokio::test]
+async fn test_explain_summary() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+
+    let plan = mcp_sql::db::dialect::explain_summary(
+        &pool,
+        mcp_sql::db::DbBackend::Sqlite,
+        "SELECT * FROM users WHERE name = 'Alice'",
+    )
+    .await
+    .unwrap();
+    assert_eq!(plan.full_scans.len(), 1);
+    assert_eq!(plan.full_scans[0].table, "users");
+    // Table sizes come from statistics, not a count of every row
+    assert_eq!(plan.full_scans[0].table_rows, None);
+
+    sqlx::query("ANALYZE").execute(&pool).await.unwrap();
+    let plan = mcp_sql::db::dialect::explain_summary(
+        &pool,
+        mcp_sql::db::DbBackend::Sqlite,
+        "SELECT * FROM users WHERE name = 'Alice'",
+    )
+    .await
+    .unwrap();
+    assert_eq!(plan.full_scans[0].table_rows, Some(2));
+
+    // Primary key lookups use an index
+    let plan = mcp_sql::db::dialect::explain_summary(
+        &pool,
+        mcp_sql::db::DbBackend::Sqlite,
+        "SELECT * FROM users WHERE id = 1",
+    )
+    .await
+    .unwrap();
+    assert!(plan.full_scans.is_empty());
+}
+
+#[tokio::test]
+async fn test_error_details() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let entry = test_entry(pool);
+    let timeout = std::time::Duration::from_secs(5);
+
+    let sql = "SELECT nme FROM users";
+    let err = mcp_sql::db::exec::fetch_all(&entry, sql, timeout, true)
+        .await
+        .err()
+        .unwrap();
+    let details = serde_json::to_value(err.details(Some(sql))).unwrap();
+    assert_eq!(details["category"], "undefined_column");
+    assert_eq!(details["object"], "nme");
+    assert_eq!(details["position"], 8);
+    assert!(details["hint"].as_str().unwrap().contains("describe_table"));
+
+    let sql = "SELECT * FORM users";
+    let err = mcp_sql::db::exec::fetch_all(&entry, sql, timeout, true)
+        .await
+        .err()
+        .unwrap();
+    let details = serde_json::to_value(err.details(Some(sql))).unwrap();
+    assert_eq!(details["category"], "syntax_error");
+    assert_eq!(details["position"], 10);
+
+    // The MCP error carries the same details as its data
+    let mcp = err.to_mcp_error_for_sql(sql);
+    assert_eq!(mcp.data.unwrap()["category"], "syntax_error");
+
+    // Behind EXPLAIN, the position is still within the statement
+    let sql = "SELECT nme FROM users";
+    let prefix = mcp_sql::db::dialect::explain_prefix(mcp_sql::db::DbBackend::Sqlite);
+    let err = mcp_sql::db::exec::fetch_all(&entry, &format!("{prefix}{sql}"), timeout, true)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.details_for_prefixed_sql(prefix, sql).position, Some(8));
+}
+
+#[tokio::test]
+async fn test_unknown_table_suggestions() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+
+    let err = mcp_sql::db::dialect::describe_table(&pool, mcp_sql::db::DbBackend::Sqlite, &QualifiedName::new(None, "userz"))
+        .await
+        .unwrap_err();
+    let details = err.details(None);
+    assert_eq!(details.category, "undefined_table");
+    assert_eq!(details.object.as_deref(), Some("userz"));
+
+    let tables = mcp_sql::db::dialect::table_names(&pool, mcp_sql::db::DbBackend::Sqlite)
+        .await
+        .unwrap();
+    assert_eq!(tables, vec!["posts", "users"]);
+    assert_eq!(
+        mcp_sql::suggest::closest("userz", tables.iter().map(String::as_str)),
+        vec!["users"]
+    );
+    assert_eq!(
+        mcp_sql::suggest::closest("POSTS", tables.iter().map(String::as_str)),
+        vec!["posts"]
+    );
+}
+
+#[tokio::test]
+async fn test_schema_qualified_names() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+
+    let schemas = mcp_sql::db::dialect::list_schemas(&pool, backend).await.unwrap();
+    let main = schemas
+        .iter()
+        .find(|s| s["schema_name"] == "main")
+        .expect("main schema should be listed");
+    assert_eq!(main["table_count"], 2);
+    assert_eq!(main["is_default"], true);
+
+    let users = QualifiedName::resolve("users", Some("main")).unwrap();
+    let columns = mcp_sql::db::dialect::describe_table(&pool, backend, &users).await.unwrap();
+    assert_eq!(columns.len(), 4);
+    let rows = mcp_sql::db::dialect::sample_data(&pool, backend, &users, 5).await.unwrap();
+    assert_eq!(rows.len(), 2);
+    let ddl = mcp_sql::db::dialect::show_create_table(&pool, backend, &users).await.unwrap();
+    assert!(ddl.contains("CREATE TABLE"));
+    assert!(mcp_sql::db::dialect::list_indexes(&pool, backend, &users).await.is_ok());
+
+    let tables = mcp_sql::db::dialect::list_tables(&pool, backend, Some("main")).await.unwrap();
+    assert_eq!(tables.len(), 2);
+
+    let missing = QualifiedName::resolve("users", Some("other")).unwrap();
+    assert!(mcp_sql::db::dialect::describe_table(&pool, backend, &missing).await.is_err());
+}
+
+#[tokio::test]
+async fn test_views() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    sqlx::query("CREATE VIEW active_users AS SELECT id, name FROM users WHERE active = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let tables = mcp_sql::db::dialect::list_tables(&pool, backend, None).await.unwrap();
+    let view = tables
+        .iter()
+        .find(|t| t["table_name"] == "active_users")
+        .expect("view should be listed");
+    assert_eq!(view["kind"], "view");
+    let users = tables.iter().find(|t| t["table_name"] == "users").unwrap();
+    assert_eq!(users["kind"], "table");
+
+    let name = QualifiedName::new(None, "active_users");
+    let columns = mcp_sql::db::dialect::describe_table(&pool, backend, &name).await.unwrap();
+    assert_eq!(columns.len(), 2);
+
+    let ddl = mcp_sql::db::dialect::show_create_table(&pool, backend, &name).await.unwrap();
+    assert!(ddl.starts_with("CREATE VIEW active_users"));
+
+    // Views stay out of the ER diagram
+    let diagram = mcp_sql::schema::generate_mermaid_er(
+        &pool,
+        backend,
+        &Annotations::default(),
+        &DiagramScope::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!diagram.contains("active_users"));
+}
+
+#[tokio::test]
+async fn test_list_constraints() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    for sql in [
+        "CREATE TABLE orders (id INTEGER, year INTEGER, code TEXT UNIQUE, PRIMARY KEY (id, year))",
+        "CREATE TABLE order_lines (
+            id INTEGER PRIMARY KEY,
+            order_id INTEGER,
+            order_year INTEGER,
+            qty INTEGER CHECK (qty > 0),
+            CONSTRAINT line_order FOREIGN KEY (order_id, order_year)
+                REFERENCES orders (id, year) ON DELETE CASCADE
+        )",
+    ] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+
+    let name = QualifiedName::new(None, "order_lines");
+    let constraints = mcp_sql::db::constraints::list_constraints(&pool, backend, &name)
+        .await
+        .unwrap();
+    let kinds: Vec<&str> = constraints.iter().map(|c| c.kind).collect();
+    assert_eq!(kinds, vec!["primary_key", "check", "foreign_key"]);
+
+    let check = &constraints[1];
+    assert_eq!(check.columns, vec!["qty"]);
+    assert_eq!(check.expression.as_deref(), Some("qty > 0"));
+
+    let fk = &constraints[2];
+    assert_eq!(fk.columns, vec!["order_id", "order_year"]);
+    assert_eq!(fk.referenced_table.as_deref(), Some("orders"));
+    assert_eq!(fk.referenced_columns, vec!["id", "year"]);
+    assert_eq!(fk.on_delete.as_deref(), Some("CASCADE"));
+    assert_eq!(fk.on_update.as_deref(), Some("NO ACTION"));
+
+    let orders = mcp_sql::db::constraints::list_constraints(
+        &pool,
+        backend,
+        &QualifiedName::new(None, "orders"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(orders[0].columns, vec!["id", "year"]);
+    assert_eq!(orders[1].kind, "unique");
+    assert_eq!(orders[1].columns, vec!["code"]);
+
+    // Each column of a composite key points at its own referenced column
+    let columns = mcp_sql::db::dialect::describe_table(&pool, backend, &name).await.unwrap();
+    let fk_of = |column: &str| {
+        columns.iter().find(|c| c["name"] == column).unwrap()["foreign_key"].clone()
+    };
+    assert_eq!(fk_of("order_id"), "orders.id");
+    assert_eq!(fk_of("order_year"), "orders.year");
+    assert!(fk_of("qty").is_null());
+
+    let missing = QualifiedName::new(None, "nope");
+    assert!(mcp_sql::db::constraints::list_constraints(&pool, backend, &missing)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_annotation_comments() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let annotations = Annotations::parse(
+        "tables:
+  users:
+    description: People who can log in
+    columns:
+      active: \"0 once the account is \\\"closed\\\"\"
+",
+    )
+    .unwrap();
+
+    // SQLite itself has no comments
+    let mut tables = mcp_sql::db::dialect::list_tables(&pool, backend, None).await.unwrap();
+    assert!(tables.iter().all(|t| t["comment"].is_null()));
+    annotations.apply_to_tables(&mut tables);
+    let users = tables.iter().find(|t| t["table_name"] == "users").unwrap();
+    assert_eq!(users["comment"], "People who can log in");
+
+    let name = QualifiedName::new(None, "users");
+    let mut columns = mcp_sql::db::dialect::describe_table(&pool, backend, &name).await.unwrap();
+    annotations.apply_to_columns("users", &mut columns);
+    let active = columns.iter().find(|c| c["name"] == "active").unwrap();
+    assert_eq!(active["comment"], "0 once the account is \"closed\"");
+    assert!(mcp_sql::db::dialect::table_comment(&pool, backend, &name).await.unwrap().is_none());
+
+    let diagram =
+        mcp_sql::schema::generate_mermaid_er(&pool, backend, &annotations, &DiagramScope::default())
+            .await
+            .unwrap();
+    assert!(diagram.contains("%% users: People who can log in"));
+    assert!(diagram.contains("INTEGER active \"0 once the account is 'closed'\""));
+}
+
+#[tokio::test]
+async fn test_annotation_values() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let annotations = Annotations::parse(
+        "tables:
+  users:
+    gotchas:
+      - Deactivated users keep their posts
+    columns:
+      active:
+        description: Account state
+        values:
+          0: closed
+          1: open
+",
+    )
+    .unwrap();
+
+    let name = QualifiedName::new(None, "users");
+    let mut columns = mcp_sql::db::dialect::describe_table(&pool, backend, &name).await.unwrap();
+    annotations.apply_to_columns("users", &mut columns);
+    let active = columns.iter().find(|c| c["name"] == "active").unwrap();
+    assert_eq!(active["comment"], "Account state");
+    assert_eq!(active["values"]["0"], "closed");
+    assert_eq!(
+        annotations.table_notes("users", None),
+        vec!["Gotcha: Deactivated users keep their posts"]
+    );
+
+    let diagram =
+        mcp_sql::schema::generate_mermaid_er(&pool, backend, &annotations, &DiagramScope::default())
+            .await
+            .unwrap();
+    assert!(diagram.contains("INTEGER active \"Account state (0 = closed, 1 = open)\""));
+
+    let hits = annotations.search("closed");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, "value");
+    assert_eq!(hits[0].column.as_deref(), Some("active"));
+}
+
+#[tokio::test]
+async fn test_routines_and_triggers() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    sqlx::query(
+        "CREATE TRIGGER posts_audit AFTER UPDATE OF title ON posts \
+         BEGIN SELECT 1; END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let triggers = mcp_sql::db::dialect::list_triggers(&pool, backend, None, None).await.unwrap();
+    assert_eq!(triggers.len(), 1);
+    assert_eq!(triggers[0]["trigger_name"], "posts_audit");
+    assert_eq!(triggers[0]["table_name"], "posts");
+    assert_eq!(triggers[0]["timing"], "AFTER");
+    assert_eq!(triggers[0]["event"], "UPDATE");
+    assert!(mcp_sql::db::dialect::list_triggers(&pool, backend, None, Some("users"))
+        .await
+        .unwrap()
+        .is_empty());
+
+    let definition = mcp_sql::db::dialect::show_trigger(&pool, backend, &QualifiedName::new(None, "posts_audit"))
+        .await
+        .unwrap();
+    assert!(definition.starts_with("CREATE TRIGGER posts_audit"));
+    assert!(mcp_sql::db::dialect::show_trigger(&pool, backend, &QualifiedName::new(None, "nope"))
+        .await
+        .is_err());
+
+    // SQLite has no stored routines
+    assert!(mcp_sql::db::dialect::list_routines(&pool, backend, None).await.unwrap().is_empty());
+    assert!(mcp_sql::db::dialect::show_routine(&pool, backend, &QualifiedName::new(None, "f"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_search_schema() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let tables = mcp_sql::db::catalog::load(&pool, backend, None).await.unwrap();
+
+    let annotations = Annotations::parse("tables:\n  posts:\n    columns:\n      body: Markdown text\n").unwrap();
+    let search = |term: &str, mode: Option<&str>| {
+        let pattern = mcp_sql::search::Pattern::parse(term, mode).unwrap();
+        mcp_sql::search::search_catalog("test", &pattern, &tables, &annotations)
+    };
+
+    let hits = search("*_id", None);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].table, "posts");
+    assert_eq!(hits[0].column.as_deref(), Some("user_id"));
+
+    let hits = search("markdown", None);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].matched, "annotation");
+
+    let hits = search("^(name|email)$", Some("regex"));
+    let found: Vec<&str> = hits.iter().filter_map(|h| h.column.as_deref()).collect();
+    assert_eq!(found, vec!["name", "email"]);
+}
+
+#[tokio::test]
+async fn test_schema_summary() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    let mut tables = mcp_sql::db::catalog::load(&pool, backend, None).await.unwrap();
+
+    // Counting every table is a full scan, so only done when asked for
+    let users = tables.iter().find(|t| t.name == "users").unwrap();
+    assert_eq!(users.row_count, None);
+    mcp_sql::db::catalog::count_rows(&test_entry(pool.clone()), None, &mut tables).await;
+    let users = tables.iter().find(|t| t.name == "users").unwrap();
+    assert_eq!(users.row_count, Some(2));
+    let posts = tables.iter().find(|t| t.name == "posts").unwrap();
+    let user_id = posts.columns.iter().find(|c| c.name == "user_id").unwrap();
+    assert_eq!(user_id.foreign_key.as_deref(), Some("users.id"));
+    assert!(!user_id.nullable);
+
+    let summary = mcp_sql::schema::schema_summary(&tables, 8000);
+    assert!(summary.contains("users (2 rows): id INTEGER PK, name TEXT!, email TEXT, active INTEGER! =1"));
+    assert!(summary.contains("user_id INTEGER! FK>users.id"));
+}
+
+#[tokio::test]
+async fn test_show_schema_scope() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    setup_test_schema(&pool).await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    for statement in [
+        "CREATE TABLE comments (id INTEGER PRIMARY KEY, post_id INTEGER REFERENCES posts(id))",
+        "CREATE TABLE tags (id INTEGER PRIMARY KEY, label TEXT)",
+        "ATTACH DATABASE ':memory:' AS aux",
+        "CREATE TABLE aux.audit (id INTEGER PRIMARY KEY, note TEXT)",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+    let diagram = |scope: DiagramScope| {
+        let pool = pool.clone();
+        async move {
+            mcp_sql::schema::generate_mermaid_er(&pool, backend, &Annotations::default(), &scope)
+                .await
+        }
+    };
+    let declares = |diagram: &str, table: &str| diagram.contains(&format!("    {table} {{"));
+
+    // One hop from comments reaches posts but not users
+    let scope = DiagramScope { focus: vec!["comments".into()], hops: 1, ..Default::default() };
+    let one_hop = diagram(scope).await.unwrap();
+    assert!(declares(&one_hop, "comments") && declares(&one_hop, "posts"));
+    assert!(!declares(&one_hop, "users") && !declares(&one_hop, "tags"));
+    assert!(one_hop.contains("posts |o--o{ comments"));
+    assert!(!one_hop.contains("users ||--o{ posts"));
+
+    let scope = DiagramScope { focus: vec!["comments".into()], hops: 2, ..Default::default() };
+    let two_hops = diagram(scope).await.unwrap();
+    assert!(declares(&two_hops, "users") && !declares(&two_hops, "tags"));
+
+    let scope = DiagramScope { pattern: Some("*s".into()), keys_only: true, ..Default::default() };
+    let keys_only = diagram(scope).await.unwrap();
+    assert!(declares(&keys_only, "tags") && !declares(&keys_only, "audit"));
+    assert!(keys_only.contains("INTEGER user_id FK"));
+    assert!(!keys_only.contains("title"));
+
+    let scope = DiagramScope { schema: Some("aux".into()), ..Default::default() };
+    let attached = diagram(scope).await.unwrap();
+    assert!(declares(&attached, "\"aux.audit\"") && !declares(&attached, "users"));
+
+    let missing = diagram(DiagramScope { focus: vec!["nope".into()], ..Default::default() }).await;
+    assert!(matches!(missing, Err(mcp_sql::error::McpSqlError::TableNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_inferred_relationships() {
+    sqlx::any::install_default_drivers();
+    let pool = create_test_pool().await;
+    let backend = mcp_sql::db::DbBackend::Sqlite;
+    for statement in [
+        "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT)",
+        "CREATE TABLE categories (id INTEGER PRIMARY KEY)",
+        "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER, categoryId INTEGER, \
+         status_id INTEGER)",
+        "INSERT INTO customers VALUES (1, 'Ada'), (2, 'Bob')",
+        "INSERT INTO categories VALUES (1)",
+        "INSERT INTO orders VALUES (1, 1, 7, 1), (2, 2, 8, 1), (3, 9, NULL, 2)",
+    ] {
+        sqlx::query(statement).execute(&pool).await.unwrap();
+    }
+    let tables = mcp_sql::db::catalog::load(&pool, backend, None).await.unwrap();
+    let mut keys = mcp_sql::infer::infer(&tables);
+    let found: Vec<(&str, String)> =
+        keys.iter().map(|k| (k.column.as_str(), k.reference())).collect();
+    assert_eq!(
+        found,
+        vec![
+            ("customer_id", "customers.id".to_string()),
+            ("categoryId", "categories.id".to_string()),
+        ]
+    );
+
+    let columns_only = mcp_sql::db::catalog::load_columns(&pool, backend, None).await.unwrap();
+    assert_eq!(mcp_sql::infer::infer(&columns_only), keys);
+
+    // Without time left, every guess is kept unchecked
+    let entry = test_entry(pool.clone());
+    let mut unchecked = keys.clone();
+    mcp_sql::infer::verify(&entry, &mut unchecked, 100, std::time::Duration::ZERO).await;
+    assert_eq!(unchecked, keys);
+
+    // No sampled category exists, so that guess goes
+    let timeout = std::time::Duration::from_secs(5);
+    mcp_sql::infer::verify(&entry, &mut keys, 100, timeout).await;
+    assert_eq!(keys.len(), 1);
+    assert_eq!((keys[0].sampled, keys[0].matched), (Some(3), Some(2)));
+
+    let name = QualifiedName::new(None, "orders");
+    let mut columns = mcp_sql::db::dialect::describe_table(&pool, backend, &name).await.unwrap();
+    mcp_sql::infer::apply_to_columns(&keys, &name, &mut columns);
+    let customer = columns.iter().find(|c| c["name"] == "customer_id").unwrap();
+    assert_eq!(customer["inferred_foreign_key"], "customers.id");
+    assert!(columns.iter().all(|c| c["foreign_key"].is_null()));
+
+    let scope = DiagramScope { inferred: true, ..Default::default() };
+    let diagram =
+        mcp_sql::schema::generate_mermaid_er(&pool, backend, &Annotations::default(), &scope)
+            .await
+            .unwrap();
+    assert!(diagram.contains("customers |o..o{ orders : \"customer_id (inferred)\""));
+    let plain = mcp_sql::schema::generate_mermaid_er(
+        &pool,
+        backend,
+        &Annotations::default(),
+        &DiagramScope::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!plain.contains("inferred"));
+}